@@ -0,0 +1,666 @@
+//! # Bike-Scan
+//! das folgende Modul enthält die Algorithmen aus den IANA-Registern für IkeV1 und IkeV2.
+//! Die Enums werden für den Scan-Bericht verwendet,
+//! unbekannte Werte werden als Other gespeichert
+
+use serde::Serialize;
+
+///Verschlüsselungsalgorithmen für IkeV1 (RFC 2409, Anhang A)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum EncryptionAlgorithmV1 {
+    ///DES-CBC
+    DesCbc,
+    ///IDEA-CBC
+    IdeaCbc,
+    ///Blowfish-CBC
+    BlowfishCbc,
+    ///RC5-R16-B64-CBC
+    Rc5R16B64Cbc,
+    ///3DES-CBC
+    TripleDesCbc,
+    ///CAST-CBC
+    CastCbc,
+    ///AES-CBC (RFC 3602)
+    AesCbc,
+    ///Camellia-CBC (RFC 4312)
+    CamelliaCbc,
+    ///unbekannter Wert
+    Other(u16),
+}
+
+impl From<u16> for EncryptionAlgorithmV1 {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => EncryptionAlgorithmV1::DesCbc,
+            2 => EncryptionAlgorithmV1::IdeaCbc,
+            3 => EncryptionAlgorithmV1::BlowfishCbc,
+            4 => EncryptionAlgorithmV1::Rc5R16B64Cbc,
+            5 => EncryptionAlgorithmV1::TripleDesCbc,
+            6 => EncryptionAlgorithmV1::CastCbc,
+            7 => EncryptionAlgorithmV1::AesCbc,
+            8 => EncryptionAlgorithmV1::CamelliaCbc,
+            other => EncryptionAlgorithmV1::Other(other),
+        }
+    }
+}
+
+impl From<EncryptionAlgorithmV1> for u16 {
+    fn from(value: EncryptionAlgorithmV1) -> Self {
+        match value {
+            EncryptionAlgorithmV1::DesCbc => 1,
+            EncryptionAlgorithmV1::IdeaCbc => 2,
+            EncryptionAlgorithmV1::BlowfishCbc => 3,
+            EncryptionAlgorithmV1::Rc5R16B64Cbc => 4,
+            EncryptionAlgorithmV1::TripleDesCbc => 5,
+            EncryptionAlgorithmV1::CastCbc => 6,
+            EncryptionAlgorithmV1::AesCbc => 7,
+            EncryptionAlgorithmV1::CamelliaCbc => 8,
+            EncryptionAlgorithmV1::Other(other) => other,
+        }
+    }
+}
+
+impl EncryptionAlgorithmV1 {
+    ///Schlüssellängen in Bit, die für den Algorithmus vorgeschlagen werden.
+    /// Algorithmen mit variabler Schlüssellänge benötigen das Key Length Attribut (RFC 2409, Anhang A),
+    /// für Algorithmen mit fester Schlüssellänge ist die Liste leer
+    pub fn key_lengths(self) -> &'static [u16] {
+        match self {
+            EncryptionAlgorithmV1::AesCbc
+            | EncryptionAlgorithmV1::CamelliaCbc
+            | EncryptionAlgorithmV1::BlowfishCbc => &[128, 192, 256],
+            EncryptionAlgorithmV1::Rc5R16B64Cbc | EncryptionAlgorithmV1::CastCbc => &[128],
+            _ => &[],
+        }
+    }
+}
+
+///Hash-Algorithmen für IkeV1 (RFC 2409, Anhang A)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum HashAlgorithmV1 {
+    ///MD5
+    Md5,
+    ///SHA-1
+    Sha1,
+    ///Tiger
+    Tiger,
+    ///SHA2-256
+    Sha2_256,
+    ///SHA2-384
+    Sha2_384,
+    ///SHA2-512
+    Sha2_512,
+    ///unbekannter Wert
+    Other(u16),
+}
+
+impl From<u16> for HashAlgorithmV1 {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => HashAlgorithmV1::Md5,
+            2 => HashAlgorithmV1::Sha1,
+            3 => HashAlgorithmV1::Tiger,
+            4 => HashAlgorithmV1::Sha2_256,
+            5 => HashAlgorithmV1::Sha2_384,
+            6 => HashAlgorithmV1::Sha2_512,
+            other => HashAlgorithmV1::Other(other),
+        }
+    }
+}
+
+impl From<HashAlgorithmV1> for u16 {
+    fn from(value: HashAlgorithmV1) -> Self {
+        match value {
+            HashAlgorithmV1::Md5 => 1,
+            HashAlgorithmV1::Sha1 => 2,
+            HashAlgorithmV1::Tiger => 3,
+            HashAlgorithmV1::Sha2_256 => 4,
+            HashAlgorithmV1::Sha2_384 => 5,
+            HashAlgorithmV1::Sha2_512 => 6,
+            HashAlgorithmV1::Other(other) => other,
+        }
+    }
+}
+
+///Authentisierungsmethoden für IkeV1 (RFC 2409, Anhang A und RFC 4754)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum AuthenticationMethodV1 {
+    ///Pre-Shared Key
+    PreSharedKey,
+    ///DSS Signaturen
+    DssSignatures,
+    ///RSA Signaturen
+    RsaSignatures,
+    ///Verschlüsselung mit RSA
+    RsaEncryption,
+    ///überarbeitete Verschlüsselung mit RSA
+    RevisedRsaEncryption,
+    ///Verschlüsselung mit El-Gamal
+    ElGamalEncryption,
+    ///überarbeitete Verschlüsselung mit El-Gamal
+    RevisedElGamalEncryption,
+    ///ECDSA Signaturen
+    EcdsaSignatures,
+    ///ECDSA mit SHA-256 auf P-256
+    EcdsaSha256P256,
+    ///ECDSA mit SHA-384 auf P-384
+    EcdsaSha384P384,
+    ///ECDSA mit SHA-512 auf P-521
+    EcdsaSha512P521,
+    ///unbekannter Wert
+    Other(u16),
+}
+
+impl From<u16> for AuthenticationMethodV1 {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => AuthenticationMethodV1::PreSharedKey,
+            2 => AuthenticationMethodV1::DssSignatures,
+            3 => AuthenticationMethodV1::RsaSignatures,
+            4 => AuthenticationMethodV1::RsaEncryption,
+            5 => AuthenticationMethodV1::RevisedRsaEncryption,
+            6 => AuthenticationMethodV1::ElGamalEncryption,
+            7 => AuthenticationMethodV1::RevisedElGamalEncryption,
+            8 => AuthenticationMethodV1::EcdsaSignatures,
+            9 => AuthenticationMethodV1::EcdsaSha256P256,
+            10 => AuthenticationMethodV1::EcdsaSha384P384,
+            11 => AuthenticationMethodV1::EcdsaSha512P521,
+            other => AuthenticationMethodV1::Other(other),
+        }
+    }
+}
+
+impl From<AuthenticationMethodV1> for u16 {
+    fn from(value: AuthenticationMethodV1) -> Self {
+        match value {
+            AuthenticationMethodV1::PreSharedKey => 1,
+            AuthenticationMethodV1::DssSignatures => 2,
+            AuthenticationMethodV1::RsaSignatures => 3,
+            AuthenticationMethodV1::RsaEncryption => 4,
+            AuthenticationMethodV1::RevisedRsaEncryption => 5,
+            AuthenticationMethodV1::ElGamalEncryption => 6,
+            AuthenticationMethodV1::RevisedElGamalEncryption => 7,
+            AuthenticationMethodV1::EcdsaSignatures => 8,
+            AuthenticationMethodV1::EcdsaSha256P256 => 9,
+            AuthenticationMethodV1::EcdsaSha384P384 => 10,
+            AuthenticationMethodV1::EcdsaSha512P521 => 11,
+            AuthenticationMethodV1::Other(other) => other,
+        }
+    }
+}
+
+///Diffie-Hellman Gruppen, die Werte sind für IkeV1 und IkeV2 identisch
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum DiffieHellmanGroup {
+    ///768-Bit MODP (RFC 2409)
+    Modp768,
+    ///1024-Bit MODP (RFC 2409)
+    Modp1024,
+    ///EC2N über GF(2^155)
+    Ec2nGp155,
+    ///EC2N über GF(2^185)
+    Ec2nGp185,
+    ///1536-Bit MODP (RFC 3526)
+    Modp1536,
+    ///EC2N über GF(2^163), zufällig
+    Ec2n163Random,
+    ///EC2N über GF(2^163), Koblitz
+    Ec2n163Koblitz,
+    ///EC2N über GF(2^283), zufällig
+    Ec2n283Random,
+    ///EC2N über GF(2^283), Koblitz
+    Ec2n283Koblitz,
+    ///EC2N über GF(2^409), zufällig
+    Ec2n409Random,
+    ///EC2N über GF(2^409), Koblitz
+    Ec2n409Koblitz,
+    ///EC2N über GF(2^571), zufällig
+    Ec2n571Random,
+    ///EC2N über GF(2^571), Koblitz
+    Ec2n571Koblitz,
+    ///2048-Bit MODP (RFC 3526)
+    Modp2048,
+    ///3072-Bit MODP (RFC 3526)
+    Modp3072,
+    ///4096-Bit MODP (RFC 3526)
+    Modp4096,
+    ///6144-Bit MODP (RFC 3526)
+    Modp6144,
+    ///8192-Bit MODP (RFC 3526)
+    Modp8192,
+    ///256-Bit ECP (RFC 5903)
+    Ecp256,
+    ///384-Bit ECP (RFC 5903)
+    Ecp384,
+    ///521-Bit ECP (RFC 5903)
+    Ecp521,
+    ///1024-Bit MODP mit 160-Bit Untergruppe (RFC 5114)
+    Modp1024S160,
+    ///2048-Bit MODP mit 224-Bit Untergruppe (RFC 5114)
+    Modp2048S224,
+    ///2048-Bit MODP mit 256-Bit Untergruppe (RFC 5114)
+    Modp2048S256,
+    ///192-Bit ECP (RFC 5114)
+    Ecp192,
+    ///224-Bit ECP (RFC 5114)
+    Ecp224,
+    ///brainpoolP224r1 (RFC 6954)
+    BrainpoolP224r1,
+    ///brainpoolP256r1 (RFC 6954)
+    BrainpoolP256r1,
+    ///brainpoolP384r1 (RFC 6954)
+    BrainpoolP384r1,
+    ///brainpoolP512r1 (RFC 6954)
+    BrainpoolP512r1,
+    ///Curve25519 (RFC 8031)
+    Curve25519,
+    ///Curve448 (RFC 8031)
+    Curve448,
+    ///GOST3410_2012_256 (RFC 9385)
+    Gost3410_2012_256,
+    ///GOST3410_2012_512 (RFC 9385)
+    Gost3410_2012_512,
+    ///unbekannter Wert
+    Other(u16),
+}
+
+impl From<u16> for DiffieHellmanGroup {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => DiffieHellmanGroup::Modp768,
+            2 => DiffieHellmanGroup::Modp1024,
+            3 => DiffieHellmanGroup::Ec2nGp155,
+            4 => DiffieHellmanGroup::Ec2nGp185,
+            5 => DiffieHellmanGroup::Modp1536,
+            6 => DiffieHellmanGroup::Ec2n163Random,
+            7 => DiffieHellmanGroup::Ec2n163Koblitz,
+            8 => DiffieHellmanGroup::Ec2n283Random,
+            9 => DiffieHellmanGroup::Ec2n283Koblitz,
+            10 => DiffieHellmanGroup::Ec2n409Random,
+            11 => DiffieHellmanGroup::Ec2n409Koblitz,
+            12 => DiffieHellmanGroup::Ec2n571Random,
+            13 => DiffieHellmanGroup::Ec2n571Koblitz,
+            14 => DiffieHellmanGroup::Modp2048,
+            15 => DiffieHellmanGroup::Modp3072,
+            16 => DiffieHellmanGroup::Modp4096,
+            17 => DiffieHellmanGroup::Modp6144,
+            18 => DiffieHellmanGroup::Modp8192,
+            19 => DiffieHellmanGroup::Ecp256,
+            20 => DiffieHellmanGroup::Ecp384,
+            21 => DiffieHellmanGroup::Ecp521,
+            22 => DiffieHellmanGroup::Modp1024S160,
+            23 => DiffieHellmanGroup::Modp2048S224,
+            24 => DiffieHellmanGroup::Modp2048S256,
+            25 => DiffieHellmanGroup::Ecp192,
+            26 => DiffieHellmanGroup::Ecp224,
+            27 => DiffieHellmanGroup::BrainpoolP224r1,
+            28 => DiffieHellmanGroup::BrainpoolP256r1,
+            29 => DiffieHellmanGroup::BrainpoolP384r1,
+            30 => DiffieHellmanGroup::BrainpoolP512r1,
+            31 => DiffieHellmanGroup::Curve25519,
+            32 => DiffieHellmanGroup::Curve448,
+            33 => DiffieHellmanGroup::Gost3410_2012_256,
+            34 => DiffieHellmanGroup::Gost3410_2012_512,
+            other => DiffieHellmanGroup::Other(other),
+        }
+    }
+}
+
+impl From<DiffieHellmanGroup> for u16 {
+    fn from(value: DiffieHellmanGroup) -> Self {
+        match value {
+            DiffieHellmanGroup::Modp768 => 1,
+            DiffieHellmanGroup::Modp1024 => 2,
+            DiffieHellmanGroup::Ec2nGp155 => 3,
+            DiffieHellmanGroup::Ec2nGp185 => 4,
+            DiffieHellmanGroup::Modp1536 => 5,
+            DiffieHellmanGroup::Ec2n163Random => 6,
+            DiffieHellmanGroup::Ec2n163Koblitz => 7,
+            DiffieHellmanGroup::Ec2n283Random => 8,
+            DiffieHellmanGroup::Ec2n283Koblitz => 9,
+            DiffieHellmanGroup::Ec2n409Random => 10,
+            DiffieHellmanGroup::Ec2n409Koblitz => 11,
+            DiffieHellmanGroup::Ec2n571Random => 12,
+            DiffieHellmanGroup::Ec2n571Koblitz => 13,
+            DiffieHellmanGroup::Modp2048 => 14,
+            DiffieHellmanGroup::Modp3072 => 15,
+            DiffieHellmanGroup::Modp4096 => 16,
+            DiffieHellmanGroup::Modp6144 => 17,
+            DiffieHellmanGroup::Modp8192 => 18,
+            DiffieHellmanGroup::Ecp256 => 19,
+            DiffieHellmanGroup::Ecp384 => 20,
+            DiffieHellmanGroup::Ecp521 => 21,
+            DiffieHellmanGroup::Modp1024S160 => 22,
+            DiffieHellmanGroup::Modp2048S224 => 23,
+            DiffieHellmanGroup::Modp2048S256 => 24,
+            DiffieHellmanGroup::Ecp192 => 25,
+            DiffieHellmanGroup::Ecp224 => 26,
+            DiffieHellmanGroup::BrainpoolP224r1 => 27,
+            DiffieHellmanGroup::BrainpoolP256r1 => 28,
+            DiffieHellmanGroup::BrainpoolP384r1 => 29,
+            DiffieHellmanGroup::BrainpoolP512r1 => 30,
+            DiffieHellmanGroup::Curve25519 => 31,
+            DiffieHellmanGroup::Curve448 => 32,
+            DiffieHellmanGroup::Gost3410_2012_256 => 33,
+            DiffieHellmanGroup::Gost3410_2012_512 => 34,
+            DiffieHellmanGroup::Other(other) => other,
+        }
+    }
+}
+
+///Verschlüsselungsalgorithmen für IkeV2 (Transformationstyp 1)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum EncryptionAlgorithmV2 {
+    ///DES mit 64-Bit IV
+    DesIv64,
+    ///DES
+    Des,
+    ///3DES
+    TripleDes,
+    ///RC5
+    Rc5,
+    ///IDEA
+    Idea,
+    ///CAST
+    Cast,
+    ///Blowfish
+    Blowfish,
+    ///3IDEA
+    TripleIdea,
+    ///DES mit 32-Bit IV
+    DesIv32,
+    ///keine Verschlüsselung
+    Null,
+    ///AES-CBC
+    AesCbc,
+    ///AES-CTR
+    AesCtr,
+    ///AES-CCM mit 8 Byte ICV
+    AesCcm8,
+    ///AES-CCM mit 12 Byte ICV
+    AesCcm12,
+    ///AES-CCM mit 16 Byte ICV
+    AesCcm16,
+    ///AES-GCM mit 8 Byte ICV
+    AesGcm8,
+    ///AES-GCM mit 12 Byte ICV
+    AesGcm12,
+    ///AES-GCM mit 16 Byte ICV
+    AesGcm16,
+    ///NULL mit AES-GMAC
+    NullAuthAesGmac,
+    ///Camellia-CBC
+    CamelliaCbc,
+    ///Camellia-CTR
+    CamelliaCtr,
+    ///Camellia-CCM mit 8 Byte ICV
+    CamelliaCcm8,
+    ///Camellia-CCM mit 12 Byte ICV
+    CamelliaCcm12,
+    ///Camellia-CCM mit 16 Byte ICV
+    CamelliaCcm16,
+    ///ChaCha20-Poly1305
+    ChaCha20Poly1305,
+    ///AES-CCM mit 8 Byte ICV und implizitem IV
+    AesCcm8Iiv,
+    ///AES-GCM mit 16 Byte ICV und implizitem IV
+    AesGcm16Iiv,
+    ///ChaCha20-Poly1305 mit implizitem IV
+    ChaCha20Poly1305Iiv,
+    ///Kuznyechik-MGM-KTREE
+    KuznyechikMgmKtree,
+    ///Magma-MGM-KTREE
+    MagmaMgmKtree,
+    ///Kuznyechik-MGM-MAC-KTREE
+    KuznyechikMgmMacKtree,
+    ///Magma-MGM-MAC-KTREE
+    MagmaMgmMacKtree,
+    ///unbekannter Wert
+    Other(u16),
+}
+
+impl From<u16> for EncryptionAlgorithmV2 {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => EncryptionAlgorithmV2::DesIv64,
+            2 => EncryptionAlgorithmV2::Des,
+            3 => EncryptionAlgorithmV2::TripleDes,
+            4 => EncryptionAlgorithmV2::Rc5,
+            5 => EncryptionAlgorithmV2::Idea,
+            6 => EncryptionAlgorithmV2::Cast,
+            7 => EncryptionAlgorithmV2::Blowfish,
+            8 => EncryptionAlgorithmV2::TripleIdea,
+            9 => EncryptionAlgorithmV2::DesIv32,
+            11 => EncryptionAlgorithmV2::Null,
+            12 => EncryptionAlgorithmV2::AesCbc,
+            13 => EncryptionAlgorithmV2::AesCtr,
+            14 => EncryptionAlgorithmV2::AesCcm8,
+            15 => EncryptionAlgorithmV2::AesCcm12,
+            16 => EncryptionAlgorithmV2::AesCcm16,
+            18 => EncryptionAlgorithmV2::AesGcm8,
+            19 => EncryptionAlgorithmV2::AesGcm12,
+            20 => EncryptionAlgorithmV2::AesGcm16,
+            21 => EncryptionAlgorithmV2::NullAuthAesGmac,
+            23 => EncryptionAlgorithmV2::CamelliaCbc,
+            24 => EncryptionAlgorithmV2::CamelliaCtr,
+            25 => EncryptionAlgorithmV2::CamelliaCcm8,
+            26 => EncryptionAlgorithmV2::CamelliaCcm12,
+            27 => EncryptionAlgorithmV2::CamelliaCcm16,
+            28 => EncryptionAlgorithmV2::ChaCha20Poly1305,
+            29 => EncryptionAlgorithmV2::AesCcm8Iiv,
+            30 => EncryptionAlgorithmV2::AesGcm16Iiv,
+            31 => EncryptionAlgorithmV2::ChaCha20Poly1305Iiv,
+            32 => EncryptionAlgorithmV2::KuznyechikMgmKtree,
+            33 => EncryptionAlgorithmV2::MagmaMgmKtree,
+            34 => EncryptionAlgorithmV2::KuznyechikMgmMacKtree,
+            35 => EncryptionAlgorithmV2::MagmaMgmMacKtree,
+            other => EncryptionAlgorithmV2::Other(other),
+        }
+    }
+}
+
+impl From<EncryptionAlgorithmV2> for u16 {
+    fn from(value: EncryptionAlgorithmV2) -> Self {
+        match value {
+            EncryptionAlgorithmV2::DesIv64 => 1,
+            EncryptionAlgorithmV2::Des => 2,
+            EncryptionAlgorithmV2::TripleDes => 3,
+            EncryptionAlgorithmV2::Rc5 => 4,
+            EncryptionAlgorithmV2::Idea => 5,
+            EncryptionAlgorithmV2::Cast => 6,
+            EncryptionAlgorithmV2::Blowfish => 7,
+            EncryptionAlgorithmV2::TripleIdea => 8,
+            EncryptionAlgorithmV2::DesIv32 => 9,
+            EncryptionAlgorithmV2::Null => 11,
+            EncryptionAlgorithmV2::AesCbc => 12,
+            EncryptionAlgorithmV2::AesCtr => 13,
+            EncryptionAlgorithmV2::AesCcm8 => 14,
+            EncryptionAlgorithmV2::AesCcm12 => 15,
+            EncryptionAlgorithmV2::AesCcm16 => 16,
+            EncryptionAlgorithmV2::AesGcm8 => 18,
+            EncryptionAlgorithmV2::AesGcm12 => 19,
+            EncryptionAlgorithmV2::AesGcm16 => 20,
+            EncryptionAlgorithmV2::NullAuthAesGmac => 21,
+            EncryptionAlgorithmV2::CamelliaCbc => 23,
+            EncryptionAlgorithmV2::CamelliaCtr => 24,
+            EncryptionAlgorithmV2::CamelliaCcm8 => 25,
+            EncryptionAlgorithmV2::CamelliaCcm12 => 26,
+            EncryptionAlgorithmV2::CamelliaCcm16 => 27,
+            EncryptionAlgorithmV2::ChaCha20Poly1305 => 28,
+            EncryptionAlgorithmV2::AesCcm8Iiv => 29,
+            EncryptionAlgorithmV2::AesGcm16Iiv => 30,
+            EncryptionAlgorithmV2::ChaCha20Poly1305Iiv => 31,
+            EncryptionAlgorithmV2::KuznyechikMgmKtree => 32,
+            EncryptionAlgorithmV2::MagmaMgmKtree => 33,
+            EncryptionAlgorithmV2::KuznyechikMgmMacKtree => 34,
+            EncryptionAlgorithmV2::MagmaMgmMacKtree => 35,
+            EncryptionAlgorithmV2::Other(other) => other,
+        }
+    }
+}
+
+impl EncryptionAlgorithmV2 {
+    ///Kombinierte Verfahren (AEAD, RFC 7296 Abschnitt 3.3) schützen auch die Integrität,
+    /// ein Proposal mit ihnen benötigt keinen Integritätsalgorithmus
+    pub fn is_aead(self) -> bool {
+        matches!(
+            self,
+            EncryptionAlgorithmV2::AesCcm8
+                | EncryptionAlgorithmV2::AesCcm12
+                | EncryptionAlgorithmV2::AesCcm16
+                | EncryptionAlgorithmV2::AesGcm8
+                | EncryptionAlgorithmV2::AesGcm12
+                | EncryptionAlgorithmV2::AesGcm16
+                | EncryptionAlgorithmV2::NullAuthAesGmac
+                | EncryptionAlgorithmV2::CamelliaCcm8
+                | EncryptionAlgorithmV2::CamelliaCcm12
+                | EncryptionAlgorithmV2::CamelliaCcm16
+                | EncryptionAlgorithmV2::ChaCha20Poly1305
+                | EncryptionAlgorithmV2::AesCcm8Iiv
+                | EncryptionAlgorithmV2::AesGcm16Iiv
+                | EncryptionAlgorithmV2::ChaCha20Poly1305Iiv
+                | EncryptionAlgorithmV2::KuznyechikMgmKtree
+                | EncryptionAlgorithmV2::MagmaMgmKtree
+                | EncryptionAlgorithmV2::KuznyechikMgmMacKtree
+                | EncryptionAlgorithmV2::MagmaMgmMacKtree
+        )
+    }
+}
+
+///Pseudo-Random Funktionen für IkeV2 (Transformationstyp 2)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum PseudoRandomFunctionV2 {
+    ///HMAC-MD5
+    HmacMd5,
+    ///HMAC-SHA1
+    HmacSha1,
+    ///HMAC-Tiger
+    HmacTiger,
+    ///AES128-XCBC
+    Aes128Xcbc,
+    ///HMAC-SHA2-256
+    HmacSha2_256,
+    ///HMAC-SHA2-384
+    HmacSha2_384,
+    ///HMAC-SHA2-512
+    HmacSha2_512,
+    ///AES128-CMAC
+    Aes128Cmac,
+    ///HMAC-Streebog-512
+    HmacStreebog512,
+    ///unbekannter Wert
+    Other(u16),
+}
+
+impl From<u16> for PseudoRandomFunctionV2 {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => PseudoRandomFunctionV2::HmacMd5,
+            2 => PseudoRandomFunctionV2::HmacSha1,
+            3 => PseudoRandomFunctionV2::HmacTiger,
+            4 => PseudoRandomFunctionV2::Aes128Xcbc,
+            5 => PseudoRandomFunctionV2::HmacSha2_256,
+            6 => PseudoRandomFunctionV2::HmacSha2_384,
+            7 => PseudoRandomFunctionV2::HmacSha2_512,
+            8 => PseudoRandomFunctionV2::Aes128Cmac,
+            9 => PseudoRandomFunctionV2::HmacStreebog512,
+            other => PseudoRandomFunctionV2::Other(other),
+        }
+    }
+}
+
+impl From<PseudoRandomFunctionV2> for u16 {
+    fn from(value: PseudoRandomFunctionV2) -> Self {
+        match value {
+            PseudoRandomFunctionV2::HmacMd5 => 1,
+            PseudoRandomFunctionV2::HmacSha1 => 2,
+            PseudoRandomFunctionV2::HmacTiger => 3,
+            PseudoRandomFunctionV2::Aes128Xcbc => 4,
+            PseudoRandomFunctionV2::HmacSha2_256 => 5,
+            PseudoRandomFunctionV2::HmacSha2_384 => 6,
+            PseudoRandomFunctionV2::HmacSha2_512 => 7,
+            PseudoRandomFunctionV2::Aes128Cmac => 8,
+            PseudoRandomFunctionV2::HmacStreebog512 => 9,
+            PseudoRandomFunctionV2::Other(other) => other,
+        }
+    }
+}
+
+///Integritätsalgorithmen für IkeV2 (Transformationstyp 3)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum IntegrityAlgorithmV2 {
+    ///HMAC-MD5-96
+    HmacMd5_96,
+    ///HMAC-SHA1-96
+    HmacSha1_96,
+    ///DES-MAC
+    DesMac,
+    ///KPDK-MD5
+    KpdkMd5,
+    ///AES-XCBC-96
+    AesXcbc96,
+    ///HMAC-MD5-128
+    HmacMd5_128,
+    ///HMAC-SHA1-160
+    HmacSha1_160,
+    ///AES-CMAC-96
+    AesCmac96,
+    ///AES-128-GMAC
+    Aes128Gmac,
+    ///AES-192-GMAC
+    Aes192Gmac,
+    ///AES-256-GMAC
+    Aes256Gmac,
+    ///HMAC-SHA2-256-128
+    HmacSha2_256_128,
+    ///HMAC-SHA2-384-192
+    HmacSha2_384_192,
+    ///HMAC-SHA2-512-256
+    HmacSha2_512_256,
+    ///unbekannter Wert
+    Other(u16),
+}
+
+impl From<u16> for IntegrityAlgorithmV2 {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => IntegrityAlgorithmV2::HmacMd5_96,
+            2 => IntegrityAlgorithmV2::HmacSha1_96,
+            3 => IntegrityAlgorithmV2::DesMac,
+            4 => IntegrityAlgorithmV2::KpdkMd5,
+            5 => IntegrityAlgorithmV2::AesXcbc96,
+            6 => IntegrityAlgorithmV2::HmacMd5_128,
+            7 => IntegrityAlgorithmV2::HmacSha1_160,
+            8 => IntegrityAlgorithmV2::AesCmac96,
+            9 => IntegrityAlgorithmV2::Aes128Gmac,
+            10 => IntegrityAlgorithmV2::Aes192Gmac,
+            11 => IntegrityAlgorithmV2::Aes256Gmac,
+            12 => IntegrityAlgorithmV2::HmacSha2_256_128,
+            13 => IntegrityAlgorithmV2::HmacSha2_384_192,
+            14 => IntegrityAlgorithmV2::HmacSha2_512_256,
+            other => IntegrityAlgorithmV2::Other(other),
+        }
+    }
+}
+
+impl From<IntegrityAlgorithmV2> for u16 {
+    fn from(value: IntegrityAlgorithmV2) -> Self {
+        match value {
+            IntegrityAlgorithmV2::HmacMd5_96 => 1,
+            IntegrityAlgorithmV2::HmacSha1_96 => 2,
+            IntegrityAlgorithmV2::DesMac => 3,
+            IntegrityAlgorithmV2::KpdkMd5 => 4,
+            IntegrityAlgorithmV2::AesXcbc96 => 5,
+            IntegrityAlgorithmV2::HmacMd5_128 => 6,
+            IntegrityAlgorithmV2::HmacSha1_160 => 7,
+            IntegrityAlgorithmV2::AesCmac96 => 8,
+            IntegrityAlgorithmV2::Aes128Gmac => 9,
+            IntegrityAlgorithmV2::Aes192Gmac => 10,
+            IntegrityAlgorithmV2::Aes256Gmac => 11,
+            IntegrityAlgorithmV2::HmacSha2_256_128 => 12,
+            IntegrityAlgorithmV2::HmacSha2_384_192 => 13,
+            IntegrityAlgorithmV2::HmacSha2_512_256 => 14,
+            IntegrityAlgorithmV2::Other(other) => other,
+        }
+    }
+}
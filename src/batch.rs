@@ -0,0 +1,310 @@
+//! # Bike-Scan
+//! das folgende Modul sendet und empfängt mehrere Datagramme mit einem Systemaufruf (sendmmsg, recvmmsg).
+//! Es ist nur unter Linux verfügbar, die Probe-Engine verwendet es für ihre gemeinsamen Sockets.
+//! Auf anderen Systemen sendet und empfängt die Engine jedes Datagramm einzeln
+
+use std::io;
+use std::mem;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+use std::net::SocketAddr;
+use std::net::SocketAddrV4;
+use std::net::SocketAddrV6;
+use std::os::fd::AsRawFd;
+use std::ptr;
+
+use tokio::io::Interest;
+use tokio::net::UdpSocket;
+
+///Puffer für die Datagramme, die mit einem recvmmsg Aufruf empfangen werden
+pub struct ReceiveBatch {
+    ///ein Puffer je Datagramm
+    buffers: Vec<Vec<u8>>,
+    ///Absenderadressen, werden vom Kernel gefüllt
+    addresses: Vec<libc::sockaddr_storage>,
+    ///Länge und Absender der empfangenen Datagramme
+    received: Vec<(usize, Option<SocketAddr>)>,
+}
+
+impl ReceiveBatch {
+    ///Puffer für 'batch_size' Datagramme mit jeweils höchstens 'datagram_length' Bytes
+    pub fn new(batch_size: usize, datagram_length: usize) -> Self {
+        ReceiveBatch {
+            buffers: vec![vec![0u8; datagram_length]; batch_size],
+            // SAFETY: sockaddr_storage is plain old data, all zero bytes are a valid value
+            addresses: vec![unsafe { mem::zeroed() }; batch_size],
+            received: Vec::with_capacity(batch_size),
+        }
+    }
+
+    ///Wartet, bis Datagramme anliegen, und empfängt so viele wie möglich mit einem Aufruf.
+    /// Gibt die Anzahl der empfangenen Datagramme zurück
+    pub async fn receive(&mut self, socket: &UdpSocket) -> io::Result<usize> {
+        loop {
+            socket.readable().await?;
+            match socket.try_io(Interest::READABLE, || self.recvmmsg(socket)) {
+                Ok(count) => return Ok(count),
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => continue,
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    ///Inhalt und Absender des Datagramms 'index' aus dem letzten Aufruf von receive().
+    /// Datagramme mit unbekannter Adressfamilie werden übersprungen
+    pub fn datagram(&self, index: usize) -> Option<(&[u8], SocketAddr)> {
+        let (length, source) = self.received.get(index)?;
+        Some((&self.buffers[index][..*length], (*source)?))
+    }
+
+    fn recvmmsg(&mut self, socket: &UdpSocket) -> io::Result<usize> {
+        let mut iovecs: Vec<libc::iovec> = self
+            .buffers
+            .iter_mut()
+            .map(|buffer| libc::iovec {
+                iov_base: buffer.as_mut_ptr().cast(),
+                iov_len: buffer.len(),
+            })
+            .collect();
+        let mut headers: Vec<libc::mmsghdr> = iovecs
+            .iter_mut()
+            .zip(self.addresses.iter_mut())
+            .map(|(iovec, address)| {
+                // SAFETY: mmsghdr is plain old data, all zero bytes are a valid value
+                let mut header: libc::mmsghdr = unsafe { mem::zeroed() };
+                header.msg_hdr.msg_name = ptr::from_mut(address).cast();
+                header.msg_hdr.msg_namelen = mem::size_of::<libc::sockaddr_storage>() as _;
+                header.msg_hdr.msg_iov = iovec;
+                header.msg_hdr.msg_iovlen = 1;
+                header
+            })
+            .collect();
+        // SAFETY: every header points to a buffer and an address that live until the call returns,
+        // the number of headers is passed along
+        let count = unsafe {
+            libc::recvmmsg(
+                socket.as_raw_fd(),
+                headers.as_mut_ptr(),
+                headers.len() as _,
+                libc::MSG_DONTWAIT,
+                ptr::null_mut(),
+            )
+        };
+        let count = usize::try_from(count).map_err(|_| io::Error::last_os_error())?;
+        self.received.clear();
+        for (header, address) in headers.iter().zip(&self.addresses).take(count) {
+            self.received
+                .push((header.msg_len as usize, socket_addr_from_raw(address)));
+        }
+        Ok(count)
+    }
+}
+
+///Sendet die Datagramme mit möglichst wenigen sendmmsg Aufrufen.
+/// Gibt für jedes Datagramm in derselben Reihenfolge das Ergebnis zurück
+pub async fn send_batch(
+    socket: &UdpSocket,
+    datagrams: &[(&[u8], SocketAddr)],
+) -> Vec<io::Result<()>> {
+    let mut results = Vec::with_capacity(datagrams.len());
+    while results.len() < datagrams.len() {
+        let remaining = &datagrams[results.len()..];
+        if let Err(error) = socket.writable().await {
+            let kind = error.kind();
+            results.push(Err(error));
+            results.extend(remaining[1..].iter().map(|_| Err(kind.into())));
+            break;
+        }
+        match socket.try_io(Interest::WRITABLE, || sendmmsg(socket, remaining)) {
+            Ok(count) => results.extend((0..count).map(|_| Ok(()))),
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => continue,
+            //the error belongs to the first datagram, the others are tried again
+            Err(error) => results.push(Err(error)),
+        }
+    }
+    results
+}
+
+///Ein sendmmsg Aufruf, gibt die Anzahl der gesendeten Datagramme zurück (mindestens eins)
+fn sendmmsg(socket: &UdpSocket, datagrams: &[(&[u8], SocketAddr)]) -> io::Result<usize> {
+    let mut addresses: Vec<(libc::sockaddr_storage, libc::socklen_t)> = datagrams
+        .iter()
+        .map(|(_, remote_addr)| socket_addr_to_raw(remote_addr))
+        .collect();
+    let mut iovecs: Vec<libc::iovec> = datagrams
+        .iter()
+        .map(|(datagram, _)| libc::iovec {
+            //sendmmsg does not write to the buffers
+            iov_base: datagram.as_ptr().cast_mut().cast(),
+            iov_len: datagram.len(),
+        })
+        .collect();
+    let mut headers: Vec<libc::mmsghdr> = iovecs
+        .iter_mut()
+        .zip(addresses.iter_mut())
+        .map(|(iovec, (address, address_length))| {
+            // SAFETY: mmsghdr is plain old data, all zero bytes are a valid value
+            let mut header: libc::mmsghdr = unsafe { mem::zeroed() };
+            header.msg_hdr.msg_name = ptr::from_mut(address).cast();
+            header.msg_hdr.msg_namelen = *address_length;
+            header.msg_hdr.msg_iov = iovec;
+            header.msg_hdr.msg_iovlen = 1;
+            header
+        })
+        .collect();
+    // SAFETY: every header points to a datagram and an address that live until the call returns,
+    // the number of headers is passed along
+    let count = unsafe {
+        libc::sendmmsg(
+            socket.as_raw_fd(),
+            headers.as_mut_ptr(),
+            headers.len() as _,
+            libc::MSG_DONTWAIT,
+        )
+    };
+    match usize::try_from(count) {
+        Ok(0) => Err(io::ErrorKind::WriteZero.into()),
+        Ok(count) => Ok(count),
+        Err(_) => Err(io::Error::last_os_error()),
+    }
+}
+
+///Wandelt die Adresse in die Darstellung des Kernels um
+fn socket_addr_to_raw(address: &SocketAddr) -> (libc::sockaddr_storage, libc::socklen_t) {
+    // SAFETY: sockaddr_storage is plain old data, all zero bytes are a valid value
+    let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let length = match address {
+        SocketAddr::V4(address) => {
+            let raw = libc::sockaddr_in {
+                sin_family: libc::AF_INET as libc::sa_family_t,
+                sin_port: address.port().to_be(),
+                sin_addr: libc::in_addr {
+                    s_addr: u32::from_ne_bytes(address.ip().octets()),
+                },
+                sin_zero: [0; 8],
+            };
+            // SAFETY: sockaddr_storage is large and aligned enough for every socket address
+            unsafe { ptr::write(ptr::from_mut(&mut storage).cast(), raw) };
+            mem::size_of::<libc::sockaddr_in>()
+        }
+        SocketAddr::V6(address) => {
+            let raw = libc::sockaddr_in6 {
+                sin6_family: libc::AF_INET6 as libc::sa_family_t,
+                sin6_port: address.port().to_be(),
+                sin6_flowinfo: address.flowinfo(),
+                sin6_addr: libc::in6_addr {
+                    s6_addr: address.ip().octets(),
+                },
+                sin6_scope_id: address.scope_id(),
+            };
+            // SAFETY: sockaddr_storage is large and aligned enough for every socket address
+            unsafe { ptr::write(ptr::from_mut(&mut storage).cast(), raw) };
+            mem::size_of::<libc::sockaddr_in6>()
+        }
+    };
+    (storage, length as libc::socklen_t)
+}
+
+///Wandelt eine Adresse des Kernels um, None bei anderen Adressfamilien als IPv4 und IPv6
+fn socket_addr_from_raw(storage: &libc::sockaddr_storage) -> Option<SocketAddr> {
+    match libc::c_int::from(storage.ss_family) {
+        libc::AF_INET => {
+            // SAFETY: the kernel wrote a sockaddr_in for AF_INET
+            let raw = unsafe { &*ptr::from_ref(storage).cast::<libc::sockaddr_in>() };
+            Some(SocketAddr::V4(SocketAddrV4::new(
+                Ipv4Addr::from(raw.sin_addr.s_addr.to_ne_bytes()),
+                u16::from_be(raw.sin_port),
+            )))
+        }
+        libc::AF_INET6 => {
+            // SAFETY: the kernel wrote a sockaddr_in6 for AF_INET6
+            let raw = unsafe { &*ptr::from_ref(storage).cast::<libc::sockaddr_in6>() };
+            Some(SocketAddr::V6(SocketAddrV6::new(
+                Ipv6Addr::from(raw.sin6_addr.s6_addr),
+                u16::from_be(raw.sin6_port),
+                raw.sin6_flowinfo,
+                raw.sin6_scope_id,
+            )))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///Sendet drei Datagramme mit einem sendmmsg Aufruf an einen Socket auf 'loopback'
+    /// und prüft Inhalt, Länge und Absender jedes Datagramms nach dem Empfang mit recvmmsg.
+    /// Der Puffer ist größer als die Anzahl der wartenden Datagramme
+    async fn round_trip(loopback: SocketAddr) {
+        let sender = UdpSocket::bind(loopback).await.unwrap();
+        let receiver = UdpSocket::bind(loopback).await.unwrap();
+        let sender_addr = sender.local_addr().unwrap();
+        let receiver_addr = receiver.local_addr().unwrap();
+        let payloads: [&[u8]; 3] = [b"first", b"second datagram", &[0xab; 1200]];
+        let datagrams: Vec<(&[u8], SocketAddr)> = payloads
+            .iter()
+            .map(|payload| (*payload, receiver_addr))
+            .collect();
+        let results = send_batch(&sender, &datagrams).await;
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|result| result.is_ok()));
+
+        let mut batch = ReceiveBatch::new(8, 2048);
+        let mut received = vec![];
+        while received.len() < payloads.len() {
+            let count = batch.receive(&receiver).await.unwrap();
+            assert!(count <= payloads.len() - received.len());
+            for index in 0..count {
+                let (datagram, source) = batch.datagram(index).unwrap();
+                received.push((datagram.to_vec(), source));
+            }
+            assert!(batch.datagram(count).is_none());
+        }
+        for ((datagram, source), payload) in received.iter().zip(payloads) {
+            assert_eq!(datagram.len(), payload.len());
+            assert_eq!(datagram.as_slice(), payload);
+            assert_eq!(*source, sender_addr);
+        }
+    }
+
+    #[tokio::test]
+    async fn ipv4_batch_round_trip() {
+        round_trip(SocketAddr::from(([127, 0, 0, 1], 0))).await;
+    }
+
+    #[tokio::test]
+    async fn ipv6_batch_round_trip() {
+        round_trip(SocketAddr::from((Ipv6Addr::LOCALHOST, 0))).await;
+    }
+
+    #[test]
+    fn ipv4_address_round_trip() {
+        let address = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(192, 0, 2, 1), 4500));
+        let (storage, length) = socket_addr_to_raw(&address);
+        assert_eq!(length as usize, mem::size_of::<libc::sockaddr_in>());
+        assert_eq!(socket_addr_from_raw(&storage), Some(address));
+    }
+
+    #[test]
+    fn ipv6_address_round_trip() {
+        let address = SocketAddr::V6(SocketAddrV6::new(
+            "2001:db8::1".parse().unwrap(),
+            500,
+            0x12345,
+            7,
+        ));
+        let (storage, length) = socket_addr_to_raw(&address);
+        assert_eq!(length as usize, mem::size_of::<libc::sockaddr_in6>());
+        assert_eq!(socket_addr_from_raw(&storage), Some(address));
+    }
+
+    #[test]
+    fn unknown_address_family_is_skipped() {
+        // SAFETY: sockaddr_storage is plain old data, all zero bytes are a valid value
+        let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+        storage.ss_family = libc::AF_UNIX as libc::sa_family_t;
+        assert_eq!(socket_addr_from_raw(&storage), None);
+    }
+}
@@ -0,0 +1,281 @@
+//! # Bike-Scan
+//! das folgende Modul ordnet empfangene Nachrichten den gesendeten Proben zu.
+//! Eine Nachricht ist die Antwort auf eine Probe, wenn der Initiator SPI übereinstimmt
+//! und der Header zu einer Antwort passt (Version, Austauschtyp, Flags, Message ID, Länge).
+//! Verspätete Antworten auf frühere Proben und fremde Nachrichten werden verworfen und gezählt
+
+use std::collections::HashSet;
+
+use log::debug;
+use zerocopy::FromBytes;
+
+use crate::error::ScanError;
+use crate::ike::ExchangeType;
+use crate::ikev2::ExchangeTypeV2;
+use crate::parse_ikev2::ResponseHeaderV2;
+use crate::report::DiscardedResponses;
+use crate::IKE_HEADER_LENGTH;
+
+///IkeV2 Flag: die Nachricht ist eine Antwort (RFC 7296, Abschnitt 3.1)
+const RESPONSE_FLAG: u8 = 0x20;
+
+///IkeV2 Flag: die Nachricht wurde vom ursprünglichen Initiator gesendet
+const INITIATOR_FLAG: u8 = 0x08;
+
+///Eine gesendete Probe, auf die eine Antwort erwartet wird
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Probe {
+    ///zufälliger Initiator Security Parameter Index der Probe
+    pub initiator_spi: u64,
+    ///Hauptversion der Probe (1 oder 2), None wenn Antworten jeder Version angenommen werden
+    pub major_version: Option<u8>,
+    ///Message ID der Probe, die Antwort muss dieselbe Message ID tragen
+    pub message_id: u32,
+}
+
+impl Probe {
+    ///Probe der Hauptversion 'major_version' im ersten Austausch (Message ID 0)
+    pub fn new(initiator_spi: u64, major_version: u8) -> Self {
+        Probe {
+            initiator_spi,
+            major_version: Some(major_version),
+            message_id: 0,
+        }
+    }
+
+    ///Nimmt auch Antworten mit einer anderen Hauptversion oder einer abweichenden Länge an
+    /// (z.B. INVALID_MAJOR_VERSION oder aufgefüllte Datagramme bei der Erkennung)
+    pub fn any_version(self) -> Self {
+        Probe {
+            major_version: None,
+            ..self
+        }
+    }
+}
+
+///Prüft, ob die Nachricht die Antwort auf die Probe ist.
+/// Die Nachricht muss einen vollständigen Ike Header mit dem Initiator SPI der Probe enthalten
+/// und genau so lang sein, wie im Header angegeben. Bei der Erkennung (Probe::any_version)
+/// wird die Länge nicht geprüft, auch eine aufgefüllte oder gekürzte Antwort stammt von einem Ike Server.
+/// IkeV1 Antworten müssen Main Mode (mit der Message ID der Probe) oder Informational sein,
+/// IkeV2 Antworten IKE_SA_INIT mit gesetztem Response Flag, ohne Initiator Flag und mit der Message ID der Probe
+pub fn check_response(probe: &Probe, response: &[u8]) -> Result<(), ScanError> {
+    let header = ResponseHeaderV2::read_from_prefix(response)
+        .filter(|_| response.len() >= IKE_HEADER_LENGTH)
+        .ok_or(ScanError::TruncatedResponse {
+            expected: IKE_HEADER_LENGTH,
+            received: response.len(),
+        })?;
+    if header.initiator_spi.get() != probe.initiator_spi {
+        return Err(ScanError::UnexpectedSpi {
+            expected: probe.initiator_spi,
+            received: header.initiator_spi.get(),
+        });
+    }
+    let major_version = header.version >> 4;
+    if let Some(expected) = probe
+        .major_version
+        .filter(|version| *version != major_version)
+    {
+        return Err(ScanError::InvalidHeader(format!(
+            "version {:#04x} in response to version {} probe",
+            header.version, expected
+        )));
+    }
+    //a datagram contains exactly one IKE message, during discovery a padded
+    //or cut datagram with the probe's SPI still comes from an IKE responder
+    let length = header.length.get() as usize;
+    if probe.major_version.is_some() && length > response.len() {
+        return Err(ScanError::TruncatedResponse {
+            expected: length,
+            received: response.len(),
+        });
+    }
+    if probe.major_version.is_some() && length < response.len() {
+        return Err(ScanError::OverlongResponse {
+            expected: length,
+            received: response.len(),
+        });
+    }
+    let message_id = header.message_id.get();
+    match major_version {
+        1 => match ExchangeType::try_from_u8(header.exchange_type) {
+            //informational messages carry their own random message ID
+            Some(ExchangeType::Informational) => Ok(()),
+            Some(ExchangeType::IdentityProtect) if message_id == probe.message_id => Ok(()),
+            Some(ExchangeType::IdentityProtect) => Err(ScanError::InvalidHeader(format!(
+                "message ID {:#010x} in main mode response",
+                message_id
+            ))),
+            _ => Err(ScanError::InvalidHeader(format!(
+                "exchange type {} in IKEv1 response",
+                header.exchange_type
+            ))),
+        },
+        2 => {
+            if ExchangeTypeV2::try_from_u8(header.exchange_type) != Some(ExchangeTypeV2::IkeSaInit)
+            {
+                return Err(ScanError::InvalidHeader(format!(
+                    "exchange type {} in IKE_SA_INIT response",
+                    header.exchange_type
+                )));
+            }
+            if header.flag & RESPONSE_FLAG == 0 || header.flag & INITIATOR_FLAG != 0 {
+                return Err(ScanError::InvalidHeader(format!(
+                    "flags {:#04x} in IKE_SA_INIT response",
+                    header.flag
+                )));
+            }
+            if message_id != probe.message_id {
+                return Err(ScanError::InvalidHeader(format!(
+                    "message ID {:#010x} in IKE_SA_INIT response",
+                    message_id
+                )));
+            }
+            Ok(())
+        }
+        _ => Err(ScanError::InvalidHeader(format!(
+            "unknown version {:#04x}",
+            header.version
+        ))),
+    }
+}
+
+///Merkt sich die Initiator SPIs aller gesendeten Proben eines Ziels
+/// und zählt die Nachrichten, die keiner offenen Probe zugeordnet werden konnten
+#[derive(Debug, Clone, Default)]
+pub struct ProbeTracker {
+    ///Initiator SPIs aller bisher gesendeten Proben
+    sent: HashSet<u64>,
+    ///verworfene Nachrichten
+    discarded: DiscardedResponses,
+}
+
+impl ProbeTracker {
+    ///Erstellt einen Tracker ohne gesendete Proben
+    pub fn new() -> Self {
+        ProbeTracker::default()
+    }
+
+    ///Trägt eine gesendete Probe ein, spätere Antworten auf sie werden als verspätet erkannt
+    pub fn register(&mut self, probe: &Probe) {
+        self.sent.insert(probe.initiator_spi);
+    }
+
+    ///Gibt an, ob die Nachricht die Antwort auf die offene Probe ist.
+    /// Andere Nachrichten werden gezählt und verworfen
+    pub fn accept(&mut self, probe: &Probe, response: &[u8]) -> bool {
+        match check_response(probe, response) {
+            Ok(()) => true,
+            Err(error) => {
+                self.discard(error);
+                false
+            }
+        }
+    }
+
+    ///Zählt eine verworfene Nachricht in der Kategorie ihres Fehlers
+    pub fn discard(&mut self, error: ScanError) {
+        match error {
+            ScanError::UnexpectedSpi { received, .. } if self.sent.contains(&received) => {
+                debug!(
+                    "Discarded late response to earlier probe {:#018x}",
+                    received
+                );
+                self.discarded.late += 1;
+            }
+            error @ ScanError::UnexpectedSpi { .. } => {
+                debug!("Discarded response: {}", error);
+                self.discarded.unknown_spi += 1;
+            }
+            error @ ScanError::TruncatedResponse { .. } => {
+                debug!("Discarded response: {}", error);
+                self.discarded.truncated += 1;
+            }
+            error @ ScanError::OverlongResponse { .. } => {
+                debug!("Discarded response: {}", error);
+                self.discarded.overlong += 1;
+            }
+            error @ ScanError::NonIkeMessage(_) => {
+                debug!("Discarded message: {}", error);
+                self.discarded.non_ike += 1;
+            }
+            error => {
+                debug!("Discarded response: {}", error);
+                self.discarded.invalid_header += 1;
+            }
+        }
+    }
+
+    ///Bisher verworfene Nachrichten
+    pub fn discarded(&self) -> DiscardedResponses {
+        self.discarded.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INITIATOR_SPI: u64 = 0x0102_0304_0506_0708;
+
+    ///IkeV1 Main Mode Antwort mit der Längenangabe 'length' und 'received' Bytes
+    fn response(length: u32, received: usize) -> Vec<u8> {
+        let mut response = INITIATOR_SPI.to_be_bytes().to_vec();
+        response.extend_from_slice(&[0x11; 8]);
+        response.extend_from_slice(&[0, 0x10, 2, 0, 0, 0, 0, 0]);
+        response.extend_from_slice(&length.to_be_bytes());
+        response.resize(received, 0);
+        response
+    }
+
+    #[test]
+    fn accepts_matching_response() {
+        let probe = Probe::new(INITIATOR_SPI, 1);
+        assert!(check_response(&probe, &response(28, 28)).is_ok());
+    }
+
+    #[test]
+    fn rejects_wrong_length_and_spi() {
+        let probe = Probe::new(INITIATOR_SPI, 1);
+        assert!(matches!(
+            check_response(&probe, &response(40, 28)),
+            Err(ScanError::TruncatedResponse { .. })
+        ));
+        assert!(matches!(
+            check_response(&probe, &response(28, 32)),
+            Err(ScanError::OverlongResponse { .. })
+        ));
+        let other = Probe::new(INITIATOR_SPI + 1, 1);
+        assert!(matches!(
+            check_response(&other, &response(28, 28)),
+            Err(ScanError::UnexpectedSpi { .. })
+        ));
+    }
+
+    #[test]
+    fn discovery_ignores_length_and_version() {
+        let probe = Probe::new(INITIATOR_SPI, 2).any_version();
+        assert!(check_response(&probe, &response(40, 28)).is_ok());
+        assert!(check_response(&probe, &response(28, 32)).is_ok());
+        assert!(matches!(
+            check_response(&probe, &response(28, 20)),
+            Err(ScanError::TruncatedResponse { .. })
+        ));
+    }
+
+    #[test]
+    fn tracker_counts_discarded_responses() {
+        let mut tracker = ProbeTracker::new();
+        let earlier = Probe::new(INITIATOR_SPI, 1);
+        let current = Probe::new(INITIATOR_SPI + 1, 1);
+        tracker.register(&earlier);
+        tracker.register(&current);
+        assert!(!tracker.accept(&current, &response(28, 28)));
+        assert!(!tracker.accept(&current, &response(28, 10)));
+        let discarded = tracker.discarded();
+        assert_eq!(discarded.late, 1);
+        assert_eq!(discarded.truncated, 1);
+        assert_eq!(discarded.total(), 2);
+    }
+}
@@ -0,0 +1,778 @@
+//! # Bike-Scan
+//! das folgende Modul enthält die Probe-Engine für Scans vieler Ziele.
+//! Alle Ziele teilen sich einen unverbundenen UDP Socket je Adressfamilie,
+//! jedes Ziel bekommt einen EngineTransport, über den die Probe-Logik wie über jeden Transport sendet.
+//! Eine Empfangs-Task je Socket verteilt die Nachrichten nach Absenderadresse und Initiator SPI,
+//! die Wartezeiten aller Ziele verwaltet eine Timer-Task in einem Heap.
+//! So sind viele Proben an viele Ziele gleichzeitig unterwegs, ohne für jedes Ziel einen Socket zu öffnen.
+//! Unter Linux sendet und empfängt die Engine mehrere Datagramme mit einem Systemaufruf (batch.rs)
+
+use std::cmp::Ordering;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+use std::time::Duration;
+
+use log::debug;
+use log::warn;
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tokio::task::AbortHandle;
+use tokio::time::Instant;
+use zerocopy::network_endian::U64;
+use zerocopy::FromBytes;
+
+#[cfg(target_os = "linux")]
+use crate::batch;
+#[cfg(target_os = "linux")]
+use crate::batch::ReceiveBatch;
+use crate::error::ScanError;
+use crate::options::ScanOptions;
+use crate::options::TransportMode;
+use crate::transport::bind_address_for;
+use crate::transport::Transport;
+use crate::transport::NON_ESP_MARKER;
+use crate::MAX_MESSAGE_LENGTH;
+
+///Pause der Empfangs-Task nach einem vorübergehenden Fehler (z.B. ENOBUFS),
+/// damit sie nicht ohne Unterbrechung erneut empfängt
+const RECEIVE_ERROR_BACKOFF: Duration = Duration::from_millis(10);
+
+///Probe-Engine mit einem unverbundenen UDP Socket je Adressfamilie für alle Ziele.
+/// Klone verwenden dieselben Sockets, die Engine wird beendet, wenn der letzte Klon
+/// und der letzte EngineTransport freigegeben wurden
+#[derive(Clone)]
+pub struct ProbeEngine {
+    state: Arc<EngineState>,
+}
+
+///Gemeinsamer Zustand der Engine und ihrer Transporte
+struct EngineState {
+    ///lokale Adresse aus den Einstellungen
+    bind_address: SocketAddr,
+    ///Nachrichten mit Non-ESP Marker senden und empfangen (NAT-Traversal)
+    non_esp_marker: bool,
+    ///Anzahl der Datagramme je sendmmsg/recvmmsg Aufruf
+    batch_size: usize,
+    ///gebundene Sockets, höchstens einer je Adressfamilie
+    sockets: tokio::sync::Mutex<Vec<SharedSocket>>,
+    ///Zuordnung der empfangenen Nachrichten zu den Transporten
+    routes: Arc<Mutex<Routes>>,
+    ///Wartezeiten für die Timer-Task
+    timers: mpsc::UnboundedSender<Timer>,
+    ///Sende- und Empfangs-Tasks der Sockets, werden mit der Engine beendet
+    tasks: Mutex<Vec<AbortHandle>>,
+    ///nächste freie Nummer für einen Transport
+    next_endpoint: AtomicU64,
+}
+
+impl Drop for EngineState {
+    fn drop(&mut self) {
+        for task in lock(&self.tasks).iter() {
+            task.abort();
+        }
+    }
+}
+
+///Ausgabe ohne Sockets und Routen
+impl fmt::Debug for ProbeEngine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProbeEngine")
+            .field("bind_address", &self.state.bind_address)
+            .field("non_esp_marker", &self.state.non_esp_marker)
+            .field("batch_size", &self.state.batch_size)
+            .finish()
+    }
+}
+
+impl ProbeEngine {
+    ///Erstellt die Engine für UDP oder NAT-Traversal mit der lokalen Adresse aus den Einstellungen.
+    /// Die Sockets werden erst gebunden, wenn ein Ziel ihrer Adressfamilie geöffnet wird.
+    /// Unter Linux werden bis zu 'batch_size' Datagramme mit einem Systemaufruf gesendet und empfangen.
+    /// Muss innerhalb einer Tokio Runtime aufgerufen werden, da die Timer-Task gestartet wird
+    pub fn new(options: &ScanOptions) -> Result<Self, ScanError> {
+        let non_esp_marker = match options.transport {
+            TransportMode::Udp => false,
+            TransportMode::NatTraversal => true,
+            TransportMode::Tcp => {
+                return Err(ScanError::InvalidInput(
+                    "the probe engine only supports UDP and NAT-T".to_string(),
+                ))
+            }
+        };
+        if options.batch_size == 0 {
+            return Err(ScanError::InvalidInput(
+                "batch size must not be zero".to_string(),
+            ));
+        }
+        let (timers, requests) = mpsc::unbounded_channel();
+        tokio::spawn(run_timers(requests));
+        Ok(ProbeEngine {
+            state: Arc::new(EngineState {
+                bind_address: options.bind_address,
+                non_esp_marker,
+                batch_size: options.batch_size,
+                sockets: tokio::sync::Mutex::new(vec![]),
+                routes: Arc::new(Mutex::new(Routes::default())),
+                timers,
+                tasks: Mutex::new(vec![]),
+                next_endpoint: AtomicU64::new(0),
+            }),
+        })
+    }
+
+    ///Öffnet einen Transport zum Server über den Socket seiner Adressfamilie
+    pub async fn open(&self, remote_addr: SocketAddr) -> Result<EngineTransport, ScanError> {
+        let socket = self.socket_for(remote_addr).await?;
+        let socket_addr = socket.socket.local_addr()?;
+        let local_addr =
+            SocketAddr::new(local_ip_for(socket_addr, remote_addr)?, socket_addr.port());
+        let endpoint = self
+            .state
+            .next_endpoint
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let (sender, events) = mpsc::unbounded_channel();
+        lock(&self.state.routes).endpoints.insert(
+            endpoint,
+            Endpoint {
+                remote_addr,
+                events: sender.clone(),
+            },
+        );
+        Ok(EngineTransport {
+            state: self.state.clone(),
+            socket,
+            endpoint,
+            remote_addr,
+            local_addr,
+            sender,
+            events,
+            sequence: 0,
+            initiator_spi: None,
+        })
+    }
+
+    ///Gibt den Socket der Adressfamilie des Servers zurück, beim ersten Ziel der Familie wird er gebunden
+    async fn socket_for(&self, remote_addr: SocketAddr) -> Result<SharedSocket, ScanError> {
+        let bind_address = bind_address_for(self.state.bind_address, remote_addr)?;
+        let mut sockets = self.state.sockets.lock().await;
+        for shared in sockets.iter() {
+            if shared.socket.local_addr()?.is_ipv4() == bind_address.is_ipv4() {
+                return Ok(shared.clone());
+            }
+        }
+        let socket = Arc::new(UdpSocket::bind(bind_address).await?);
+        debug!("Probe engine bound to {}", socket.local_addr()?);
+        let shared = self.start_socket(socket);
+        sockets.push(shared.clone());
+        Ok(shared)
+    }
+
+    ///Startet die Tasks eines neu gebundenen Sockets.
+    /// Unter Linux werden bei einer Batch-Größe über 1 sendmmsg und recvmmsg verwendet,
+    /// sonst wird jedes Datagramm einzeln gesendet und mit recv_from empfangen
+    fn start_socket(&self, socket: Arc<UdpSocket>) -> SharedSocket {
+        let routes = self.state.routes.clone();
+        let non_esp_marker = self.state.non_esp_marker;
+        let stopped = Arc::new(AtomicBool::new(false));
+        let receiver = Receiver {
+            socket: socket.clone(),
+            routes,
+            stopped: stopped.clone(),
+        };
+        let mut tasks = lock(&self.state.tasks);
+        #[cfg(target_os = "linux")]
+        if self.state.batch_size > 1 {
+            let batch_size = self.state.batch_size;
+            let (outgoing, queue) = mpsc::unbounded_channel();
+            let sender = tokio::spawn(run_batch_sender(socket.clone(), queue, batch_size));
+            let receiver = tokio::spawn(run_batch_receiver(receiver, non_esp_marker, batch_size));
+            tasks.extend([sender.abort_handle(), receiver.abort_handle()]);
+            return SharedSocket {
+                socket,
+                outgoing: Some(outgoing),
+                stopped,
+            };
+        }
+        let receiver = tokio::spawn(run_receiver(receiver, non_esp_marker));
+        tasks.push(receiver.abort_handle());
+        SharedSocket {
+            socket,
+            outgoing: None,
+            stopped,
+        }
+    }
+}
+
+///Gebundener Socket der Engine
+#[derive(Debug, Clone)]
+struct SharedSocket {
+    socket: Arc<UdpSocket>,
+    ///Warteschlange der Sende-Task, None wenn jedes Datagramm direkt gesendet wird
+    outgoing: Option<mpsc::UnboundedSender<Outgoing>>,
+    ///die Empfangs-Task ist nach einem dauerhaften Fehler beendet
+    stopped: Arc<AtomicBool>,
+}
+
+///Datagramm in der Warteschlange der Sende-Task
+#[derive(Debug)]
+struct Outgoing {
+    datagram: Vec<u8>,
+    remote_addr: SocketAddr,
+    ///Ergebnis des Sendens für den Transport
+    result: oneshot::Sender<io::Result<()>>,
+}
+
+///Transport zu einem Server über den gemeinsamen Socket einer ProbeEngine.
+/// Antworten werden über den Initiator SPI der gesendeten Nachrichten zugeordnet
+pub struct EngineTransport {
+    state: Arc<EngineState>,
+    socket: SharedSocket,
+    ///Nummer des Transports in den Routen der Engine
+    endpoint: u64,
+    remote_addr: SocketAddr,
+    local_addr: SocketAddr,
+    ///Kanal für Ereignisse an diesen Transport, wird den Timern mitgegeben
+    sender: mpsc::UnboundedSender<Event>,
+    events: mpsc::UnboundedReceiver<Event>,
+    ///Nummer der aktuellen Wartezeit, ältere Timer werden ignoriert
+    sequence: u64,
+    ///Initiator SPI der aktuellen Probe. Die Route einer Probe wird entfernt, sobald die nächste Probe
+    /// gesendet oder der Transport geschlossen wird, verspätete Antworten kommen über die Absenderadresse an
+    initiator_spi: Option<u64>,
+}
+
+///Ausgabe ohne Kanäle
+impl fmt::Debug for EngineTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EngineTransport")
+            .field("remote_addr", &self.remote_addr)
+            .field("local_addr", &self.local_addr)
+            .finish()
+    }
+}
+
+impl Drop for EngineTransport {
+    fn drop(&mut self) {
+        let mut routes = lock(&self.state.routes);
+        if let Some(initiator_spi) = self.initiator_spi {
+            routes.remove_probe(self.remote_addr, initiator_spi, self.endpoint);
+        }
+        routes.endpoints.remove(&self.endpoint);
+    }
+}
+
+impl Transport for EngineTransport {
+    async fn send(&mut self, message: &[u8]) -> Result<(), ScanError> {
+        if let Some(initiator_spi) = initiator_spi(message) {
+            let mut routes = lock(&self.state.routes);
+            //the previous probe is finished, late replies to it take the route by source address
+            if let Some(previous) = self
+                .initiator_spi
+                .replace(initiator_spi)
+                .filter(|previous| *previous != initiator_spi)
+            {
+                routes.remove_probe(self.remote_addr, previous, self.endpoint);
+            }
+            routes
+                .probes
+                .insert((self.remote_addr, initiator_spi), self.endpoint);
+        }
+        let mut datagram = Vec::with_capacity(NON_ESP_MARKER.len() + message.len());
+        if self.state.non_esp_marker {
+            datagram.extend_from_slice(&NON_ESP_MARKER);
+        }
+        datagram.extend_from_slice(message);
+        match &self.socket.outgoing {
+            Some(outgoing) => {
+                let (result, sent) = oneshot::channel();
+                outgoing
+                    .send(Outgoing {
+                        datagram,
+                        remote_addr: self.remote_addr,
+                        result,
+                    })
+                    .map_err(|_| engine_stopped())?;
+                sent.await.map_err(|_| engine_stopped())??;
+            }
+            None => {
+                self.socket
+                    .socket
+                    .send_to(&datagram, self.remote_addr)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn receive(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, ScanError> {
+        if self
+            .socket
+            .stopped
+            .load(std::sync::atomic::Ordering::Relaxed)
+        {
+            return Err(engine_stopped());
+        }
+        self.sequence += 1;
+        let timer = Timer {
+            deadline: Instant::now() + timeout,
+            sequence: self.sequence,
+            events: self.sender.clone(),
+        };
+        if self.state.timers.send(timer).is_err() {
+            return Err(engine_stopped());
+        }
+        loop {
+            match self.events.recv().await {
+                Some(Event::Datagram(message)) => {
+                    let length = message.len().min(buf.len());
+                    buf[..length].copy_from_slice(&message[..length]);
+                    return Ok(length);
+                }
+                Some(Event::Timeout(sequence)) if sequence == self.sequence => {
+                    return Err(ScanError::Timeout)
+                }
+                //timer of an earlier receive that already got its message
+                Some(Event::Timeout(_)) => continue,
+                Some(Event::Stopped) | None => return Err(engine_stopped()),
+            }
+        }
+    }
+
+    fn local_addr(&self) -> Result<SocketAddr, ScanError> {
+        Ok(self.local_addr)
+    }
+
+    fn peer_addr(&self) -> Result<SocketAddr, ScanError> {
+        Ok(self.remote_addr)
+    }
+}
+
+///Ereignis für einen Transport
+#[derive(Debug)]
+enum Event {
+    ///empfangene Ike Nachricht (ohne Non-ESP Marker)
+    Datagram(Vec<u8>),
+    ///die Wartezeit mit dieser Nummer ist abgelaufen
+    Timeout(u64),
+    ///der Socket des Transports kann nicht mehr empfangen
+    Stopped,
+}
+
+///Zuordnung der empfangenen Nachrichten zu den Transporten
+#[derive(Debug, Default)]
+struct Routes {
+    ///Transport je Absenderadresse und Initiator SPI
+    probes: HashMap<(SocketAddr, u64), u64>,
+    ///Adresse und Ereignis-Kanal je Transport
+    endpoints: HashMap<u64, Endpoint>,
+}
+
+///Ein geöffneter Transport in den Routen
+#[derive(Debug)]
+struct Endpoint {
+    remote_addr: SocketAddr,
+    events: mpsc::UnboundedSender<Event>,
+}
+
+impl Routes {
+    ///Entfernt die Route einer Probe, falls sie noch zu diesem Transport gehört
+    fn remove_probe(&mut self, remote_addr: SocketAddr, initiator_spi: u64, endpoint: u64) {
+        let key = (remote_addr, initiator_spi);
+        if self.probes.get(&key) == Some(&endpoint) {
+            self.probes.remove(&key);
+        }
+    }
+
+    ///Sucht den Transport für eine Nachricht. Ist der Initiator SPI unbekannt,
+    /// bekommt ein Transport zum Absender die Nachricht, damit er sie zählen kann
+    fn route(&self, source: SocketAddr, initiator_spi: Option<u64>) -> Option<&Endpoint> {
+        initiator_spi
+            .and_then(|initiator_spi| self.probes.get(&(source, initiator_spi)))
+            .and_then(|endpoint| self.endpoints.get(endpoint))
+            //unknown SPIs are rare, a linear search is good enough
+            .or_else(|| {
+                self.endpoints
+                    .values()
+                    .find(|endpoint| endpoint.remote_addr == source)
+            })
+    }
+}
+
+///Wartezeit eines Transports, sortiert nach dem Ablaufzeitpunkt
+#[derive(Debug)]
+struct Timer {
+    deadline: Instant,
+    sequence: u64,
+    events: mpsc::UnboundedSender<Event>,
+}
+
+impl PartialEq for Timer {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Timer {}
+
+impl PartialOrd for Timer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Timer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.deadline, self.sequence).cmp(&(other.deadline, other.sequence))
+    }
+}
+
+///Timer-Task: hält alle Wartezeiten in einem Heap und meldet jedem Transport den Ablauf seiner Wartezeit.
+/// Endet, wenn die Engine freigegeben wurde
+async fn run_timers(mut requests: mpsc::UnboundedReceiver<Timer>) {
+    let mut timers: BinaryHeap<Reverse<Timer>> = BinaryHeap::new();
+    loop {
+        let next_deadline = timers.peek().map(|Reverse(timer)| timer.deadline);
+        tokio::select! {
+            request = requests.recv() => match request {
+                Some(timer) => timers.push(Reverse(timer)),
+                None => return,
+            },
+            _ = tokio::time::sleep_until(next_deadline.unwrap_or_else(Instant::now)),
+                if next_deadline.is_some() =>
+            {
+                let now = Instant::now();
+                while let Some(Reverse(timer)) = timers.peek() {
+                    if timer.deadline > now {
+                        break;
+                    }
+                    if let Some(Reverse(timer)) = timers.pop() {
+                        //the transport may already be closed
+                        let _ = timer.events.send(Event::Timeout(timer.sequence));
+                    }
+                }
+            }
+        }
+    }
+}
+
+///Empfangs-Task eines Sockets mit den Routen, an die sie die Nachrichten verteilt
+struct Receiver {
+    socket: Arc<UdpSocket>,
+    routes: Arc<Mutex<Routes>>,
+    ///wird bei einem dauerhaften Fehler gesetzt, siehe SharedSocket
+    stopped: Arc<AtomicBool>,
+}
+
+impl Receiver {
+    ///Behandelt einen Empfangsfehler und gibt zurück, ob die Task weiter empfangen soll.
+    /// Nach einem vorübergehenden Fehler wird kurz pausiert, nach einem dauerhaften Fehler
+    /// wird die Task beendet und jeder Transport dieses Sockets erhält ScanError::Socket
+    async fn recover(&self, error: io::Error) -> bool {
+        if is_transient(&error) {
+            debug!("Receive on shared socket failed: {}", error);
+            tokio::time::sleep(RECEIVE_ERROR_BACKOFF).await;
+            return true;
+        }
+        warn!(
+            "Receive on shared socket failed, stopping probe engine socket: {}",
+            error
+        );
+        self.stopped
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+        let is_ipv4 = self.socket.local_addr().map(|address| address.is_ipv4());
+        for endpoint in lock(&self.routes).endpoints.values() {
+            if is_ipv4
+                .as_ref()
+                .map_or(true, |is_ipv4| *is_ipv4 == endpoint.remote_addr.is_ipv4())
+            {
+                //the transport may be closing
+                let _ = endpoint.events.send(Event::Stopped);
+            }
+        }
+        false
+    }
+}
+
+///Gibt an, ob ein Empfangsfehler vorübergehend ist, z.B. volle Puffer des Kernels (ENOBUFS)
+/// oder ein ICMP Fehler zu einem früher gesendeten Datagramm
+fn is_transient(error: &io::Error) -> bool {
+    #[cfg(target_os = "linux")]
+    if error.raw_os_error() == Some(libc::ENOBUFS) {
+        return true;
+    }
+    matches!(
+        error.kind(),
+        io::ErrorKind::Interrupted
+            | io::ErrorKind::WouldBlock
+            | io::ErrorKind::OutOfMemory
+            | io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionReset
+    )
+}
+
+///Empfangs-Task eines Sockets: stellt jede Nachricht dem Transport zu, zu dem Absender und Initiator SPI gehören
+async fn run_receiver(receiver: Receiver, non_esp_marker: bool) {
+    let mut buf = vec![0u8; MAX_MESSAGE_LENGTH];
+    loop {
+        match receiver.socket.recv_from(&mut buf).await {
+            Ok((length, source)) => {
+                dispatch(&receiver.routes, non_esp_marker, &buf[..length], source)
+            }
+            Err(error) => {
+                if !receiver.recover(error).await {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+///Empfangs-Task eines Sockets unter Linux: empfängt bis zu 'batch_size' Datagramme mit einem recvmmsg Aufruf
+#[cfg(target_os = "linux")]
+async fn run_batch_receiver(receiver: Receiver, non_esp_marker: bool, batch_size: usize) {
+    let mut batch = ReceiveBatch::new(batch_size, MAX_MESSAGE_LENGTH);
+    loop {
+        let count = match batch.receive(&receiver.socket).await {
+            Ok(count) => count,
+            Err(error) => {
+                if !receiver.recover(error).await {
+                    return;
+                }
+                continue;
+            }
+        };
+        for index in 0..count {
+            if let Some((datagram, source)) = batch.datagram(index) {
+                dispatch(&receiver.routes, non_esp_marker, datagram, source);
+            }
+        }
+    }
+}
+
+///Sende-Task eines Sockets unter Linux: sendet alle Datagramme, die sich in der Warteschlange
+/// angesammelt haben, mit sendmmsg (höchstens 'batch_size' je Aufruf)
+#[cfg(target_os = "linux")]
+async fn run_batch_sender(
+    socket: Arc<UdpSocket>,
+    mut queue: mpsc::UnboundedReceiver<Outgoing>,
+    batch_size: usize,
+) {
+    let mut pending = Vec::with_capacity(batch_size);
+    while let Some(first) = queue.recv().await {
+        pending.push(first);
+        while pending.len() < batch_size {
+            match queue.try_recv() {
+                Ok(next) => pending.push(next),
+                Err(_) => break,
+            }
+        }
+        let datagrams: Vec<(&[u8], SocketAddr)> = pending
+            .iter()
+            .map(|outgoing: &Outgoing| (outgoing.datagram.as_slice(), outgoing.remote_addr))
+            .collect();
+        let results = batch::send_batch(&socket, &datagrams).await;
+        for (outgoing, result) in pending.drain(..).zip(results) {
+            //the transport may be closing
+            let _ = outgoing.result.send(result);
+        }
+    }
+}
+
+///Stellt eine empfangene Nachricht dem Transport zu, zu dem Absender und Initiator SPI gehören
+fn dispatch(routes: &Mutex<Routes>, non_esp_marker: bool, datagram: &[u8], source: SocketAddr) {
+    let message = if non_esp_marker {
+        match datagram.strip_prefix(&NON_ESP_MARKER) {
+            Some(message) => message,
+            None => {
+                debug!("Discarded message without non-ESP marker from {}", source);
+                return;
+            }
+        }
+    } else {
+        datagram
+    };
+    match lock(routes).route(source, initiator_spi(message)) {
+        Some(endpoint) => {
+            //the transport may be closing
+            let _ = endpoint.events.send(Event::Datagram(message.to_vec()));
+        }
+        None => debug!("Discarded message from {} without open scan", source),
+    }
+}
+
+///Initiator SPI aus den ersten acht Bytes einer Ike Nachricht
+fn initiator_spi(message: &[u8]) -> Option<u64> {
+    U64::read_from_prefix(message).map(|initiator_spi| initiator_spi.get())
+}
+
+///Lokale IP Adresse für den Server, wird für die NAT Detection verwendet.
+/// Ist der Socket an keine konkrete Adresse gebunden, wählt das Betriebssystem die Adresse der Route zum Server,
+/// ohne dass dabei ein Paket gesendet wird
+fn local_ip_for(
+    socket_addr: SocketAddr,
+    remote_addr: SocketAddr,
+) -> Result<std::net::IpAddr, ScanError> {
+    if !socket_addr.ip().is_unspecified() {
+        return Ok(socket_addr.ip());
+    }
+    let probe_socket = std::net::UdpSocket::bind(SocketAddr::new(socket_addr.ip(), 0))?;
+    probe_socket.connect(remote_addr)?;
+    Ok(probe_socket.local_addr()?.ip())
+}
+
+///Fehler, wenn die Tasks der Engine nicht mehr laufen (z.B. beim Beenden der Runtime)
+fn engine_stopped() -> ScanError {
+    ScanError::Socket(io::Error::other("probe engine stopped"))
+}
+
+///Sperrt den Mutex, auch wenn eine andere Task beim Halten der Sperre abgebrochen ist
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::correlation::Probe;
+    use crate::correlation::ProbeTracker;
+
+    use super::*;
+
+    ///IkeV1 Main Mode Header ohne Payloads mit dem Initiator SPI 'initiator_spi'
+    fn message(initiator_spi: u64) -> Vec<u8> {
+        let mut message = initiator_spi.to_be_bytes().to_vec();
+        message.extend_from_slice(&[0; 8]);
+        message.extend_from_slice(&[0, 0x10, 2, 0, 0, 0, 0, 0, 0, 0, 0, 28]);
+        message
+    }
+
+    ///Engine auf der Loopback Adresse
+    fn loopback_engine() -> ProbeEngine {
+        let options = ScanOptions {
+            bind_address: SocketAddr::from(([127, 0, 0, 1], 0)),
+            ..ScanOptions::default()
+        };
+        ProbeEngine::new(&options).unwrap()
+    }
+
+    #[tokio::test]
+    async fn replies_are_dispatched_by_source_and_spi() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let engine = loopback_engine();
+        let mut first = engine.open(server_addr).await.unwrap();
+        let mut second = engine.open(server_addr).await.unwrap();
+        first.send(&message(1)).await.unwrap();
+        second.send(&message(2)).await.unwrap();
+
+        //reply in reverse order and with an unknown SPI, all from the same address
+        let mut buf = [0u8; 64];
+        let mut source = None;
+        for _ in 0..2 {
+            let (_, from) = server.recv_from(&mut buf).await.unwrap();
+            source = Some(from);
+        }
+        let source = source.unwrap();
+        for initiator_spi in [2, 1, 3] {
+            server
+                .send_to(&message(initiator_spi), source)
+                .await
+                .unwrap();
+        }
+
+        let timeout = Duration::from_secs(5);
+        let length = first.receive(&mut buf, timeout).await.unwrap();
+        assert_eq!(buf[..length], message(1));
+        let length = second.receive(&mut buf, timeout).await.unwrap();
+        assert_eq!(buf[..length], message(2));
+
+        //the unknown SPI takes the fallback route to one of the transports to the sender
+        let mut tracker = ProbeTracker::new();
+        let short = Duration::from_millis(200);
+        for (transport, initiator_spi) in [(&mut first, 1), (&mut second, 2)] {
+            if let Ok(length) = transport.receive(&mut buf, short).await {
+                let probe = Probe::new(initiator_spi, 1);
+                assert!(!tracker.accept(&probe, &buf[..length]));
+            }
+        }
+        assert_eq!(tracker.discarded().unknown_spi, 1);
+        assert_eq!(tracker.discarded().total(), 1);
+    }
+
+    #[tokio::test]
+    async fn routes_of_finished_probes_are_removed() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let engine = loopback_engine();
+        let mut first = engine.open(server_addr).await.unwrap();
+        let mut second = engine.open(server_addr).await.unwrap();
+        for initiator_spi in [1, 1, 2, 3] {
+            first.send(&message(initiator_spi)).await.unwrap();
+        }
+        second.send(&message(4)).await.unwrap();
+        let probes = |engine: &ProbeEngine| {
+            let mut probes: Vec<u64> = lock(&engine.state.routes)
+                .probes
+                .keys()
+                .map(|(_, initiator_spi)| *initiator_spi)
+                .collect();
+            probes.sort_unstable();
+            probes
+        };
+        assert_eq!(probes(&engine), vec![3, 4]);
+        drop(second);
+        assert_eq!(probes(&engine), vec![3]);
+        assert_eq!(lock(&engine.state.routes).endpoints.len(), 1);
+        drop(first);
+        assert!(probes(&engine).is_empty());
+    }
+
+    #[test]
+    fn transient_receive_errors_are_retried() {
+        assert!(is_transient(&io::Error::from(io::ErrorKind::Interrupted)));
+        assert!(is_transient(&io::Error::from(
+            io::ErrorKind::ConnectionRefused
+        )));
+        #[cfg(target_os = "linux")]
+        assert!(is_transient(&io::Error::from_raw_os_error(libc::ENOBUFS)));
+        assert!(!is_transient(&io::Error::from(io::ErrorKind::NotConnected)));
+    }
+
+    #[tokio::test]
+    async fn fatal_receive_error_stops_transports() {
+        let options = ScanOptions {
+            bind_address: SocketAddr::from(([127, 0, 0, 1], 0)),
+            ..ScanOptions::default()
+        };
+        let engine = ProbeEngine::new(&options).unwrap();
+        let mut transport = engine
+            .open(SocketAddr::from(([127, 0, 0, 1], 9)))
+            .await
+            .unwrap();
+        let receiver = Receiver {
+            socket: transport.socket.socket.clone(),
+            routes: engine.state.routes.clone(),
+            stopped: transport.socket.stopped.clone(),
+        };
+        assert!(
+            !receiver
+                .recover(io::Error::from(io::ErrorKind::NotConnected))
+                .await
+        );
+        let mut buf = [0u8; 16];
+        assert!(matches!(
+            transport.receive(&mut buf, Duration::from_secs(5)).await,
+            Err(ScanError::Socket(_))
+        ));
+    }
+}
@@ -0,0 +1,115 @@
+//! # Bike-Scan
+//! das folgende Modul enthält die Fehler, die bei einem Scan auftreten können.
+//! Alle öffentlichen Funktionen geben ScanError zurück, anstatt abzubrechen
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use openssl::error::ErrorStack;
+
+///Fehler eines Scans
+#[derive(Debug)]
+pub enum ScanError {
+    ///Fehler beim Binden, Senden oder Empfangen über den Socket
+    Socket(io::Error),
+    ///der Server hat innerhalb der Wartezeit nicht geantwortet
+    Timeout,
+    ///der Port des Servers ist nicht erreichbar (ICMP Port Unreachable)
+    PortUnreachable,
+    ///der Server hat die TCP Verbindung geschlossen
+    ConnectionClosed,
+    ///die Antwort ist kürzer als erwartet
+    TruncatedResponse {
+        ///erwartete Mindestlänge in Bytes
+        expected: usize,
+        ///empfangene Bytes
+        received: usize,
+    },
+    ///die Antwort ist länger als die Längenangabe im Ike Header
+    OverlongResponse {
+        ///Länge laut Ike Header
+        expected: usize,
+        ///empfangene Bytes
+        received: usize,
+    },
+    ///die Antwort konnte nicht verarbeitet werden
+    MalformedResponse(String),
+    ///die empfangene Nachricht ist keine Ike Nachricht (z.B. NAT Keepalive oder ESP Paket ohne Non-ESP Marker),
+    /// sie wird verworfen und der Empfang fortgesetzt
+    NonIkeMessage(String),
+    ///der Initiator Security Parameter Index der Antwort passt nicht zur Anfrage
+    UnexpectedSpi {
+        ///gesendeter Security Parameter Index
+        expected: u64,
+        ///empfangener Security Parameter Index
+        received: u64,
+    },
+    ///der Header der Antwort passt nicht zur Anfrage (Version, Austauschtyp, Flags oder Message ID)
+    InvalidHeader(String),
+    ///Fehler von OpenSSL beim Erzeugen der Key-Exchange Daten
+    Crypto(ErrorStack),
+    ///ungültige Eingabe beim Erstellen eines Pakets oder in den Einstellungen
+    InvalidInput(String),
+    ///die Task des Scans ist abgebrochen (z.B. durch einen Panic)
+    TaskFailed(String),
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScanError::Socket(error) => write!(f, "socket error: {}", error),
+            ScanError::Timeout => write!(f, "no response from target"),
+            ScanError::PortUnreachable => write!(f, "port unreachable"),
+            ScanError::ConnectionClosed => write!(f, "connection closed by target"),
+            ScanError::TruncatedResponse { expected, received } => write!(
+                f,
+                "truncated response: expected at least {} bytes, received {}",
+                expected, received
+            ),
+            ScanError::OverlongResponse { expected, received } => write!(
+                f,
+                "over-long response: header length {} bytes, received {}",
+                expected, received
+            ),
+            ScanError::MalformedResponse(reason) => write!(f, "malformed response: {}", reason),
+            ScanError::NonIkeMessage(reason) => write!(f, "not an IKE message: {}", reason),
+            ScanError::UnexpectedSpi { expected, received } => write!(
+                f,
+                "unexpected initiator SPI: expected {:#018x}, received {:#018x}",
+                expected, received
+            ),
+            ScanError::InvalidHeader(reason) => write!(f, "unexpected IKE header: {}", reason),
+            ScanError::Crypto(error) => write!(f, "crypto error: {}", error),
+            ScanError::InvalidInput(reason) => write!(f, "invalid input: {}", reason),
+            ScanError::TaskFailed(reason) => write!(f, "scan task failed: {}", reason),
+        }
+    }
+}
+
+impl Error for ScanError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ScanError::Socket(error) => Some(error),
+            ScanError::Crypto(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+///Ein abgelehnter Empfang auf einem verbundenen UDP Socket bedeutet ICMP Port Unreachable
+impl From<io::Error> for ScanError {
+    fn from(value: io::Error) -> Self {
+        match value.kind() {
+            io::ErrorKind::ConnectionRefused => ScanError::PortUnreachable,
+            io::ErrorKind::TimedOut => ScanError::Timeout,
+            _ => ScanError::Socket(value),
+        }
+    }
+}
+
+impl From<ErrorStack> for ScanError {
+    fn from(value: ErrorStack) -> Self {
+        ScanError::Crypto(value)
+    }
+}
@@ -2,15 +2,17 @@
 //! das folgende Modul erstellt ein Paket für Ike Version 1
 //! Es werden die Structs für den Aufbau definiert und erläutert
 
-use std::io;
-use std::mem::size_of;
-
+use serde::Serialize;
 use zerocopy;
 use zerocopy::network_endian::*;
 use zerocopy::AsBytes;
 use zerocopy::FromBytes;
 use zerocopy::FromZeroes;
 
+use crate::algorithms::EncryptionAlgorithmV1;
+use crate::error::ScanError;
+use crate::report::AcceptedTransformV1;
+
 ///Ike Wrapper Struct
 /// dieses Struct umschließt alle Bestandteile die für ein IkeV1 Paket notwendig sind
 #[derive(Debug, Clone)]
@@ -25,6 +27,8 @@ pub struct IkeV1 {
     pub proposal_payload: ProposalPayload,
     ///der Transform Payload wird als Vektor behandelt, da so die Attribute verarbeitet werden können
     pub transform: Vec<Transform>,
+    ///Hersteller-IDs, die nach dem Security Association Payload gesendet werden
+    pub vendor_ids: Vec<Vec<u8>>,
 }
 
 impl IkeV1 {
@@ -36,8 +40,10 @@ impl IkeV1 {
     /// 'encryption' ist der Verschlüsselungsalgorithmus
     /// 'life_type_attribute' ist die Zeiteinheit der Lebensdauer der Transformation
     /// 'life_duration_attribute' ist der Wert der Lebenszeit.
+    /// Bei Algorithmen mit variabler Schlüssellänge (z.B. AES und Camellia) wird für jede Schlüssellänge
+    /// eine eigene Transformation mit dem Key Length Attribut erstellt.
     /// Zu Beginn wird ein leerer Vektor erzeugt, in dem die Attribute, die in der For-Schleife erstellt werden, gepusht werden
-    /// es gibt insgesamt 5880 Möglichkeiten, die Attribute miteinander zu kombinieren
+    /// es gibt insgesamt 19488 Möglichkeiten, die Attribute miteinander zu kombinieren
     /// der Vektor wird am Ende der Funktion ausgegeben
     pub fn build_transforms() -> Vec<Transform> {
         let mut transform_vec = vec![];
@@ -46,41 +52,61 @@ impl IkeV1 {
             for diffie_group in (1..=21).chain(24..=24).chain(28..=34) {
                 for hash in 1..=6 {
                     for encryption in 1..=8 {
-                        transform_vec.push(Transform {
-                            transform_payload: TransformPayload {
-                                next_payload: payload,
-                                reserved: 0,
-                                length: U16::from(36),
-                                transform_number: 0,
-                                transform_id: 1,
-                                reserved2: U16::from(0),
-                            },
-                            encryption_attribute: Attribute {
-                                attribute_type: U16::from(AttributeType::Encryption),
-                                attribute_value_or_length: U16::from(encryption),
-                            },
-                            hash_attribute: Attribute {
-                                attribute_type: U16::from(AttributeType::HashType),
-                                attribute_value_or_length: U16::from(hash),
-                            },
-                            diffie_hellman_attribute: Attribute {
-                                attribute_type: U16::from(AttributeType::DiffieHellmanGroup),
-                                attribute_value_or_length: U16::from(diffie_group),
-                            },
-                            authentication_method_attribute: Attribute {
-                                attribute_type: U16::from(AttributeType::AuthenticationMethod),
-                                attribute_value_or_length: U16::from(auth_method),
-                            },
-                            life_type_attribute: Attribute {
-                                attribute_type: U16::from(AttributeType::LifeType),
-                                attribute_value_or_length: U16::from(1),
-                            },
-                            life_duration_attribute: Attribute {
-                                attribute_type: U16::from(AttributeType::LifeDuration),
-                                attribute_value_or_length: U16::from(4),
-                            },
-                            life_duration_value: U32::from(28800),
-                        });
+                        let key_lengths = EncryptionAlgorithmV1::from(encryption).key_lengths();
+                        let key_length_attributes: Vec<Option<Attribute>> =
+                            if key_lengths.is_empty() {
+                                vec![None]
+                            } else {
+                                key_lengths
+                                    .iter()
+                                    .map(|key_length| {
+                                        Some(Attribute {
+                                            attribute_type: U16::from(AttributeType::KeyLength),
+                                            attribute_value_or_length: U16::from(*key_length),
+                                        })
+                                    })
+                                    .collect()
+                            };
+                        for key_length_attribute in key_length_attributes {
+                            let mut transform = Transform {
+                                transform_payload: TransformPayload {
+                                    next_payload: payload,
+                                    reserved: 0,
+                                    length: Default::default(),
+                                    transform_number: 0,
+                                    transform_id: 1,
+                                    reserved2: U16::from(0),
+                                },
+                                encryption_attribute: Attribute {
+                                    attribute_type: U16::from(AttributeType::Encryption),
+                                    attribute_value_or_length: U16::from(encryption),
+                                },
+                                key_length_attribute,
+                                hash_attribute: Attribute {
+                                    attribute_type: U16::from(AttributeType::HashType),
+                                    attribute_value_or_length: U16::from(hash),
+                                },
+                                diffie_hellman_attribute: Attribute {
+                                    attribute_type: U16::from(AttributeType::DiffieHellmanGroup),
+                                    attribute_value_or_length: U16::from(diffie_group),
+                                },
+                                authentication_method_attribute: Attribute {
+                                    attribute_type: U16::from(AttributeType::AuthenticationMethod),
+                                    attribute_value_or_length: U16::from(auth_method),
+                                },
+                                life_type_attribute: Attribute {
+                                    attribute_type: U16::from(AttributeType::LifeType),
+                                    attribute_value_or_length: U16::from(1),
+                                },
+                                life_duration_attribute: Attribute {
+                                    attribute_type: U16::from(AttributeType::LifeDuration),
+                                    attribute_value_or_length: U16::from(4),
+                                },
+                                life_duration_value: U32::from(28800),
+                            };
+                            transform.calculate_length();
+                            transform_vec.push(transform);
+                        }
                     }
                 }
             }
@@ -90,9 +116,15 @@ impl IkeV1 {
 
     /// Diese Funktion stellt sicher, dass die Anzahl der Transformationen in einem Ike Paket die Grenze von 255 nicht überschreitet.
     /// Der zweite Teil der Funktion setzt das Feld 'next_payload' der letzten Transformation auf den Wert null.
-    pub fn set_transforms(&mut self, transforms: &[Transform]) {
+    /// Bei keiner oder mehr als 255 Transformationen wird ein Fehler zurückgegeben.
+    pub fn set_transforms(&mut self, transforms: &[Transform]) -> Result<(), ScanError> {
         let length = transforms.len();
-        let length_checked = u8::try_from(length).expect("Too many transforms");
+        let length_checked = u8::try_from(length)
+            .ok()
+            .filter(|length| *length > 0)
+            .ok_or_else(|| {
+                ScanError::InvalidInput(format!("{} transforms in one proposal", length))
+            })?;
         self.proposal_payload.number_of_transforms = length_checked;
         let mut change_transforms = Vec::from(transforms);
         for i in 0..length_checked {
@@ -101,23 +133,40 @@ impl IkeV1 {
                 .transform_number = i;
         }
         change_transforms[length - 1].transform_payload.next_payload = 0;
-        self.transform = change_transforms
+        self.transform = change_transforms;
+        Ok(())
     }
 
     ///In dieser Funktion wird die Länge des Ike Pakets berechnet.
-    /// Die Länge des Proposal Payloads berechnet sich aus der Größe einer Transformation multipliziert
-    /// mit der Anzahl der Transformationen addiert mit der Länge des Payload Headers.
+    /// Die Länge des Proposal Payloads berechnet sich aus der Summe der Längen der Transformationen
+    /// addiert mit der Länge des Payload Headers.
     /// Die Länge des Security Association Payloads berechnet sich aus der
     /// Länge des Proposal Payloads addiert mit der Länge des Payload Headers.
     /// Die Länge des Ike Pakets wird durch die Länge des Security Association Payload addiert mit
     /// der Länge des Headers an sich berechnet.
     pub fn calculate_length(&mut self) {
-        let proposal_length: U16 =
-            U16::from(8 + (self.proposal_payload.number_of_transforms as u16) * 36);
+        let transforms_length: u16 = self
+            .transform
+            .iter()
+            .map(|transform| transform.transform_payload.length.get())
+            .sum();
+        let proposal_length: U16 = U16::from(8 + transforms_length);
         self.proposal_payload.length = proposal_length;
         let security_association_length: U16 = proposal_length + U16::from(12);
         self.security_association_payload.sa_length = security_association_length;
-        let ike_packet_length: U32 = U32::from(28) + U32::from(security_association_length);
+        self.security_association_payload.sa_next_payload = if self.vendor_ids.is_empty() {
+            u8::from(PayloadTypeV1::NoNextPayload)
+        } else {
+            u8::from(PayloadTypeV1::VendorID)
+        };
+        let vendor_id_length: usize = self
+            .vendor_ids
+            .iter()
+            .map(|vendor_id| 4 + vendor_id.len())
+            .sum();
+        let ike_packet_length: U32 = U32::from(28)
+            + U32::from(security_association_length)
+            + U32::from(vendor_id_length as u32);
         self.header.length = ike_packet_length;
     }
 
@@ -128,20 +177,68 @@ impl IkeV1 {
         ike_v1_bytes.extend_from_slice(self.header.as_bytes());
         ike_v1_bytes.extend_from_slice(self.security_association_payload.as_bytes());
         ike_v1_bytes.extend_from_slice(self.proposal_payload.as_bytes());
-        ike_v1_bytes.extend_from_slice(self.transform.as_bytes());
+        for transform in &self.transform {
+            transform.write_bytes(&mut ike_v1_bytes);
+        }
+        for (index, vendor_id) in self.vendor_ids.iter().enumerate() {
+            let next_payload = if index + 1 < self.vendor_ids.len() {
+                PayloadTypeV1::VendorID
+            } else {
+                PayloadTypeV1::NoNextPayload
+            };
+            let payload = VendorIdPayloadV1 {
+                next_payload: u8::from(next_payload),
+                reserved: 0,
+                length: U16::from(4 + vendor_id.len() as u16),
+            };
+            ike_v1_bytes.extend_from_slice(payload.as_bytes());
+            ike_v1_bytes.extend_from_slice(vendor_id);
+        }
         ike_v1_bytes
     }
 }
 
+///Hersteller-IDs für NAT-Traversal (MD5 von "RFC 3947", "draft-ietf-ipsec-nat-t-ike-03"
+/// und "draft-ietf-ipsec-nat-t-ike-02\n")
+pub const NAT_TRAVERSAL_VENDOR_IDS: [[u8; 16]; 3] = [
+    [
+        0x4a, 0x13, 0x1c, 0x81, 0x07, 0x03, 0x58, 0x45, 0x5c, 0x57, 0x28, 0xf2, 0x0e, 0x95, 0x45,
+        0x2f,
+    ],
+    [
+        0x7d, 0x94, 0x19, 0xa6, 0x53, 0x10, 0xca, 0x6f, 0x2c, 0x17, 0x9d, 0x92, 0x15, 0x52, 0x9d,
+        0x56,
+    ],
+    [
+        0x90, 0xcb, 0x80, 0x91, 0x3e, 0xbb, 0x69, 0x6e, 0x08, 0x63, 0x81, 0xb5, 0xec, 0x42, 0x7b,
+        0x1f,
+    ],
+];
+
+///Vendor ID Payload ohne die Hersteller-ID
+#[derive(Debug, Copy, Clone, AsBytes)]
+#[repr(C, packed)]
+pub struct VendorIdPayloadV1 {
+    ///nächster Payload
+    pub next_payload: u8,
+    ///reservierter Bereich
+    pub reserved: u8,
+    ///Payload Länge
+    pub length: U16,
+}
+
 ///Wrapper Struct für die Transformationen.
-/// Dieses Struct bildet ein Transform mit den dazugehörigen Attributen ab
-#[derive(Debug, Copy, Clone, AsBytes, FromZeroes, FromBytes)]
-#[repr(packed)]
+/// Dieses Struct bildet ein Transform mit den dazugehörigen Attributen ab.
+/// Da das Key Length Attribut nur bei manchen Verschlüsselungsalgorithmen gesendet wird,
+/// werden die Bytes mit write_bytes() erzeugt
+#[derive(Debug, Copy, Clone)]
 pub struct Transform {
     ///Transform Payload
     pub transform_payload: TransformPayload,
     ///Attribut für den Verschlüsselungsalgorithmus
     pub encryption_attribute: Attribute,
+    ///Attribut für die Schlüssellänge, nur bei Algorithmen mit variabler Schlüssellänge
+    pub key_length_attribute: Option<Attribute>,
     ///Attribut für den Hash-Typ
     pub hash_attribute: Attribute,
     ///Attribut für die Diffie-Hellman Gruppe
@@ -156,9 +253,64 @@ pub struct Transform {
     pub life_duration_value: U32,
 }
 
+impl Transform {
+    ///festlegen der Länge der Transformation (mit oder ohne Key Length Attribut)
+    pub fn calculate_length(&mut self) {
+        let key_length_attribute = if self.key_length_attribute.is_some() {
+            size_of::<Attribute>()
+        } else {
+            0
+        };
+        self.transform_payload.length = U16::from(36 + key_length_attribute as u16);
+    }
+
+    ///Hängt die Bytes der Transformation an den Vektor an.
+    /// Das Key Length Attribut folgt direkt auf den Verschlüsselungsalgorithmus
+    pub fn write_bytes(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(self.transform_payload.as_bytes());
+        bytes.extend_from_slice(self.encryption_attribute.as_bytes());
+        if let Some(key_length_attribute) = &self.key_length_attribute {
+            bytes.extend_from_slice(key_length_attribute.as_bytes());
+        }
+        bytes.extend_from_slice(self.hash_attribute.as_bytes());
+        bytes.extend_from_slice(self.diffie_hellman_attribute.as_bytes());
+        bytes.extend_from_slice(self.authentication_method_attribute.as_bytes());
+        bytes.extend_from_slice(self.life_type_attribute.as_bytes());
+        bytes.extend_from_slice(self.life_duration_attribute.as_bytes());
+        bytes.extend_from_slice(self.life_duration_value.as_bytes());
+    }
+
+    ///Vorgeschlagene Schlüssellänge in Bit, None ohne Key Length Attribut
+    pub fn key_length(&self) -> Option<u16> {
+        self.key_length_attribute
+            .map(|attribute| attribute.attribute_value_or_length.get())
+    }
+
+    ///Prüft, ob die vom Server akzeptierte Transformation zu dieser Transformation gehört.
+    /// Verglichen werden Verschlüsselungsalgorithmus, Hash-Typ, Diffie-Hellman Gruppe und Authentisierungsmethode.
+    /// Die Schlüssellänge wird nur verglichen, wenn der Server sie angegeben hat,
+    /// sonst trifft die akzeptierte Transformation auf jede Schlüssellänge zu
+    pub fn matches(&self, accepted: &AcceptedTransformV1) -> bool {
+        let transform = *self;
+        let encryption = transform.encryption_attribute.attribute_value_or_length;
+        let hash = transform.hash_attribute.attribute_value_or_length;
+        let diffie_hellman = transform.diffie_hellman_attribute.attribute_value_or_length;
+        let authentication_method = transform
+            .authentication_method_attribute
+            .attribute_value_or_length;
+        encryption.get() == u16::from(accepted.encryption)
+            && hash.get() == u16::from(accepted.hash)
+            && diffie_hellman.get() == u16::from(accepted.diffie_hellman_group)
+            && authentication_method.get() == u16::from(accepted.authentication_method)
+            && accepted
+                .key_length
+                .is_none_or(|accepted_length| self.key_length() == Some(accepted_length))
+    }
+}
+
 ///Ike Header
 #[derive(Debug, Copy, Clone, AsBytes, FromBytes, FromZeroes)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct IkeV1Header {
     ///Security Parameter Index des Initiators
     pub initiator_spi: U64,
@@ -209,6 +361,8 @@ pub enum PayloadTypeV1 {
     Nonce,
     ///Notification Payload
     Notification,
+    ///Delete Payload
+    Delete,
     ///Hersteller ID Payload
     VendorID,
 }
@@ -228,13 +382,15 @@ impl From<PayloadTypeV1> for u8 {
             PayloadTypeV1::Signature => 9,
             PayloadTypeV1::Nonce => 10,
             PayloadTypeV1::Notification => 11,
+            PayloadTypeV1::Delete => 12,
             PayloadTypeV1::VendorID => 13,
         }
     }
 }
 
 impl PayloadTypeV1 {
-    fn try_from_u8(value: u8) -> Option<Self> {
+    ///Umwandeln des nummerischen Werts in den Payload-Typ, None bei unbekannten Werten
+    pub fn try_from_u8(value: u8) -> Option<Self> {
         match value {
             0 => Some(PayloadTypeV1::NoNextPayload),
             1 => Some(PayloadTypeV1::SecurityAssociation),
@@ -248,6 +404,7 @@ impl PayloadTypeV1 {
             9 => Some(PayloadTypeV1::Signature),
             10 => Some(PayloadTypeV1::Nonce),
             11 => Some(PayloadTypeV1::Notification),
+            12 => Some(PayloadTypeV1::Delete),
             13 => Some(PayloadTypeV1::VendorID),
             _ => None,
         }
@@ -255,7 +412,7 @@ impl PayloadTypeV1 {
 }
 
 ///Ike Version 1 Modi
-#[derive(Debug, Clone, AsBytes)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, AsBytes, Serialize)]
 #[repr(u8)]
 pub enum ExchangeType {
     ///Hauptmodus
@@ -284,7 +441,8 @@ impl From<ExchangeType> for u8 {
 }
 
 impl ExchangeType {
-    fn try_from_u8(value: u8) -> Option<Self> {
+    ///Umwandeln des nummerischen Werts in den Austauschtyp, None bei unbekannten Werten
+    pub fn try_from_u8(value: u8) -> Option<Self> {
         match value {
             2 => Some(ExchangeType::IdentityProtect),
             4 => Some(ExchangeType::AggressiveExchange),
@@ -299,7 +457,7 @@ impl ExchangeType {
 ///Defining Payloads
 ///Security Association Payload Version 1
 #[derive(Debug, Copy, Clone, AsBytes, FromBytes, FromZeroes)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct SecurityAssociationV1 {
     ///nächster Payload (Proposal Payload)
     pub sa_next_payload: u8,
@@ -339,7 +497,8 @@ impl From<SaSituation> for U32 {
 }
 
 impl SaSituation {
-    fn try_from_u32(value: u32) -> Option<Self> {
+    ///Umwandeln des nummerischen Werts in die Situation, None bei unbekannten Werten
+    pub fn try_from_u32(value: u32) -> Option<Self> {
         match value {
             1 => Some(SaSituation::IdentityOnly),
             2 => Some(SaSituation::Secrecy),
@@ -351,7 +510,7 @@ impl SaSituation {
 
 ///Proposal Payload
 #[derive(Debug, Copy, Clone, AsBytes, FromBytes, FromZeroes)]
-#[repr(packed)]
+#[repr(C, packed)]
 ///rfc 2408 page 28
 pub struct ProposalPayload {
     ///nächster Payload (Transformation Payload)
@@ -373,7 +532,7 @@ pub struct ProposalPayload {
 
 /// Transform Payload  (rfc 2408 seite 30)
 #[derive(Debug, Copy, Clone, AsBytes, FromBytes, FromZeroes)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct TransformPayload {
     ///nächster Payload
     /// drei: Transform folgt
@@ -393,7 +552,7 @@ pub struct TransformPayload {
 }
 ///Attribute
 #[derive(Debug, Copy, Clone, AsBytes, FromBytes, FromZeroes)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct Attribute {
     ///Attribut Typ (wird in Enum erläutert)
     pub attribute_type: U16,
@@ -418,6 +577,8 @@ pub enum AttributeType {
     LifeType,
     ///Wert der Lebensdauer
     LifeDuration,
+    ///Schlüssellänge
+    KeyLength,
 }
 
 /// das significant bit wird gesetzt
@@ -434,6 +595,170 @@ impl From<AttributeType> for U16 {
             AttributeType::DiffieHellmanGroup => 4 | 1 << 15,
             AttributeType::LifeType => 11 | 1 << 15,
             AttributeType::LifeDuration => 12,
+            AttributeType::KeyLength => 14 | 1 << 15,
         })
     }
 }
+
+impl AttributeType {
+    ///Attribut Typ ohne das significant bit, so wie er beim Parsen verglichen wird
+    pub fn type_value(self) -> u16 {
+        U16::from(self).get() & !(1 << 15)
+    }
+}
+
+///Nachrichtentypen des Notify Payloads für IkeV1 (RFC 2408, Seite 49)
+/// Werte unter 16384 sind Fehlermeldungen
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum NotifyMessageTypeV1 {
+    ///ungültiger Payload-Typ
+    InvalidPayloadType,
+    ///Domain of Interpretation wird nicht unterstützt
+    DoiNotSupported,
+    ///Situation wird nicht unterstützt
+    SituationNotSupported,
+    ///ungültiger Cookie
+    InvalidCookie,
+    ///ungültige Hauptversion
+    InvalidMajorVersion,
+    ///ungültige Nebenversion
+    InvalidMinorVersion,
+    ///ungültiger Austauschtyp
+    InvalidExchangeType,
+    ///ungültige Flags
+    InvalidFlags,
+    ///ungültige Nachrichten ID
+    InvalidMessageId,
+    ///ungültige Protokoll ID
+    InvalidProtocolId,
+    ///ungültiger Security Parameter Index
+    InvalidSpi,
+    ///ungültige Transformations ID
+    InvalidTransformId,
+    ///Attribute werden nicht unterstützt
+    AttributesNotSupported,
+    ///keine Transformation wurde akzeptiert
+    NoProposalChosen,
+    ///fehlerhafte Syntax des Proposals
+    BadProposalSyntax,
+    ///fehlerhafter Payload
+    PayloadMalformed,
+    ///ungültige Schlüsselinformationen
+    InvalidKeyInformation,
+    ///ungültige Identifizierungsinformationen
+    InvalidIdInformation,
+    ///ungültige Zertifikatskodierung
+    InvalidCertEncoding,
+    ///ungültiges Zertifikat
+    InvalidCertificate,
+    ///Zertifikatstyp wird nicht unterstützt
+    CertTypeUnsupported,
+    ///ungültige Zertifizierungsstelle
+    InvalidCertAuthority,
+    ///ungültige Hash-Informationen
+    InvalidHashInformation,
+    ///Authentisierung fehlgeschlagen
+    AuthenticationFailed,
+    ///ungültige Signatur
+    InvalidSignature,
+    ///Adressbenachrichtigung
+    AddressNotification,
+    ///Lebensdauer der Security Association
+    NotifySaLifetime,
+    ///Zertifikat nicht verfügbar
+    CertificateUnavailable,
+    ///Austauschtyp wird nicht unterstützt
+    UnsupportedExchangeType,
+    ///ungleiche Payload-Längen
+    UnequalPayloadLengths,
+    ///Lebensdauer des Responders (RFC 2407)
+    ResponderLifetime,
+    ///Replay-Status (RFC 2407)
+    ReplayStatus,
+    ///erster Kontakt (RFC 2407)
+    InitialContact,
+    ///unbekannter Wert
+    Other(u16),
+}
+
+impl From<u16> for NotifyMessageTypeV1 {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => NotifyMessageTypeV1::InvalidPayloadType,
+            2 => NotifyMessageTypeV1::DoiNotSupported,
+            3 => NotifyMessageTypeV1::SituationNotSupported,
+            4 => NotifyMessageTypeV1::InvalidCookie,
+            5 => NotifyMessageTypeV1::InvalidMajorVersion,
+            6 => NotifyMessageTypeV1::InvalidMinorVersion,
+            7 => NotifyMessageTypeV1::InvalidExchangeType,
+            8 => NotifyMessageTypeV1::InvalidFlags,
+            9 => NotifyMessageTypeV1::InvalidMessageId,
+            10 => NotifyMessageTypeV1::InvalidProtocolId,
+            11 => NotifyMessageTypeV1::InvalidSpi,
+            12 => NotifyMessageTypeV1::InvalidTransformId,
+            13 => NotifyMessageTypeV1::AttributesNotSupported,
+            14 => NotifyMessageTypeV1::NoProposalChosen,
+            15 => NotifyMessageTypeV1::BadProposalSyntax,
+            16 => NotifyMessageTypeV1::PayloadMalformed,
+            17 => NotifyMessageTypeV1::InvalidKeyInformation,
+            18 => NotifyMessageTypeV1::InvalidIdInformation,
+            19 => NotifyMessageTypeV1::InvalidCertEncoding,
+            20 => NotifyMessageTypeV1::InvalidCertificate,
+            21 => NotifyMessageTypeV1::CertTypeUnsupported,
+            22 => NotifyMessageTypeV1::InvalidCertAuthority,
+            23 => NotifyMessageTypeV1::InvalidHashInformation,
+            24 => NotifyMessageTypeV1::AuthenticationFailed,
+            25 => NotifyMessageTypeV1::InvalidSignature,
+            26 => NotifyMessageTypeV1::AddressNotification,
+            27 => NotifyMessageTypeV1::NotifySaLifetime,
+            28 => NotifyMessageTypeV1::CertificateUnavailable,
+            29 => NotifyMessageTypeV1::UnsupportedExchangeType,
+            30 => NotifyMessageTypeV1::UnequalPayloadLengths,
+            24576 => NotifyMessageTypeV1::ResponderLifetime,
+            24577 => NotifyMessageTypeV1::ReplayStatus,
+            24578 => NotifyMessageTypeV1::InitialContact,
+            other => NotifyMessageTypeV1::Other(other),
+        }
+    }
+}
+
+impl From<NotifyMessageTypeV1> for u16 {
+    fn from(value: NotifyMessageTypeV1) -> Self {
+        match value {
+            NotifyMessageTypeV1::InvalidPayloadType => 1,
+            NotifyMessageTypeV1::DoiNotSupported => 2,
+            NotifyMessageTypeV1::SituationNotSupported => 3,
+            NotifyMessageTypeV1::InvalidCookie => 4,
+            NotifyMessageTypeV1::InvalidMajorVersion => 5,
+            NotifyMessageTypeV1::InvalidMinorVersion => 6,
+            NotifyMessageTypeV1::InvalidExchangeType => 7,
+            NotifyMessageTypeV1::InvalidFlags => 8,
+            NotifyMessageTypeV1::InvalidMessageId => 9,
+            NotifyMessageTypeV1::InvalidProtocolId => 10,
+            NotifyMessageTypeV1::InvalidSpi => 11,
+            NotifyMessageTypeV1::InvalidTransformId => 12,
+            NotifyMessageTypeV1::AttributesNotSupported => 13,
+            NotifyMessageTypeV1::NoProposalChosen => 14,
+            NotifyMessageTypeV1::BadProposalSyntax => 15,
+            NotifyMessageTypeV1::PayloadMalformed => 16,
+            NotifyMessageTypeV1::InvalidKeyInformation => 17,
+            NotifyMessageTypeV1::InvalidIdInformation => 18,
+            NotifyMessageTypeV1::InvalidCertEncoding => 19,
+            NotifyMessageTypeV1::InvalidCertificate => 20,
+            NotifyMessageTypeV1::CertTypeUnsupported => 21,
+            NotifyMessageTypeV1::InvalidCertAuthority => 22,
+            NotifyMessageTypeV1::InvalidHashInformation => 23,
+            NotifyMessageTypeV1::AuthenticationFailed => 24,
+            NotifyMessageTypeV1::InvalidSignature => 25,
+            NotifyMessageTypeV1::AddressNotification => 26,
+            NotifyMessageTypeV1::NotifySaLifetime => 27,
+            NotifyMessageTypeV1::CertificateUnavailable => 28,
+            NotifyMessageTypeV1::UnsupportedExchangeType => 29,
+            NotifyMessageTypeV1::UnequalPayloadLengths => 30,
+            NotifyMessageTypeV1::ResponderLifetime => 24576,
+            NotifyMessageTypeV1::ReplayStatus => 24577,
+            NotifyMessageTypeV1::InitialContact => 24578,
+            NotifyMessageTypeV1::Other(other) => other,
+        }
+    }
+}
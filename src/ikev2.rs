@@ -2,8 +2,14 @@
 //! das folgende Modul erstellt ein Paket für Ike Version 2
 //! Es werden die Structs für den Aufbau definiert und erläutert
 
-use openssl::dh::Dh;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+
+use log::debug;
+use openssl::hash::hash;
+use openssl::hash::MessageDigest;
 use rand::random;
+use serde::Serialize;
 use zerocopy::network_endian::U16;
 use zerocopy::network_endian::U32;
 use zerocopy::network_endian::U64;
@@ -11,6 +17,11 @@ use zerocopy::AsBytes;
 use zerocopy::FromBytes;
 use zerocopy::FromZeroes;
 
+use crate::algorithms::DiffieHellmanGroup;
+use crate::error::ScanError;
+use crate::key_exchange;
+use crate::report::AcceptedTransformV2;
+
 //done(header, sa payload, proposal payload, transformationen ggf. key exchange payload)
 //todo: attribute der transforms definieren (dh gruppem, encryption, authentication, hash)
 //todo: wrapper struct fuer ikev2 paket bauen, wrapper fuer transforms mit attributen bauen (rfc)
@@ -20,6 +31,8 @@ use zerocopy::FromZeroes;
 pub struct IkeV2 {
     ///Header
     pub header: IkeV2Header,
+    ///Notify Payload mit dem Cookie des Servers, wird vor dem Security-Association-Payload gesendet
+    pub cookie: Option<NotifyV2>,
     ///Security-Association-Payload
     pub sa_payload_v2: SecurityAssociationV2,
     ///Proposal
@@ -40,6 +53,8 @@ pub struct IkeV2 {
     pub nonce_payload: NoncePayloadV2,
     ///Nonce
     pub nonce_data: Vec<u8>,
+    ///Notify Payloads nach der Nonce (z.B. NAT Detection)
+    pub notify_payloads: Vec<NotifyV2>,
 }
 impl IkeV2 {
     ///In dieser Funktion werden die Transformationen erstellt.
@@ -133,16 +148,24 @@ impl IkeV2 {
     ///Mit dieser Funktion wird sichergestellt, dass die Anzahl der Transformationen 255 in einem
     /// Proposal nicht übersteigt.
     /// Im unteren Teil wird bei der letzten Transformation next_transform auf null gesetzt
+    /// Ohne Diffie-Hellman Gruppe oder bei mehr als 255 Transformationen wird ein Fehler zurückgegeben.
     pub fn set_transforms_v2(
         &mut self,
         encryption: &[TransformAttributeV2],
         prf: &[TransformV2],
         integrity_algorithm: &[TransformV2],
         diffie_group: &[TransformV2],
-    ) {
+    ) -> Result<(), ScanError> {
         let full_length =
             encryption.len() + prf.len() + integrity_algorithm.len() + diffie_group.len();
-        let length_checked = u8::try_from(full_length).expect("Too many transforms");
+        if diffie_group.is_empty() {
+            return Err(ScanError::InvalidInput(
+                "proposal without Diffie-Hellman group".to_string(),
+            ));
+        }
+        let length_checked = u8::try_from(full_length).map_err(|_| {
+            ScanError::InvalidInput(format!("{} transforms in one proposal", full_length))
+        })?;
         self.proposal_v2.number_of_transforms = length_checked;
         self.encryption_transforms = Vec::from(encryption);
         self.prf_transform = Vec::from(prf);
@@ -150,34 +173,90 @@ impl IkeV2 {
         let mut change_transform = Vec::from(diffie_group);
         change_transform[diffie_group.len() - 1].next_transform =
             u8::from(PayloadTypeV2::NoNextPayload);
-        self.diffie_transform = change_transform
+        self.diffie_transform = change_transform;
+        Ok(())
     }
     ///Mit dieser Funktion werden die Key-Exchange-Daten generiert.
-    /// Zuerst werden die Parameter für den Diffie-Hellman-Austausch erzeugt.
-    /// Die Länge der Primzahl ist 1024 und der Generator ist 2.
-    /// Danach werden die Schlüssel erstellt.
-    /// Aus den Schlüsseln wird er Public Key extrahiert.
-    /// Aus dem Public Key wird die Primzahl extrahiert, diese bildet die Key-Exchange Daten
-    pub fn generate_key_exchange_data(&mut self) {
-        let prime_len = 1024;
-        let diffie_hellman = Dh::generate_params(prime_len, 2).unwrap();
-        let private_key = diffie_hellman.generate_key().unwrap();
-        println!("Primes: {}", private_key.prime_p().num_bytes());
-        let public_key = private_key.public_key();
-
-        let key_exchange_data = public_key
-            .to_vec_padded(private_key.prime_p().num_bytes())
-            .unwrap();
-
-        println!("{:?}", key_exchange_data);
+    /// Es wird die erste vorgeschlagene Diffie-Hellman Gruppe verwendet,
+    /// für die Key-Exchange Daten erzeugt werden können (siehe key_exchange.rs).
+    /// Ist keine solche Gruppe im Proposal enthalten, wird ein Fehler zurückgegeben
+    pub fn generate_key_exchange_data(&mut self) -> Result<(), ScanError> {
+        let group = self
+            .diffie_transform
+            .iter()
+            .map(|transform| {
+                let transform_id = transform.transform_id;
+                DiffieHellmanGroup::from(transform_id.get())
+            })
+            .find(|group| key_exchange::is_supported(*group))
+            .ok_or_else(|| {
+                ScanError::InvalidInput("no supported Diffie-Hellman group in proposal".to_string())
+            })?;
+        self.set_key_exchange_group(group)
+    }
 
+    ///Erzeugt die Key-Exchange Daten für die Gruppe und trägt die Gruppe im Key Exchange Payload ein
+    pub fn set_key_exchange_group(&mut self, group: DiffieHellmanGroup) -> Result<(), ScanError> {
+        let key_exchange_data = key_exchange::generate(group)?;
+        debug!(
+            "Key exchange data for {:?}: {} bytes",
+            group,
+            key_exchange_data.len()
+        );
+        self.key_exchange.diffie_hellman_group = U16::from(u16::from(group));
         self.key_exchange_data = key_exchange_data;
+        Ok(())
+    }
+    ///Setzt das Cookie, das der Server mit der COOKIE Notification verlangt (RFC 7296 Seite 31).
+    /// Der Notify Payload wird als erster Payload gesendet
+    pub fn set_cookie(&mut self, cookie: Vec<u8>) {
+        self.cookie = Some(NotifyV2 {
+            payload: NotifyPayloadV2 {
+                next_payload: u8::from(PayloadTypeV2::SecurityAssociation),
+                reserved: 0,
+                length: Default::default(),
+                protocol_id: 0,
+                spi_size: 0,
+                notify_message_type: U16::from(u16::from(NotifyMessageTypeV2::Cookie)),
+            },
+            data: cookie,
+        });
+        self.header.next_payload = u8::from(PayloadTypeV2::Notify);
+    }
+
+    ///Fügt die NAT_DETECTION_SOURCE_IP und NAT_DETECTION_DESTINATION_IP Notifications hinzu (RFC 7296 Seite 68).
+    /// Die Daten sind jeweils der SHA-1 Hash über die Security Parameter Indexe, die Adresse und den Port
+    pub fn add_nat_detection(
+        &mut self,
+        source: SocketAddr,
+        destination: SocketAddr,
+    ) -> Result<(), ScanError> {
+        let initiator_spi = self.header.initiator_spi;
+        let responder_spi = self.header.responder_spi;
+        for (message_type, address) in [
+            (NotifyMessageTypeV2::NatDetectionSourceIp, source),
+            (NotifyMessageTypeV2::NatDetectionDestinationIp, destination),
+        ] {
+            self.notify_payloads.push(NotifyV2 {
+                payload: NotifyPayloadV2 {
+                    next_payload: u8::from(PayloadTypeV2::NoNextPayload),
+                    reserved: 0,
+                    length: Default::default(),
+                    protocol_id: 0,
+                    spi_size: 0,
+                    notify_message_type: U16::from(u16::from(message_type)),
+                },
+                data: nat_detection_hash(initiator_spi.get(), responder_spi.get(), address)?,
+            });
+        }
+        Ok(())
     }
+
     ///In dieser Funktion wird die Nonce erstellt
     /// es werden 174 randomisierte Bytes in einem Vektor gesammelt
     pub fn generate_nonce_data(&mut self) {
         let nonce_data: Vec<u8> = (0..174).map(|_| random::<u8>()).collect();
-        println!("Nonce: {:?}", nonce_data);
+        debug!("Nonce: {:?}", nonce_data);
         self.nonce_data = nonce_data;
     }
     ///Mit dieser Funktion wird die Länge des gesamten IkeV2 Pakets berechnet.
@@ -205,29 +284,48 @@ impl IkeV2 {
             diffie.calculate_length();
             length += diffie.length;
         }
-        println!("{:?}", length);
-        println!("ecnryption length {}", self.encryption_transforms.len());
+        debug!("{:?}", length);
+        debug!("ecnryption length {}", self.encryption_transforms.len());
         let proposal_length = U16::from(8) + length;
         self.proposal_v2.length = proposal_length;
-        println!("proposal length is {:?}", proposal_length);
+        debug!("proposal length is {:?}", proposal_length);
         let sa_length = U16::from(4) + proposal_length;
         self.sa_payload_v2.sa2_length = sa_length;
-        println!("Sa length is {:?}", sa_length);
+        debug!("Sa length is {:?}", sa_length);
         self.key_exchange.length = U16::from(8 + (self.key_exchange_data.len() as u16));
-        println!("key exchange length: {:?}", self.key_exchange_data.len());
+        debug!("key exchange length: {:?}", self.key_exchange_data.len());
         self.nonce_payload.length = U16::from(4 + (self.nonce_data.len() as u16));
-        println!("nonce length: {:?}", self.nonce_payload.length);
+        debug!("nonce length: {:?}", self.nonce_payload.length);
+        let cookie_length = self
+            .cookie
+            .as_mut()
+            .map_or(U16::from(0), |cookie| cookie.calculate_length());
+        //the notify payloads are chained after the nonce, the last one ends the chain
+        let mut notify_length = U32::from(0);
+        let mut next_payload = u8::from(PayloadTypeV2::NoNextPayload);
+        for notify in self.notify_payloads.iter_mut().rev() {
+            notify.payload.next_payload = next_payload;
+            notify_length += U32::from(notify.calculate_length());
+            next_payload = u8::from(PayloadTypeV2::Notify);
+        }
+        self.nonce_payload.next_payload_ = next_payload;
         self.header.length = U32::from(28)
+            + U32::from(cookie_length)
             + U32::from(sa_length)
             + U32::from(self.key_exchange.length)
-            + U32::from(self.nonce_payload.length);
-        println!("Packet length is {:?}", self.header.length);
+            + U32::from(self.nonce_payload.length)
+            + notify_length;
+        debug!("Packet length is {:?}", self.header.length);
     }
     ///Die Bestandteile des IkeV2 Pakets werden in einem leeren Vektor gepusht, sie werden in
     /// bytes umgewandelt
     pub fn convert_to_bytes_v2(&mut self) -> Vec<u8> {
         let mut bytes_v2 = vec![];
         bytes_v2.extend_from_slice(self.header.as_bytes());
+        if let Some(cookie) = &self.cookie {
+            bytes_v2.extend_from_slice(cookie.payload.as_bytes());
+            bytes_v2.extend_from_slice(&cookie.data);
+        }
         bytes_v2.extend_from_slice(self.sa_payload_v2.as_bytes());
         bytes_v2.extend_from_slice(self.proposal_v2.as_bytes());
         bytes_v2.extend_from_slice(self.encryption_transforms.as_bytes());
@@ -238,13 +336,35 @@ impl IkeV2 {
         bytes_v2.extend_from_slice(self.key_exchange_data.as_bytes());
         bytes_v2.extend_from_slice(self.nonce_payload.as_bytes());
         bytes_v2.extend_from_slice(self.nonce_data.as_bytes());
+        for notify in &self.notify_payloads {
+            bytes_v2.extend_from_slice(notify.payload.as_bytes());
+            bytes_v2.extend_from_slice(&notify.data);
+        }
         bytes_v2
     }
 }
 
+///Berechnet den Hash für die NAT Detection Notifications:
+/// SHA-1(initiator_spi | responder_spi | IP Adresse | Port)
+pub fn nat_detection_hash(
+    initiator_spi: u64,
+    responder_spi: u64,
+    address: SocketAddr,
+) -> Result<Vec<u8>, ScanError> {
+    let mut data = vec![];
+    data.extend_from_slice(&initiator_spi.to_be_bytes());
+    data.extend_from_slice(&responder_spi.to_be_bytes());
+    match address.ip() {
+        IpAddr::V4(ip) => data.extend_from_slice(&ip.octets()),
+        IpAddr::V6(ip) => data.extend_from_slice(&ip.octets()),
+    }
+    data.extend_from_slice(&address.port().to_be_bytes());
+    Ok(hash(MessageDigest::sha1(), &data)?.to_vec())
+}
+
 ///Ike Version 2 Header (Rfc 7296, Seite 72)
 #[derive(Debug, Copy, Clone, AsBytes)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct IkeV2Header {
     ///Initiator Security Parameter Index
     pub initiator_spi: U64,
@@ -312,7 +432,8 @@ impl From<PayloadTypeV2> for u8 {
 }
 
 impl PayloadTypeV2 {
-    fn try_from_u8(value: u8) -> Option<Self> {
+    ///Umwandeln des nummerischen Werts in den Payload-Typ, None bei unbekannten Werten
+    pub fn try_from_u8(value: u8) -> Option<Self> {
         match value {
             0 => Some(PayloadTypeV2::NoNextPayload),
             33 => Some(PayloadTypeV2::SecurityAssociation),
@@ -330,7 +451,7 @@ impl PayloadTypeV2 {
     }
 }
 ///Austauschtypen (RFC 7296, Seite 74)
-#[derive(Debug, Clone, AsBytes)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, AsBytes, Serialize)]
 #[repr(u8)]
 pub enum ExchangeTypeV2 {
     ///Initialer Austausch
@@ -355,7 +476,8 @@ impl From<ExchangeTypeV2> for u8 {
 }
 
 impl ExchangeTypeV2 {
-    fn try_from_u8(value: u8) -> Option<Self> {
+    ///Umwandeln des nummerischen Werts in den Austauschtyp, None bei unbekannten Werten
+    pub fn try_from_u8(value: u8) -> Option<Self> {
         match value {
             34 => Some(ExchangeTypeV2::IkeSaInit),
             35 => Some(ExchangeTypeV2::IkeAuth),
@@ -369,7 +491,7 @@ impl ExchangeTypeV2 {
 ///Payloads
 ///Security Association Payload for IkeV2 (RFC 7296, Seite 77)
 #[derive(Debug, Copy, Clone, AsBytes)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct SecurityAssociationV2 {
     ///nächster Payload
     pub sa2_next_payload: u8,
@@ -382,7 +504,7 @@ pub struct SecurityAssociationV2 {
 ///Proposal IkeV2 (RFC 7296, Seite 80)
 /// next_proposal kann entweder null sein oder zwei falls noch ein Proposal folgt
 #[derive(Debug, Copy, Clone, AsBytes)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct Proposal {
     ///nächstes Proposal
     pub next_proposal: u8,
@@ -431,7 +553,8 @@ impl From<ProtocolId> for u8 {
 }
 
 impl ProtocolId {
-    fn try_from_u8(value: u8) -> Option<Self> {
+    ///Umwandeln des nummerischen Werts in die Protokoll-ID, None bei unbekannten Werten
+    pub fn try_from_u8(value: u8) -> Option<Self> {
         match value {
             0 => Some(ProtocolId::Reserved),
             1 => Some(ProtocolId::IKE),
@@ -446,7 +569,7 @@ impl ProtocolId {
 
 ///Transform Payload for IkeV2 (Rfc 7296, Seite 79)
 #[derive(Debug, Copy, Clone, AsBytes, PartialEq)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct TransformV2 {
     ///nächste Transformation
     pub next_transform: u8,
@@ -471,7 +594,7 @@ impl TransformV2 {
 
 ///Wrapper struct für Transformation für den Verschlüsselungsalgorithmus
 #[derive(Debug, Copy, Clone, AsBytes, PartialEq)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct TransformAttributeV2 {
     ///nächste Transformation
     pub next_transform: u8,
@@ -494,11 +617,23 @@ impl TransformAttributeV2 {
     pub fn calculate_length(&mut self) {
         self.length = U16::from(4 + 8);
     }
+
+    ///Prüft, ob der vom Server akzeptierte Verschlüsselungsalgorithmus zu dieser Transformation gehört.
+    /// Die Schlüssellänge wird nur verglichen, wenn der Server sie angegeben hat,
+    /// sonst trifft der akzeptierte Algorithmus auf jede Schlüssellänge zu
+    pub fn matches(&self, accepted: &AcceptedTransformV2) -> bool {
+        let transform_id = self.transform_id;
+        let key_length = self.attribute.attribute_value;
+        transform_id.get() == u16::from(accepted.encryption)
+            && accepted
+                .key_length
+                .is_none_or(|accepted_length| key_length.get() == accepted_length)
+    }
 }
 
 ///Attribut für die Schlüssellänge
 #[derive(Debug, Copy, Clone, AsBytes, FromBytes, FromZeroes, PartialEq)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct AttributeV2 {
     ///Attribut Typ (in Enum AttributeType)
     pub attribute_type: U16,
@@ -522,6 +657,13 @@ impl From<AttributeType> for U16 {
     }
 }
 
+impl AttributeType {
+    ///Attribut Typ ohne das significant bit, so wie er beim Parsen verglichen wird
+    pub fn type_value(self) -> u16 {
+        U16::from(self).get() & !(1 << 15)
+    }
+}
+
 ///Schlüssellängen für AES_CBC und AES_CTR
 #[derive(Debug, Copy, Clone, AsBytes)]
 #[repr(u8)]
@@ -575,7 +717,7 @@ impl From<TransformTypeValues> for u8 {
 
 ///Key Exchange Payload (RFC, Seite 89)
 #[derive(Debug, Copy, Clone, AsBytes, FromBytes, FromZeroes, PartialEq)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct KeyExchangePayloadV2 {
     ///nächster Payload
     pub next_payload: u8,
@@ -591,7 +733,7 @@ pub struct KeyExchangePayloadV2 {
 
 ///Nonce Payload (RFC 7296, Seite 99)
 #[derive(Debug, Copy, Clone, AsBytes, FromBytes, FromZeroes, PartialEq)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct NoncePayloadV2 {
     ///nächster Payload
     pub next_payload_: u8,
@@ -600,3 +742,222 @@ pub struct NoncePayloadV2 {
     ///Payload Länge
     pub length: U16,
 }
+
+///Notify Payload (RFC 7296, Seite 100) ohne Daten
+#[derive(Debug, Copy, Clone, AsBytes, PartialEq)]
+#[repr(C, packed)]
+pub struct NotifyPayloadV2 {
+    ///nächster Payload
+    pub next_payload: u8,
+    ///reservierter Bereich
+    pub reserved: u8,
+    ///Payload Länge
+    pub length: U16,
+    ///Protokoll ID, 0 wenn sich die Nachricht auf die IKE-SA bezieht
+    pub protocol_id: u8,
+    ///Größe des Security Parameter Index
+    pub spi_size: u8,
+    ///Nachrichtentyp
+    pub notify_message_type: U16,
+}
+
+///Notify Payload mit den Notification Daten
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotifyV2 {
+    ///Notify Payload
+    pub payload: NotifyPayloadV2,
+    ///Notification Daten
+    pub data: Vec<u8>,
+}
+
+impl NotifyV2 {
+    ///festlegen der Länge des Notify Payloads, die Länge wird zurückgegeben
+    pub fn calculate_length(&mut self) -> U16 {
+        self.payload.length = U16::from(8 + self.data.len() as u16);
+        self.payload.length
+    }
+}
+
+///Nachrichtentypen des Notify Payloads für IkeV2 (RFC 7296, Seite 101)
+/// Werte unter 16384 sind Fehlermeldungen
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum NotifyMessageTypeV2 {
+    ///kritischer Payload wird nicht unterstützt
+    UnsupportedCriticalPayload,
+    ///ungültiger IKE Security Parameter Index
+    InvalidIkeSpi,
+    ///ungültige Hauptversion
+    InvalidMajorVersion,
+    ///ungültige Syntax
+    InvalidSyntax,
+    ///ungültige Nachrichten ID
+    InvalidMessageId,
+    ///ungültiger Security Parameter Index
+    InvalidSpi,
+    ///kein Proposal wurde akzeptiert
+    NoProposalChosen,
+    ///ungültiger Key Exchange Payload, enthält die gewünschte Diffie-Hellman Gruppe
+    InvalidKePayload,
+    ///Authentisierung fehlgeschlagen
+    AuthenticationFailed,
+    ///nur ein Paar Traffic Selectors erlaubt
+    SinglePairRequired,
+    ///keine weiteren Security Associations
+    NoAdditionalSas,
+    ///Fehler bei der internen Adresse
+    InternalAddressFailure,
+    ///Configuration Payload erforderlich
+    FailedCpRequired,
+    ///Traffic Selectors nicht akzeptiert
+    TsUnacceptable,
+    ///ungültige Selektoren
+    InvalidSelectors,
+    ///vorübergehender Fehler
+    TemporaryFailure,
+    ///Kind-SA nicht gefunden
+    ChildSaNotFound,
+    ///erster Kontakt
+    InitialContact,
+    ///Fenstergröße
+    SetWindowSize,
+    ///weitere Traffic Selectors möglich
+    AdditionalTsPossible,
+    ///IPComp wird unterstützt
+    IpcompSupported,
+    ///NAT-Erkennung Quell-IP
+    NatDetectionSourceIp,
+    ///NAT-Erkennung Ziel-IP
+    NatDetectionDestinationIp,
+    ///Cookie für den Schutz vor DoS-Angriffen
+    Cookie,
+    ///Transportmodus verwenden
+    UseTransportMode,
+    ///Zertifikatsabruf über HTTP wird unterstützt
+    HttpCertLookupSupported,
+    ///Neuverschlüsselung der SA
+    RekeySa,
+    ///ESP TFC Padding wird nicht unterstützt
+    EspTfcPaddingNotSupported,
+    ///auch nicht-erste Fragmente
+    NonFirstFragmentsAlso,
+    ///mehrfache Authentisierung wird unterstützt
+    MultipleAuthSupported,
+    ///Umleitung wird unterstützt
+    RedirectSupported,
+    ///Umleitung
+    Redirect,
+    ///IkeV2 ohne Kind-SA wird unterstützt
+    ChildlessIkev2Supported,
+    ///IkeV2 Fragmentierung wird unterstützt
+    Ikev2FragmentationSupported,
+    ///unterstützte Hash-Algorithmen für Signaturen
+    SignatureHashAlgorithms,
+    ///unbekannter Wert
+    Other(u16),
+}
+
+impl From<u16> for NotifyMessageTypeV2 {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => NotifyMessageTypeV2::UnsupportedCriticalPayload,
+            4 => NotifyMessageTypeV2::InvalidIkeSpi,
+            5 => NotifyMessageTypeV2::InvalidMajorVersion,
+            7 => NotifyMessageTypeV2::InvalidSyntax,
+            9 => NotifyMessageTypeV2::InvalidMessageId,
+            11 => NotifyMessageTypeV2::InvalidSpi,
+            14 => NotifyMessageTypeV2::NoProposalChosen,
+            17 => NotifyMessageTypeV2::InvalidKePayload,
+            24 => NotifyMessageTypeV2::AuthenticationFailed,
+            34 => NotifyMessageTypeV2::SinglePairRequired,
+            35 => NotifyMessageTypeV2::NoAdditionalSas,
+            36 => NotifyMessageTypeV2::InternalAddressFailure,
+            37 => NotifyMessageTypeV2::FailedCpRequired,
+            38 => NotifyMessageTypeV2::TsUnacceptable,
+            39 => NotifyMessageTypeV2::InvalidSelectors,
+            43 => NotifyMessageTypeV2::TemporaryFailure,
+            44 => NotifyMessageTypeV2::ChildSaNotFound,
+            16384 => NotifyMessageTypeV2::InitialContact,
+            16385 => NotifyMessageTypeV2::SetWindowSize,
+            16386 => NotifyMessageTypeV2::AdditionalTsPossible,
+            16387 => NotifyMessageTypeV2::IpcompSupported,
+            16388 => NotifyMessageTypeV2::NatDetectionSourceIp,
+            16389 => NotifyMessageTypeV2::NatDetectionDestinationIp,
+            16390 => NotifyMessageTypeV2::Cookie,
+            16391 => NotifyMessageTypeV2::UseTransportMode,
+            16392 => NotifyMessageTypeV2::HttpCertLookupSupported,
+            16393 => NotifyMessageTypeV2::RekeySa,
+            16394 => NotifyMessageTypeV2::EspTfcPaddingNotSupported,
+            16395 => NotifyMessageTypeV2::NonFirstFragmentsAlso,
+            16404 => NotifyMessageTypeV2::MultipleAuthSupported,
+            16406 => NotifyMessageTypeV2::RedirectSupported,
+            16407 => NotifyMessageTypeV2::Redirect,
+            16418 => NotifyMessageTypeV2::ChildlessIkev2Supported,
+            16430 => NotifyMessageTypeV2::Ikev2FragmentationSupported,
+            16431 => NotifyMessageTypeV2::SignatureHashAlgorithms,
+            other => NotifyMessageTypeV2::Other(other),
+        }
+    }
+}
+
+impl From<NotifyMessageTypeV2> for u16 {
+    fn from(value: NotifyMessageTypeV2) -> Self {
+        match value {
+            NotifyMessageTypeV2::UnsupportedCriticalPayload => 1,
+            NotifyMessageTypeV2::InvalidIkeSpi => 4,
+            NotifyMessageTypeV2::InvalidMajorVersion => 5,
+            NotifyMessageTypeV2::InvalidSyntax => 7,
+            NotifyMessageTypeV2::InvalidMessageId => 9,
+            NotifyMessageTypeV2::InvalidSpi => 11,
+            NotifyMessageTypeV2::NoProposalChosen => 14,
+            NotifyMessageTypeV2::InvalidKePayload => 17,
+            NotifyMessageTypeV2::AuthenticationFailed => 24,
+            NotifyMessageTypeV2::SinglePairRequired => 34,
+            NotifyMessageTypeV2::NoAdditionalSas => 35,
+            NotifyMessageTypeV2::InternalAddressFailure => 36,
+            NotifyMessageTypeV2::FailedCpRequired => 37,
+            NotifyMessageTypeV2::TsUnacceptable => 38,
+            NotifyMessageTypeV2::InvalidSelectors => 39,
+            NotifyMessageTypeV2::TemporaryFailure => 43,
+            NotifyMessageTypeV2::ChildSaNotFound => 44,
+            NotifyMessageTypeV2::InitialContact => 16384,
+            NotifyMessageTypeV2::SetWindowSize => 16385,
+            NotifyMessageTypeV2::AdditionalTsPossible => 16386,
+            NotifyMessageTypeV2::IpcompSupported => 16387,
+            NotifyMessageTypeV2::NatDetectionSourceIp => 16388,
+            NotifyMessageTypeV2::NatDetectionDestinationIp => 16389,
+            NotifyMessageTypeV2::Cookie => 16390,
+            NotifyMessageTypeV2::UseTransportMode => 16391,
+            NotifyMessageTypeV2::HttpCertLookupSupported => 16392,
+            NotifyMessageTypeV2::RekeySa => 16393,
+            NotifyMessageTypeV2::EspTfcPaddingNotSupported => 16394,
+            NotifyMessageTypeV2::NonFirstFragmentsAlso => 16395,
+            NotifyMessageTypeV2::MultipleAuthSupported => 16404,
+            NotifyMessageTypeV2::RedirectSupported => 16406,
+            NotifyMessageTypeV2::Redirect => 16407,
+            NotifyMessageTypeV2::ChildlessIkev2Supported => 16418,
+            NotifyMessageTypeV2::Ikev2FragmentationSupported => 16430,
+            NotifyMessageTypeV2::SignatureHashAlgorithms => 16431,
+            NotifyMessageTypeV2::Other(other) => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv6Addr;
+
+    use super::*;
+
+    #[test]
+    fn nat_detection_hash_covers_ipv6_address() {
+        let address = SocketAddr::from((Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1), 4500));
+        let mut data = vec![0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 2];
+        data.extend_from_slice(&[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        data.extend_from_slice(&[0x11, 0x94]);
+        let expected = hash(MessageDigest::sha1(), &data).unwrap().to_vec();
+        assert_eq!(nat_detection_hash(1, 2, address).unwrap(), expected);
+        //the port is part of the hash
+        let other_port = SocketAddr::from((Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1), 500));
+        assert_ne!(nat_detection_hash(1, 2, other_port).unwrap(), expected);
+    }
+}
@@ -0,0 +1,199 @@
+//! # Bike-Scan
+//! das folgende Modul erzeugt die Key-Exchange Daten für eine Diffie-Hellman Gruppe.
+//! Für MODP Gruppen werden die festen Primzahlen aus RFC 2409, RFC 3526 und RFC 5114 verwendet,
+//! für ECP Gruppen der öffentliche Punkt ohne Präfix (RFC 5903) und
+//! für Curve25519 und Curve448 der öffentliche Schlüssel (RFC 8031)
+
+use openssl::bn::BigNum;
+use openssl::bn::BigNumContext;
+use openssl::dh::Dh;
+use openssl::ec::EcGroup;
+use openssl::ec::EcKey;
+use openssl::ec::PointConversionForm;
+use openssl::nid::Nid;
+use openssl::pkey::PKey;
+use openssl::pkey::Params;
+
+use crate::algorithms::DiffieHellmanGroup;
+use crate::error::ScanError;
+
+///brainpoolP224r1 hat im openssl Crate keine Konstante
+const NID_BRAINPOOL_P224R1: i32 = 925;
+
+///Prüft, ob für die Gruppe Key-Exchange Daten erzeugt werden können
+pub fn is_supported(group: DiffieHellmanGroup) -> bool {
+    !matches!(
+        group,
+        DiffieHellmanGroup::Ec2nGp155
+            | DiffieHellmanGroup::Ec2nGp185
+            | DiffieHellmanGroup::Ec2n163Random
+            | DiffieHellmanGroup::Ec2n163Koblitz
+            | DiffieHellmanGroup::Ec2n283Random
+            | DiffieHellmanGroup::Ec2n283Koblitz
+            | DiffieHellmanGroup::Ec2n409Random
+            | DiffieHellmanGroup::Ec2n409Koblitz
+            | DiffieHellmanGroup::Ec2n571Random
+            | DiffieHellmanGroup::Ec2n571Koblitz
+            | DiffieHellmanGroup::Gost3410_2012_256
+            | DiffieHellmanGroup::Gost3410_2012_512
+            | DiffieHellmanGroup::Other(_)
+    )
+}
+
+///Erzeugt einen neuen Schlüssel für die Gruppe und gibt den öffentlichen Teil
+/// in der Form zurück, in der er im Key Exchange Payload übertragen wird.
+/// Für nicht unterstützte Gruppen wird InvalidInput zurückgegeben
+pub fn generate(group: DiffieHellmanGroup) -> Result<Vec<u8>, ScanError> {
+    match group {
+        DiffieHellmanGroup::Modp768 => modp(BigNum::get_rfc2409_prime_768()?),
+        DiffieHellmanGroup::Modp1024 => modp(BigNum::get_rfc2409_prime_1024()?),
+        DiffieHellmanGroup::Modp1536 => modp(BigNum::get_rfc3526_prime_1536()?),
+        DiffieHellmanGroup::Modp2048 => modp(BigNum::get_rfc3526_prime_2048()?),
+        DiffieHellmanGroup::Modp3072 => modp(BigNum::get_rfc3526_prime_3072()?),
+        DiffieHellmanGroup::Modp4096 => modp(BigNum::get_rfc3526_prime_4096()?),
+        DiffieHellmanGroup::Modp6144 => modp(BigNum::get_rfc3526_prime_6144()?),
+        DiffieHellmanGroup::Modp8192 => modp(BigNum::get_rfc3526_prime_8192()?),
+        DiffieHellmanGroup::Modp1024S160 => dh_public_key(Dh::get_1024_160()?),
+        DiffieHellmanGroup::Modp2048S224 => dh_public_key(Dh::get_2048_224()?),
+        DiffieHellmanGroup::Modp2048S256 => dh_public_key(Dh::get_2048_256()?),
+        DiffieHellmanGroup::Ecp256 => ecp(Nid::X9_62_PRIME256V1),
+        DiffieHellmanGroup::Ecp384 => ecp(Nid::SECP384R1),
+        DiffieHellmanGroup::Ecp521 => ecp(Nid::SECP521R1),
+        DiffieHellmanGroup::Ecp192 => ecp(Nid::X9_62_PRIME192V1),
+        DiffieHellmanGroup::Ecp224 => ecp(Nid::SECP224R1),
+        DiffieHellmanGroup::BrainpoolP224r1 => ecp(Nid::from_raw(NID_BRAINPOOL_P224R1)),
+        DiffieHellmanGroup::BrainpoolP256r1 => ecp(Nid::BRAINPOOL_P256R1),
+        DiffieHellmanGroup::BrainpoolP384r1 => ecp(Nid::BRAINPOOL_P384R1),
+        DiffieHellmanGroup::BrainpoolP512r1 => ecp(Nid::BRAINPOOL_P512R1),
+        DiffieHellmanGroup::Curve25519 => Ok(PKey::generate_x25519()?.raw_public_key()?),
+        DiffieHellmanGroup::Curve448 => Ok(PKey::generate_x448()?.raw_public_key()?),
+        unsupported => Err(ScanError::InvalidInput(format!(
+            "no key exchange data for Diffie-Hellman group {:?}",
+            unsupported
+        ))),
+    }
+}
+
+///MODP Gruppe mit fester Primzahl und Generator 2
+fn modp(prime: BigNum) -> Result<Vec<u8>, ScanError> {
+    let diffie_hellman = Dh::from_pqg(prime, None, BigNum::from_u32(2)?)?;
+    dh_public_key(diffie_hellman)
+}
+
+///Öffentlicher Schlüssel, mit führenden Nullen auf die Länge der Primzahl aufgefüllt
+fn dh_public_key(parameters: Dh<Params>) -> Result<Vec<u8>, ScanError> {
+    let private_key = parameters.generate_key()?;
+    let prime_length = private_key.prime_p().num_bytes();
+    Ok(private_key.public_key().to_vec_padded(prime_length)?)
+}
+
+///ECP Gruppe: x- und y-Koordinate des öffentlichen Punkts ohne das Präfix 0x04
+fn ecp(curve: Nid) -> Result<Vec<u8>, ScanError> {
+    let group = EcGroup::from_curve_name(curve)?;
+    let key = EcKey::generate(&group)?;
+    let mut context = BigNumContext::new()?;
+    let point =
+        key.public_key()
+            .to_bytes(&group, PointConversionForm::UNCOMPRESSED, &mut context)?;
+    Ok(point[1..].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::ec::EcPoint;
+
+    use super::*;
+
+    ///Länge der Key-Exchange Daten je unterstützter Gruppe (IANA ID, Länge in Bytes)
+    const KEY_EXCHANGE_LENGTHS: [(u16, usize); 22] = [
+        (1, 96),
+        (2, 128),
+        (5, 192),
+        (14, 256),
+        (15, 384),
+        (16, 512),
+        (17, 768),
+        (18, 1024),
+        (19, 64),
+        (20, 96),
+        (21, 132),
+        (22, 128),
+        (23, 256),
+        (24, 256),
+        (25, 48),
+        (26, 56),
+        (27, 56),
+        (28, 64),
+        (29, 96),
+        (30, 128),
+        (31, 32),
+        (32, 56),
+    ];
+
+    ///ECP Gruppen und ihre Kurven
+    const ECP_CURVES: [(u16, Nid); 9] = [
+        (19, Nid::X9_62_PRIME256V1),
+        (20, Nid::SECP384R1),
+        (21, Nid::SECP521R1),
+        (25, Nid::X9_62_PRIME192V1),
+        (26, Nid::SECP224R1),
+        (27, Nid::from_raw(NID_BRAINPOOL_P224R1)),
+        (28, Nid::BRAINPOOL_P256R1),
+        (29, Nid::BRAINPOOL_P384R1),
+        (30, Nid::BRAINPOOL_P512R1),
+    ];
+
+    #[test]
+    fn key_exchange_data_has_group_length() {
+        for (id, length) in KEY_EXCHANGE_LENGTHS {
+            let group = DiffieHellmanGroup::from(id);
+            assert!(is_supported(group), "group {}", id);
+            assert_eq!(generate(group).unwrap().len(), length, "group {}", id);
+        }
+    }
+
+    #[test]
+    fn every_supported_group_is_tested() {
+        for id in 0..=u16::from(u8::MAX) {
+            if is_supported(DiffieHellmanGroup::from(id)) {
+                assert!(
+                    KEY_EXCHANGE_LENGTHS.iter().any(|(known, _)| *known == id),
+                    "group {}",
+                    id
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn ecp_data_is_a_point_on_the_curve() {
+        for (id, curve) in ECP_CURVES {
+            let data = generate(DiffieHellmanGroup::from(id)).unwrap();
+            let group = EcGroup::from_curve_name(curve).unwrap();
+            let mut context = BigNumContext::new().unwrap();
+            let mut uncompressed = vec![0x04];
+            uncompressed.extend_from_slice(&data);
+            let point = EcPoint::from_bytes(&group, &uncompressed, &mut context).unwrap();
+            assert!(
+                point.is_on_curve(&group, &mut context).unwrap(),
+                "group {}",
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn brainpool_p224r1_nid_names_the_curve() {
+        let nid = Nid::from_raw(NID_BRAINPOOL_P224R1);
+        assert_eq!(nid.short_name().unwrap(), "brainpoolP224r1");
+    }
+
+    #[test]
+    fn unsupported_groups_are_rejected() {
+        for id in [3, 4, 6, 7, 8, 9, 10, 11, 12, 13, 33, 34, 100] {
+            let group = DiffieHellmanGroup::from(id);
+            assert!(!is_supported(group), "group {}", id);
+            assert!(matches!(generate(group), Err(ScanError::InvalidInput(_))));
+        }
+    }
+}
@@ -5,28 +5,43 @@
 //! Die Funktionen verwenden Structs und Implementationen
 //! der jeweigen Module.
 //! Module für die scan()-Funktion: ike.rs, parse_ike.rs
-//! Module für die scan_v2()-Funktion: ikev2.rs, parse_ikev2.rs
+//! Module für die scan_v2()-Funktion: ikev2.rs, parse_ikev2.rs, key_exchange.rs
+//! Das Ziel und die Einstellungen (options.rs) werden beiden Funktionen übergeben.
+//! Die Nachrichten werden über das Trait Transport (transport.rs) gesendet,
+//! mit scan_transport() und scan_v2_transport() kann ein eigener Transport verwendet werden.
+//! Die Ergebnisse werden als Bericht (report.rs) zurückgegeben.
+//! Die Rate der Proben an ein Ziel begrenzt ein Token-Bucket (rate_limit.rs),
+//! Antworten werden den Proben über den Initiator SPI zugeordnet (correlation.rs).
+//! Mehrere Ziele und CIDR Bereiche (targets.rs) werden mit scheduler.rs gleichzeitig gescannt,
+//! mit der Probe-Engine (engine.rs) teilen sich alle Ziele einen UDP Socket,
+//! unter Linux bündelt sie Datagramme mit sendmmsg/recvmmsg (batch.rs).
+//! Die Funktion discover() prüft vorab mit je einer Probe, welche Ziele Ike Server sind.
 
 #![warn(missing_docs, clippy::expect_used, clippy::unwrap_used)]
 
-use std::io;
 use std::net::SocketAddr;
-use std::time;
+use std::time::Duration;
 
+use log::debug;
 use rand::Rng;
-use tokio::net::UdpSocket;
-use zerocopy::network_endian::U16;
+use tokio::net::lookup_host;
+use tokio::net::ToSocketAddrs;
+use tokio::time::Instant;
 use zerocopy::network_endian::U32;
 use zerocopy::network_endian::U64;
-use zerocopy::AsBytes;
-use zerocopy::FromBytes;
 
+use crate::algorithms::EncryptionAlgorithmV2;
+use crate::correlation::Probe;
+use crate::correlation::ProbeTracker;
+use crate::error::ScanError;
 use crate::ike::IkeV1;
 use crate::ike::IkeV1Header;
 use crate::ike::PayloadTypeV1::NoNextPayload;
 use crate::ike::PayloadTypeV1::SecurityAssociation;
 use crate::ike::ProposalPayload;
 use crate::ike::SecurityAssociationV1;
+use crate::ike::Transform;
+use crate::ike::NAT_TRAVERSAL_VENDOR_IDS;
 use crate::ikev2::ExchangeTypeV2;
 use crate::ikev2::IkeV2;
 use crate::ikev2::IkeV2Header;
@@ -36,170 +51,1500 @@ use crate::ikev2::PayloadTypeV2;
 use crate::ikev2::Proposal;
 use crate::ikev2::ProtocolId;
 use crate::ikev2::SecurityAssociationV2;
+use crate::ikev2::TransformAttributeV2;
+use crate::ikev2::TransformTypeValues;
+use crate::ikev2::TransformV2;
+use crate::options::ScanOptions;
+use crate::options::TransformSelection;
+use crate::options::TransportMode;
 use crate::parse_ike::ResponsePacket;
 use crate::parse_ikev2::ResponsePacketV2;
+use crate::rate_limit::RateLimiter;
+use crate::report::DiscoveryReport;
+use crate::report::EndpointState;
+use crate::report::IkeV1Report;
+use crate::report::IkeV2Report;
+use crate::report::ScanReport;
+use crate::transport::Connection;
+use crate::transport::Transport;
 
+pub mod algorithms;
+#[cfg(target_os = "linux")]
+mod batch;
+pub mod correlation;
+pub mod engine;
+pub mod error;
 pub mod ike;
 pub mod ikev2;
+pub mod key_exchange;
+pub mod options;
 pub mod parse_ike;
 pub mod parse_ikev2;
+pub mod rate_limit;
+pub mod report;
+pub mod scheduler;
+pub mod targets;
+pub mod transport;
+
+///Länge des Ike Headers in Bytes (gleich für IkeV1 und IkeV2)
+const IKE_HEADER_LENGTH: usize = 28;
+
+///Position der Versionsnummer im Ike Header (Hauptversion in den oberen vier Bits)
+const IKE_VERSION_OFFSET: usize = 17;
+
+///Größe des Empfangspuffers, reicht für das größte UDP Datagramm und die größte Nachricht im TCP Stream.
+/// Kürzere Puffer würden größere Antworten (z.B. mit Zertifikatsanfragen oder großen Key-Exchange Daten) abschneiden
+const MAX_MESSAGE_LENGTH: usize = 65535;
+
+///Wie oft ein IKE_SA_INIT mit einem neuen Cookie wiederholt wird, bevor die Cookie-Antwort übernommen wird
+const MAX_COOKIE_RETRIES: usize = 2;
 
 ///Diese Funktion generiert das IkeV1 Paket und sendet diese an der Zielserver.
-/// Wenn keine Transformationen gefunden werden,
-/// wird das IkeV2 Paket mit der Funktion scan_v2 an den Server gesendet.
-/// Die Antworten des Servers werden für IkeV1 verarbeitet.
-pub async fn scan() -> io::Result<()> {
-    let socket = UdpSocket::bind("0.0.0.0:0".parse::<SocketAddr>().unwrap()).await?;
-    let remote_addr = "<IP>:<Port>".parse::<SocketAddr>().unwrap();
-    socket.connect(remote_addr).await?;
-    //sending IKE Version 1 packet
-    let transforms = IkeV1::build_transforms();
-    for chunk in transforms.chunks(255) {
-        //calculate random Initiator Security Parameter Index
-        let initiator_spi: u64 = rand::thread_rng().gen();
-        //Ike Version 1 Packet
-        let mut ike_v1 = IkeV1 {
-            header: IkeV1Header {
-                initiator_spi: U64::from(initiator_spi),
-                responder_spi: 0,
-                next_payload: u8::from(SecurityAssociation),
-                version: 16,
-                exchange_type: 2,
-                flag: 0,
-                message_id: 0,
-                length: Default::default(),
-            },
-            security_association_payload: SecurityAssociationV1 {
-                sa_next_payload: u8::from(NoNextPayload),
-                reserved: 0,
-                sa_length: Default::default(),
-                sa_doi: U32::from(1),
-                sa_situation: U32::from(1),
-            },
-            proposal_payload: ProposalPayload {
-                next_payload: u8::from(NoNextPayload),
-                reserved: 0,
-                length: Default::default(),
-                proposal: 1,
-                protocol_id: 1,
-                spi_size: 0,
-                number_of_transforms: Default::default(),
-            },
-            transform: vec![],
-        };
-        ike_v1.set_transforms(chunk);
-        ike_v1.calculate_length();
-        let bytes = ike_v1.convert_to_bytes();
+/// Das Ziel kann als SocketAddr oder als Hostname mit Port übergeben werden.
+/// Ist 'enumerate' in den Einstellungen gesetzt, werden die akzeptierten Transformationen
+/// aus dem Paket entfernt und der Rest erneut gesendet, bis der Server mit NO_PROPOSAL_CHOSEN antwortet.
+/// Die Antworten des Servers werden für IkeV1 verarbeitet und als Bericht zurückgegeben.
+/// Die Erkennung der Version und der Wechsel zu IkeV2 erfolgen mit scan_auto().
+pub async fn scan(
+    target: impl ToSocketAddrs,
+    options: &ScanOptions,
+) -> Result<ScanReport, ScanError> {
+    let remote_addr = resolve_target(target, options.bind_address).await?;
+    let mut connection = Connection::open(remote_addr, options).await?;
+    scan_transport(&mut connection, options).await
+}
 
-        socket.send(&bytes).await.expect("Couldn't send packet");
+///Führt den IkeV1 Scan wie scan() über einen bereits geöffneten Transport aus
+pub async fn scan_transport(
+    transport: &mut impl Transport,
+    options: &ScanOptions,
+) -> Result<ScanReport, ScanError> {
+    let mut context = ProbeContext::new(options)?;
+    let mut report = scan_limited(transport, options, &mut context).await?;
+    report.discarded_responses = context.tracker.discarded();
+    Ok(report)
+}
 
-        let mut buf = [0u8; 112];
-        socket
-            .recv_from(&mut buf)
-            .await
-            .expect("couldn't read buffer");
+///IkeV1 Scan mit dem Zustand der Proben, den auch andere Scans desselben Ziels verwenden
+async fn scan_limited(
+    transport: &mut impl Transport,
+    options: &ScanOptions,
+    context: &mut ProbeContext,
+) -> Result<ScanReport, ScanError> {
+    if options.chunk_size_v1 == 0 {
+        return Err(ScanError::InvalidInput(
+            "chunk size must not be zero".to_string(),
+        ));
+    }
+    let mut report = ScanReport::new(transport.peer_addr()?);
+    let mut ike_v1_report = IkeV1Report::default();
+    //sending IKE Version 1 packet
+    let transforms = options.transforms.v1.apply(IkeV1::build_transforms());
+    for chunk in transforms.chunks(options.chunk_size_v1) {
+        let mut remaining = chunk.to_vec();
+        while !remaining.is_empty() {
+            wait_for_next_probe(context).await;
+            let Some(ike_response) = probe_v1(transport, &remaining, options, context).await?
+            else {
+                ike_v1_report.unanswered_probes += 1;
+                break;
+            };
+            ike_response.parse_response(&mut ike_v1_report);
+            if !options.enumerate || ike_response.no_proposal_chosen() {
+                break;
+            }
+            //remove the accepted transforms and propose the rest of the chunk again,
+            //without a key length in the reply only the first proposed key length is removed
+            let mut excluded = false;
+            for accepted_transform in ike_response.accepted_transforms() {
+                excluded |= exclude_first(&mut remaining, |transform| {
+                    transform.matches(&accepted_transform)
+                });
+            }
+            if !excluded {
+                debug!("Response does not select a proposed transform, stopping enumeration");
+                break;
+            }
+        }
+    }
+    report.ike_v1 = Some(ike_v1_report);
+    Ok(report)
+}
+
+///Sendet ein IkeV1 Paket (Main Mode) mit den übergebenen Transformationen und parst die Antwort.
+/// Ohne Antwort wird None zurückgegeben
+async fn probe_v1(
+    transport: &mut impl Transport,
+    transforms: &[Transform],
+    options: &ScanOptions,
+    context: &mut ProbeContext,
+) -> Result<Option<ResponsePacket>, ScanError> {
+    let (initiator_spi, bytes) = build_probe_v1(transforms, options)?;
+    let mut buf = vec![0u8; MAX_MESSAGE_LENGTH];
+    let probe = Probe::new(initiator_spi, 1);
+    let Some(length) = exchange(transport, &probe, &bytes, &mut buf, options, context).await?
+    else {
+        return Ok(None);
+    };
 
-        let byte_slice = buf.as_slice();
+    //parse Ike Response
+    ResponsePacket::parse(&buf[..length]).map(Some)
+}
 
-        //parse Ike Response
-        let ike_response = ResponsePacket::read_from_prefix(byte_slice).expect("Slice too short");
-        ike_response.parse_response();
-        let seconds = time::Duration::from_secs(60);
-        tokio::time::sleep(seconds).await;
+///Erstellt ein IkeV1 Paket (Main Mode) mit den übergebenen Transformationen.
+/// Gibt den zufälligen Initiator Security Parameter Index und das Paket zurück
+fn build_probe_v1(
+    transforms: &[Transform],
+    options: &ScanOptions,
+) -> Result<(u64, Vec<u8>), ScanError> {
+    //calculate random Initiator Security Parameter Index
+    let initiator_spi: u64 = rand::thread_rng().gen();
+    //Ike Version 1 Packet
+    let mut ike_v1 = IkeV1 {
+        header: IkeV1Header {
+            initiator_spi: U64::from(initiator_spi),
+            responder_spi: 0,
+            next_payload: u8::from(SecurityAssociation),
+            version: 16,
+            exchange_type: 2,
+            flag: 0,
+            message_id: 0,
+            length: Default::default(),
+        },
+        security_association_payload: SecurityAssociationV1 {
+            sa_next_payload: u8::from(NoNextPayload),
+            reserved: 0,
+            sa_length: Default::default(),
+            sa_doi: U32::from(1),
+            sa_situation: U32::from(1),
+        },
+        proposal_payload: ProposalPayload {
+            next_payload: u8::from(NoNextPayload),
+            reserved: 0,
+            length: Default::default(),
+            proposal: 1,
+            protocol_id: 1,
+            spi_size: 0,
+            number_of_transforms: Default::default(),
+        },
+        transform: vec![],
+        vendor_ids: vec![],
+    };
+    if options.transport == TransportMode::NatTraversal {
+        ike_v1.vendor_ids = NAT_TRAVERSAL_VENDOR_IDS.map(Vec::from).to_vec();
     }
-    Ok(())
+    ike_v1.set_transforms(transforms)?;
+    ike_v1.calculate_length();
+    Ok((initiator_spi, ike_v1.convert_to_bytes()))
 }
 
 ///Es wird das Paket für Ikev2 generiert und an den Server gesendet.
-/// Die Antwort des Servers wird verarbeitet und in der Konsole ausgegeben
-pub async fn scan_v2() -> io::Result<()> {
-    let socket = UdpSocket::bind("0.0.0.0:0".parse::<SocketAddr>().unwrap()).await?;
-    let remote_addr = "<IP>:<Port>".parse::<SocketAddr>().unwrap();
-    socket.connect(remote_addr).await?;
+/// Das Ziel kann als SocketAddr oder als Hostname mit Port übergeben werden.
+/// Ist 'enumerate' in den Einstellungen gesetzt, wird für jeden Transformationstyp der akzeptierte
+/// Algorithmus entfernt und das Proposal erneut gesendet, bis der Server mit NO_PROPOSAL_CHOSEN antwortet.
+/// Die Antwort des Servers wird verarbeitet und als Bericht zurückgegeben
+pub async fn scan_v2(
+    target: impl ToSocketAddrs,
+    options: &ScanOptions,
+) -> Result<ScanReport, ScanError> {
+    let remote_addr = resolve_target(target, options.bind_address).await?;
+    let mut connection = Connection::open(remote_addr, options).await?;
+    scan_v2_transport(&mut connection, options).await
+}
+
+///Führt den IkeV2 Scan wie scan_v2() über einen bereits geöffneten Transport aus
+pub async fn scan_v2_transport(
+    transport: &mut impl Transport,
+    options: &ScanOptions,
+) -> Result<ScanReport, ScanError> {
+    let mut context = ProbeContext::new(options)?;
+    let mut report = scan_v2_limited(transport, options, &mut context).await?;
+    report.discarded_responses = context.tracker.discarded();
+    Ok(report)
+}
+
+///IkeV2 Scan mit dem Zustand der Proben, den auch andere Scans desselben Ziels verwenden
+async fn scan_v2_limited(
+    transport: &mut impl Transport,
+    options: &ScanOptions,
+    context: &mut ProbeContext,
+) -> Result<ScanReport, ScanError> {
+    let chunk_size = options.chunk_size_v2;
+    if chunk_size == 0 {
+        return Err(ScanError::InvalidInput(
+            "chunk size must not be zero".to_string(),
+        ));
+    }
+    let mut report = ScanReport::new(transport.peer_addr()?);
+    let mut ike_v2_report = IkeV2Report::default();
     //sending IKE Version 2 Packet
-    let transforms_v2 = IkeV2::build_transforms_v2();
-    for encryption_chunk in transforms_v2.0.chunks(63) {
-        for prf_chunk in transforms_v2.1.chunks(63) {
-            for integrity_algorithm_chunk in transforms_v2.2.chunks(63) {
-                for diffie_group_chunk in transforms_v2.3.chunks(63) {
-                    let initiator_spi_v2: u64 = rand::thread_rng().gen();
-                    let mut ike_v2 = IkeV2 {
-                        header: IkeV2Header {
-                            initiator_spi: U64::from(initiator_spi_v2),
-                            responder_spi: U64::from(0),
-                            next_payload: u8::from(PayloadTypeV2::SecurityAssociation),
-                            version: 32,
-                            exchange_type: u8::from(ExchangeTypeV2::IkeSaInit),
-                            flag: 8,
-                            message_id: 0,
-                            length: Default::default(),
-                        },
-                        sa_payload_v2: SecurityAssociationV2 {
-                            sa2_next_payload: u8::from(PayloadTypeV2::KeyExchange),
-                            critical_bit: 0,
-                            sa2_length: Default::default(),
-                        },
-                        proposal_v2: Proposal {
-                            next_proposal: 0,
-                            reserved: 0,
-                            length: Default::default(),
-                            proposal_number: 1,
-                            protocol_id: ProtocolId::IKE,
-                            spi_size: 0,
-                            number_of_transforms: Default::default(),
-                        },
-                        encryption_transforms: vec![],
-                        prf_transform: vec![],
-                        integrity_algorithm_transform: vec![],
-                        diffie_transform: vec![],
-                        key_exchange: KeyExchangePayloadV2 {
-                            next_payload: u8::from(PayloadTypeV2::Nonce),
-                            reserved: 0,
-                            length: Default::default(),
-                            diffie_hellman_group: U16::from(2),
-                            reserved2: Default::default(),
-                        },
-                        key_exchange_data: vec![],
-                        nonce_payload: NoncePayloadV2 {
-                            next_payload_: 0,
-                            reserved: 0,
-                            length: Default::default(),
-                        },
-                        nonce_data: vec![],
+    let transforms_v2 = options.transforms.v2.apply(IkeV2::build_transforms_v2());
+    for encryption_chunk in transforms_v2.0.chunks(chunk_size) {
+        for prf_chunk in transforms_v2.1.chunks(chunk_size) {
+            for integrity_algorithm_chunk in transforms_v2.2.chunks(chunk_size) {
+                for diffie_group_chunk in transforms_v2.3.chunks(chunk_size) {
+                    wait_for_next_probe(context).await;
+                    let proposal = ProposalV2 {
+                        encryption: encryption_chunk,
+                        prf: prf_chunk,
+                        integrity_algorithm: integrity_algorithm_chunk,
+                        diffie_group: diffie_group_chunk,
+                    };
+                    let Some(ike_v2_response) =
+                        probe_v2(transport, &proposal, options, &mut ike_v2_report, context)
+                            .await?
+                    else {
+                        ike_v2_report.unanswered_probes += 1;
+                        continue;
                     };
-                    ike_v2.set_transforms_v2(
-                        encryption_chunk,
-                        prf_chunk,
-                        integrity_algorithm_chunk,
-                        diffie_group_chunk,
-                    );
-                    ike_v2.generate_key_exchange_data();
-                    ike_v2.generate_nonce_data();
-                    ike_v2.calculate_length_v2();
-
-                    let bytes_v2 = ike_v2.convert_to_bytes_v2();
-                    socket.send(&bytes_v2).await.expect("Couldn't send packet");
-
-                    let mut buf_v2 = [0u8; 285];
-                    socket
-                        .recv_from(&mut buf_v2)
-                        .await
-                        .expect("couldn't read buffer");
-                    let byte_slice_v2 = buf_v2.as_slice();
-                    let ike_v2_response = ResponsePacketV2::parse_ike_v2(byte_slice_v2).unwrap();
-                    //println!("{:?}", ike_v2_response);
-
-                    println!(
-                        "Ike Version is {:?}, ExchangeType is {:?}",
-                        ike_v2_response.header.version, ike_v2_response.header.exchange_type
-                    );
-
-                    println!("Found Transforms: Encryption Algorthm: {:?}, Prf-Funktion: {:?}, Integrity Algorithm: {:?}, Diffie-Hellamn-Gruppe: {:?}"
-                             ,ike_v2_response.encryption_transform.transform_id, ike_v2_response.prf_transform.transform_id, ike_v2_response.integrity_algorithm_transform.transform_id,
-                             ike_v2_response.diffie_transform.transform_id);
+                    ike_v2_response.parse_response(&mut ike_v2_report);
+                    let Some(first_accepted) = ike_v2_response
+                        .accepted_transform()
+                        .filter(|_| options.enumerate)
+                    else {
+                        continue;
+                    };
+                    //exclude the accepted algorithm of one transform type at a time and propose again
+                    for transform_type in [
+                        TransformTypeValues::EncryptionAlgorithm,
+                        TransformTypeValues::PseudoRandomFunction,
+                        TransformTypeValues::IntegrityAlgorithm,
+                        TransformTypeValues::DiffieHellmanGroup,
+                    ] {
+                        let mut encryption = encryption_chunk.to_vec();
+                        let mut prf = prf_chunk.to_vec();
+                        let mut integrity_algorithm = integrity_algorithm_chunk.to_vec();
+                        let mut diffie_group = diffie_group_chunk.to_vec();
+                        let mut accepted = first_accepted;
+                        loop {
+                            let excluded = match transform_type {
+                                TransformTypeValues::EncryptionAlgorithm => {
+                                    exclude_first(&mut encryption, |transform| {
+                                        transform.matches(&accepted)
+                                    })
+                                }
+                                TransformTypeValues::PseudoRandomFunction => {
+                                    exclude_id(&mut prf, u16::from(accepted.prf))
+                                }
+                                TransformTypeValues::IntegrityAlgorithm => {
+                                    accepted.integrity.is_some_and(|integrity| {
+                                        exclude_id(&mut integrity_algorithm, u16::from(integrity))
+                                    })
+                                }
+                                TransformTypeValues::DiffieHellmanGroup => exclude_id(
+                                    &mut diffie_group,
+                                    u16::from(accepted.diffie_hellman_group),
+                                ),
+                                TransformTypeValues::ExtendedSequenceNumbers => false,
+                            };
+                            //without integrity algorithms only AEAD ciphers form a valid proposal
+                            let integrity_missing = integrity_algorithm.is_empty()
+                                && !encryption.iter().all(|transform| {
+                                    let transform_id = transform.transform_id;
+                                    EncryptionAlgorithmV2::from(transform_id.get()).is_aead()
+                                });
+                            if !excluded
+                                || encryption.is_empty()
+                                || prf.is_empty()
+                                || integrity_missing
+                                || diffie_group.is_empty()
+                            {
+                                break;
+                            }
+                            wait_for_next_probe(context).await;
+                            let proposal = ProposalV2 {
+                                encryption: &encryption,
+                                prf: &prf,
+                                integrity_algorithm: &integrity_algorithm,
+                                diffie_group: &diffie_group,
+                            };
+                            let Some(ike_v2_response) = probe_v2(
+                                transport,
+                                &proposal,
+                                options,
+                                &mut ike_v2_report,
+                                context,
+                            )
+                            .await?
+                            else {
+                                ike_v2_report.unanswered_probes += 1;
+                                break;
+                            };
+                            ike_v2_response.parse_response(&mut ike_v2_report);
+                            match ike_v2_response.accepted_transform() {
+                                Some(next_accepted) => accepted = next_accepted,
+                                None => break,
+                            }
+                        }
+                    }
                 }
             }
         }
     }
-    Ok(())
+    report.ike_v2 = Some(ike_v2_report);
+    Ok(report)
+}
+
+///Transformationen eines IkeV2 Proposals, je Transformationstyp eine Liste
+struct ProposalV2<'a> {
+    encryption: &'a [TransformAttributeV2],
+    prf: &'a [TransformV2],
+    integrity_algorithm: &'a [TransformV2],
+    diffie_group: &'a [TransformV2],
+}
+
+///Sendet ein IkeV2 Paket (IKE_SA_INIT) mit den übergebenen Transformationen und parst die Antwort.
+/// Verlangt der Server ein Cookie, wird das Paket mit dem Cookie erneut gesendet,
+/// höchstens MAX_COOKIE_RETRIES mal, danach wird die Antwort mit dem Cookie zurückgegeben.
+/// Antwortet der Server mit INVALID_KE_PAYLOAD, wird die angefragte Gruppe in den Bericht übernommen
+/// und das Paket mit passenden Key-Exchange Daten erneut gesendet.
+/// Ohne Antwort wird None zurückgegeben
+async fn probe_v2(
+    transport: &mut impl Transport,
+    proposal: &ProposalV2<'_>,
+    options: &ScanOptions,
+    report: &mut IkeV2Report,
+    context: &mut ProbeContext,
+) -> Result<Option<ResponsePacketV2>, ScanError> {
+    let local_addr = transport.local_addr()?;
+    let remote_addr = transport.peer_addr()?;
+    let mut ike_v2 = build_probe_v2(proposal, local_addr, remote_addr, options)?;
+    let probe = Probe::new(ike_v2.header.initiator_spi.get(), 2);
+
+    let mut tried_groups = vec![];
+    let mut cookie_retries = 0;
+    let mut buf_v2 = vec![0u8; MAX_MESSAGE_LENGTH];
+    loop {
+        let bytes_v2 = ike_v2.convert_to_bytes_v2();
+        let Some(length) =
+            exchange(transport, &probe, &bytes_v2, &mut buf_v2, options, context).await?
+        else {
+            return Ok(None);
+        };
+        let ike_v2_response = ResponsePacketV2::parse(&buf_v2[..length])?;
+        if let Some(nat_detection) = ike_v2_response.nat_detection(local_addr, remote_addr)? {
+            report.nat_detection = Some(nat_detection);
+        }
+
+        //COOKIE: resend the request with the cookie in front of the other payloads
+        if let Some(cookie) = ike_v2_response.cookie() {
+            debug!("Responder requested cookie {:?}", cookie);
+            report.cookie_required = true;
+            context.limiter.on_cookie();
+            //a responder under load may hand out a fresh cookie for every request
+            if cookie_retries == MAX_COOKIE_RETRIES
+                || ike_v2
+                    .cookie
+                    .as_ref()
+                    .is_some_and(|sent| sent.data == cookie)
+            {
+                return Ok(Some(ike_v2_response));
+            }
+            cookie_retries += 1;
+            ike_v2.set_cookie(cookie.to_vec());
+            ike_v2.calculate_length_v2();
+            continue;
+        }
+
+        //INVALID_KE_PAYLOAD: resend with the group the responder asked for
+        let Some(requested_group) = ike_v2_response.requested_key_exchange_group() else {
+            return Ok(Some(ike_v2_response));
+        };
+        debug!(
+            "Responder requested Diffie-Hellman group {:?}",
+            requested_group
+        );
+        report.add_requested_group(requested_group);
+        let proposed = proposal.diffie_group.iter().any(|transform| {
+            let transform_id = transform.transform_id;
+            transform_id.get() == u16::from(requested_group)
+        });
+        if !proposed
+            || !key_exchange::is_supported(requested_group)
+            || tried_groups.contains(&requested_group)
+        {
+            return Ok(Some(ike_v2_response));
+        }
+        tried_groups.push(requested_group);
+        ike_v2.set_key_exchange_group(requested_group)?;
+        ike_v2.calculate_length_v2();
+    }
+}
+
+///Erkennt die Ike Versionen des Ziels und scannt jede Version, die der Server spricht.
+/// Zuerst wird wie bei discover() je eine IkeV1 (Version 0x10) und IkeV2 (Version 0x20) Probe gesendet.
+/// Antwortet der Server auf eine Probe mit INVALID_MAJOR_VERSION, mit einer anderen Version
+/// oder gar nicht, wird diese Version nicht gescannt.
+/// Die Ergebnisse beider Scans und der Erkennung werden in einem Bericht zusammengefasst
+pub async fn scan_auto(
+    target: impl ToSocketAddrs,
+    options: &ScanOptions,
+) -> Result<ScanReport, ScanError> {
+    let remote_addr = resolve_target(target, options.bind_address).await?;
+    match Connection::open(remote_addr, options).await {
+        Ok(mut connection) => scan_auto_transport(&mut connection, options).await,
+        Err(ScanError::PortUnreachable) => {
+            let mut report = ScanReport::new(remote_addr);
+            report.discovery = Some(DiscoveryReport::closed(remote_addr));
+            Ok(report)
+        }
+        Err(error) => Err(error),
+    }
+}
+
+///Führt den Scan wie scan_auto() über einen bereits geöffneten Transport aus
+pub async fn scan_auto_transport(
+    transport: &mut impl Transport,
+    options: &ScanOptions,
+) -> Result<ScanReport, ScanError> {
+    let mut context = ProbeContext::new(options)?;
+    let discovery = discover_limited(transport, options, &mut context).await?;
+    let mut report = ScanReport::new(discovery.target);
+    if discovery.ike_v1 == EndpointState::Responder {
+        report.ike_v1 = scan_limited(transport, options, &mut context).await?.ike_v1;
+    }
+    if discovery.ike_v2 == EndpointState::Responder {
+        report.ike_v2 = scan_v2_limited(transport, options, &mut context)
+            .await?
+            .ike_v2;
+    }
+    report.discovery = Some(discovery);
+    report.discarded_responses = context.tracker.discarded();
+    Ok(report)
+}
+
+///Prüft schnell, ob am Ziel ein Ike Server antwortet.
+/// Es wird je eine Probe mit einer minimalen Auswahl (TransformSelection::discovery())
+/// für IkeV1 (Main Mode) und IkeV2 (IKE_SA_INIT) gesendet.
+/// Jede Antwort, auch eine Notification, zählt als Antwort eines Ike Servers,
+/// ICMP Port Unreachable bedeutet, dass der Port geschlossen ist
+pub async fn discover(
+    target: impl ToSocketAddrs,
+    options: &ScanOptions,
+) -> Result<DiscoveryReport, ScanError> {
+    let remote_addr = resolve_target(target, options.bind_address).await?;
+    match Connection::open(remote_addr, options).await {
+        Ok(mut connection) => discover_transport(&mut connection, options).await,
+        Err(ScanError::PortUnreachable) => Ok(DiscoveryReport::closed(remote_addr)),
+        Err(error) => Err(error),
+    }
+}
+
+///Führt die Erkennung wie discover() über einen bereits geöffneten Transport aus
+pub async fn discover_transport(
+    transport: &mut impl Transport,
+    options: &ScanOptions,
+) -> Result<DiscoveryReport, ScanError> {
+    let mut context = ProbeContext::new(options)?;
+    discover_limited(transport, options, &mut context).await
+}
+
+///Erkennung mit dem Zustand der Proben, den auch andere Scans desselben Ziels verwenden
+async fn discover_limited(
+    transport: &mut impl Transport,
+    options: &ScanOptions,
+    context: &mut ProbeContext,
+) -> Result<DiscoveryReport, ScanError> {
+    let mut report = DiscoveryReport::new(transport.peer_addr()?);
+    let selection = TransformSelection::discovery();
+
+    let transforms = selection.v1.apply(IkeV1::build_transforms());
+    let (initiator_spi, bytes) = build_probe_v1(&transforms, options)?;
+    wait_for_next_probe(context).await;
+    report.ike_v1 = discovery_probe(transport, &bytes, initiator_spi, 1, options, context).await?;
+    if report.ike_v1 == EndpointState::Closed {
+        report.ike_v2 = EndpointState::Closed;
+        return Ok(report);
+    }
+
+    let (encryption, prf, integrity_algorithm, diffie_group) =
+        selection.v2.apply(IkeV2::build_transforms_v2());
+    let proposal = ProposalV2 {
+        encryption: &encryption,
+        prf: &prf,
+        integrity_algorithm: &integrity_algorithm,
+        diffie_group: &diffie_group,
+    };
+    let mut ike_v2 = build_probe_v2(
+        &proposal,
+        transport.local_addr()?,
+        transport.peer_addr()?,
+        options,
+    )?;
+    let initiator_spi_v2 = ike_v2.header.initiator_spi.get();
+    wait_for_next_probe(context).await;
+    report.ike_v2 = discovery_probe(
+        transport,
+        &ike_v2.convert_to_bytes_v2(),
+        initiator_spi_v2,
+        2,
+        options,
+        context,
+    )
+    .await?;
+    //the ICMP error of the first probe may only arrive with the second one
+    if report.ike_v2 == EndpointState::Closed && report.ike_v1 == EndpointState::NoResponse {
+        report.ike_v1 = EndpointState::Closed;
+    }
+    Ok(report)
+}
+
+///Sendet eine Probe der Erkennung mit der Hauptversion 'major_version' und wertet aus,
+/// ob eine Antwort kam und ob der Server die Version unterstützt.
+/// Antworten mit einer anderen Hauptversion werden der Probe ebenfalls zugeordnet
+async fn discovery_probe(
+    transport: &mut impl Transport,
+    bytes: &[u8],
+    initiator_spi: u64,
+    major_version: u8,
+    options: &ScanOptions,
+    context: &mut ProbeContext,
+) -> Result<EndpointState, ScanError> {
+    let probe = Probe::new(initiator_spi, major_version).any_version();
+    let mut buf = vec![0u8; MAX_MESSAGE_LENGTH];
+    match exchange(transport, &probe, bytes, &mut buf, options, context).await {
+        Ok(Some(length)) => Ok(classify_response(&buf[..length], major_version)),
+        Ok(None) => Ok(EndpointState::NoResponse),
+        Err(ScanError::PortUnreachable) => Ok(EndpointState::Closed),
+        Err(error) => Err(error),
+    }
+}
+
+///Wertet die Antwort auf eine Probe der Erkennung aus, die Antwort gehört bereits zur Probe.
+/// Jede Antwort zählt als Antwort eines Ike Servers, eine andere Hauptversion im Header
+/// oder die Notification INVALID_MAJOR_VERSION bedeuten, dass die Version nicht unterstützt wird
+fn classify_response(response: &[u8], major_version: u8) -> EndpointState {
+    let version = response
+        .get(IKE_VERSION_OFFSET)
+        .copied()
+        .unwrap_or_default();
+    if version >> 4 != major_version {
+        debug!(
+            "Response with version {:#04x} to version {} probe",
+            version, major_version
+        );
+        return EndpointState::Unsupported;
+    }
+    let invalid_major_version = if major_version == 1 {
+        ResponsePacket::parse(response).map(|packet| packet.invalid_major_version())
+    } else {
+        ResponsePacketV2::parse(response).map(|packet| packet.invalid_major_version())
+    };
+    match invalid_major_version {
+        Ok(true) => EndpointState::Unsupported,
+        Ok(false) => EndpointState::Responder,
+        Err(error) => {
+            debug!("Response could not be parsed during discovery: {}", error);
+            EndpointState::Responder
+        }
+    }
+}
+
+///Erstellt ein IkeV2 Paket (IKE_SA_INIT) mit den übergebenen Transformationen,
+/// Key-Exchange Daten für die erste unterstützte Gruppe und einer Nonce.
+/// Bei NAT-Traversal werden die NAT Detection Notifications für 'local_addr' und 'remote_addr' angehängt
+fn build_probe_v2(
+    proposal: &ProposalV2<'_>,
+    local_addr: SocketAddr,
+    remote_addr: SocketAddr,
+    options: &ScanOptions,
+) -> Result<IkeV2, ScanError> {
+    let initiator_spi_v2: u64 = rand::thread_rng().gen();
+    let mut ike_v2 = IkeV2 {
+        header: IkeV2Header {
+            initiator_spi: U64::from(initiator_spi_v2),
+            responder_spi: U64::from(0),
+            next_payload: u8::from(PayloadTypeV2::SecurityAssociation),
+            version: 32,
+            exchange_type: u8::from(ExchangeTypeV2::IkeSaInit),
+            flag: 8,
+            message_id: 0,
+            length: Default::default(),
+        },
+        cookie: None,
+        sa_payload_v2: SecurityAssociationV2 {
+            sa2_next_payload: u8::from(PayloadTypeV2::KeyExchange),
+            critical_bit: 0,
+            sa2_length: Default::default(),
+        },
+        proposal_v2: Proposal {
+            next_proposal: 0,
+            reserved: 0,
+            length: Default::default(),
+            proposal_number: 1,
+            protocol_id: ProtocolId::IKE,
+            spi_size: 0,
+            number_of_transforms: Default::default(),
+        },
+        encryption_transforms: vec![],
+        prf_transform: vec![],
+        integrity_algorithm_transform: vec![],
+        diffie_transform: vec![],
+        key_exchange: KeyExchangePayloadV2 {
+            next_payload: u8::from(PayloadTypeV2::Nonce),
+            reserved: 0,
+            length: Default::default(),
+            diffie_hellman_group: Default::default(),
+            reserved2: Default::default(),
+        },
+        key_exchange_data: vec![],
+        nonce_payload: NoncePayloadV2 {
+            next_payload_: 0,
+            reserved: 0,
+            length: Default::default(),
+        },
+        nonce_data: vec![],
+        notify_payloads: vec![],
+    };
+    if options.transport == TransportMode::NatTraversal {
+        ike_v2.add_nat_detection(local_addr, remote_addr)?;
+    }
+    ike_v2.set_transforms_v2(
+        proposal.encryption,
+        proposal.prf,
+        proposal.integrity_algorithm,
+        proposal.diffie_group,
+    )?;
+    ike_v2.generate_key_exchange_data()?;
+    ike_v2.generate_nonce_data();
+    ike_v2.calculate_length_v2();
+    Ok(ike_v2)
+}
+
+///Wartet vor jeder Probe, bis der Token-Bucket eine weitere Probe erlaubt
+async fn wait_for_next_probe(context: &mut ProbeContext) {
+    context.limiter.acquire().await;
+}
+
+///Entfernt alle Transformationen, auf die 'accepted' zutrifft.
+/// Gibt zurück, ob mindestens eine Transformation entfernt wurde
+fn exclude<T>(transforms: &mut Vec<T>, accepted: impl Fn(&T) -> bool) -> bool {
+    let proposed = transforms.len();
+    transforms.retain(|transform| !accepted(transform));
+    transforms.len() != proposed
+}
+
+///Entfernt die erste Transformation, auf die 'accepted' zutrifft.
+/// Gibt zurück, ob eine Transformation entfernt wurde
+fn exclude_first<T>(transforms: &mut Vec<T>, accepted: impl Fn(&T) -> bool) -> bool {
+    match transforms.iter().position(accepted) {
+        Some(index) => {
+            transforms.remove(index);
+            true
+        }
+        None => false,
+    }
+}
+
+///Entfernt die Transformationen mit der Transformations-ID 'transform_id'
+fn exclude_id(transforms: &mut Vec<TransformV2>, transform_id: u16) -> bool {
+    exclude(transforms, |transform| {
+        let id = transform.transform_id;
+        id.get() == transform_id
+    })
+}
+
+///Löst das Ziel auf. Ist die lokale Adresse konkret angegeben, wird die erste Adresse
+/// derselben Familie verwendet, sonst die erste gefundene Adresse (IPv4 oder IPv6)
+pub async fn resolve_target(
+    target: impl ToSocketAddrs,
+    bind_address: SocketAddr,
+) -> Result<SocketAddr, ScanError> {
+    let addresses: Vec<SocketAddr> = lookup_host(target).await?.collect();
+    addresses
+        .iter()
+        .find(|address| {
+            bind_address.ip().is_unspecified() || address.is_ipv4() == bind_address.is_ipv4()
+        })
+        .or(addresses.first())
+        .copied()
+        .ok_or_else(|| ScanError::InvalidInput("target could not be resolved".to_string()))
+}
+
+///Sendet eine Probe und wartet auf die Antwort.
+/// Ohne Antwort wird die Probe nach dem Zeitplan in den Einstellungen erneut gesendet,
+/// die Wartezeit verlängert sich dabei mit jedem Versuch.
+/// Über einen zuverlässigen Transport wird nur weiter gewartet, außer der Server hat die Verbindung geschlossen.
+/// Nachrichten, die nicht zur Probe gehören (correlation.rs), werden verworfen.
+/// Wenn auch nach der letzten Wiederholung keine Antwort kommt, wird None zurückgegeben.
+/// Antworten und fehlende Antworten passen die Rate des Token-Buckets an
+async fn exchange(
+    transport: &mut impl Transport,
+    probe: &Probe,
+    bytes: &[u8],
+    buf: &mut [u8],
+    options: &ScanOptions,
+    context: &mut ProbeContext,
+) -> Result<Option<usize>, ScanError> {
+    context.tracker.register(probe);
+    let mut send = true;
+    for attempt in 0..=options.retransmission.retries {
+        if send {
+            transport.send(bytes).await?;
+        }
+        send = !transport.is_reliable();
+        let timeout = options
+            .retransmission
+            .timeout(options.receive_timeout, attempt);
+        match receive_response(transport, probe, buf, timeout, &mut context.tracker).await {
+            Ok(length) => {
+                context.limiter.on_response();
+                return Ok(Some(length));
+            }
+            Err(ScanError::Timeout) => {
+                debug!("No response after {:?} (attempt {})", timeout, attempt + 1);
+                context.limiter.on_timeout();
+            }
+            Err(ScanError::ConnectionClosed) => {
+                debug!("Connection closed by target (attempt {})", attempt + 1);
+                send = true;
+            }
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(None)
+}
+
+///Empfängt Nachrichten, bis die Antwort auf die Probe kommt oder 'timeout' abgelaufen ist.
+/// Verworfene Nachrichten, auch solche ohne Non-ESP Marker, verlängern die Wartezeit nicht
+async fn receive_response(
+    transport: &mut impl Transport,
+    probe: &Probe,
+    buf: &mut [u8],
+    timeout: Duration,
+    tracker: &mut ProbeTracker,
+) -> Result<usize, ScanError> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match transport.receive(buf, remaining).await {
+            Ok(length) if tracker.accept(probe, &buf[..length]) => return Ok(length),
+            Ok(_) => {}
+            Err(error @ ScanError::NonIkeMessage(_)) => tracker.discard(error),
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+///Zustand der Proben an ein Ziel, den alle Scans desselben Ziels teilen
+struct ProbeContext {
+    ///Token-Bucket für die Rate der Proben
+    limiter: RateLimiter,
+    ///gesendete Proben und verworfene Nachrichten
+    tracker: ProbeTracker,
+}
+
+impl ProbeContext {
+    ///Erstellt den Zustand mit der Startrate aus den Einstellungen.
+    /// Ein Backoff-Faktor von null wird abgelehnt, da jede Wiederholung sonst sofort abläuft
+    fn new(options: &ScanOptions) -> Result<Self, ScanError> {
+        if options.retransmission.backoff_factor == 0 {
+            return Err(ScanError::InvalidInput(
+                "backoff factor must not be zero".to_string(),
+            ));
+        }
+        Ok(ProbeContext {
+            limiter: RateLimiter::new(&options.rate_limit)?,
+            tracker: ProbeTracker::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv6Addr;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
+    use crate::algorithms::AuthenticationMethodV1;
+    use crate::algorithms::DiffieHellmanGroup;
+    use crate::algorithms::EncryptionAlgorithmV1;
+    use crate::algorithms::EncryptionAlgorithmV2;
+    use crate::algorithms::HashAlgorithmV1;
+    use crate::algorithms::IntegrityAlgorithmV2;
+    use crate::algorithms::PseudoRandomFunctionV2;
+    use crate::ike::NotifyMessageTypeV1;
+    use crate::ikev2::AttributeType;
+    use crate::ikev2::NotifyMessageTypeV2;
+    use crate::options::RateLimit;
+    use crate::options::Retransmission;
+    use crate::options::TransformSelectionV1;
+    use crate::options::TransformSelectionV2;
+    use crate::parse_ikev2::ParsedTransformV2;
+    use crate::parse_ikev2::ResponsePayloadV2;
+    use crate::report::AcceptedAlgorithmsV2;
+    use crate::report::AcceptedTransformV1;
+    use crate::transport::MemoryTransport;
+    use crate::transport::NatTraversalTransport;
+    use crate::transport::NON_ESP_MARKER;
+
+    use super::*;
+
+    const RESPONDER_SPI: u64 = 0x1112_1314_1516_1718;
+
+    ///Schnelle Proben, kurze Wartezeit ohne Wiederholung und wenige Transformationen:
+    /// IkeV1 AES (drei Schlüssellängen), SHA1, Pre-Shared Key, MODP 2048;
+    /// IkeV2 AES-CBC, HMAC-SHA1, HMAC-SHA1-96, MODP 2048 und ECP 256
+    fn options() -> ScanOptions {
+        ScanOptions {
+            rate_limit: RateLimit {
+                rate: 1000.0,
+                max_rate: 1000.0,
+                burst: 10.0,
+                ..RateLimit::default()
+            },
+            receive_timeout: Duration::from_millis(200),
+            retransmission: Retransmission {
+                retries: 0,
+                ..Retransmission::default()
+            },
+            transforms: TransformSelection {
+                v1: TransformSelectionV1 {
+                    encryption: Some(vec![7]),
+                    hash: Some(vec![2]),
+                    authentication_method: Some(vec![1]),
+                    diffie_hellman_group: Some(vec![14]),
+                    key_length: None,
+                },
+                v2: TransformSelectionV2 {
+                    encryption: Some(vec![12]),
+                    prf: Some(vec![2]),
+                    integrity: Some(vec![2]),
+                    diffie_hellman_group: Some(vec![14, 19]),
+                },
+            },
+            ..ScanOptions::default()
+        }
+    }
+
+    ///Startet einen Server am anderen Ende eines MemoryTransport.
+    /// 'respond' erhält jede Anfrage und gibt die Antworten zurück, die gesendet werden
+    fn responder(
+        mut respond: impl FnMut(&[u8]) -> Vec<Vec<u8>> + Send + 'static,
+    ) -> MemoryTransport {
+        let local_addr = SocketAddr::from(([192, 0, 2, 1], 500));
+        let remote_addr = SocketAddr::from(([198, 51, 100, 7], 500));
+        let (scanner, mut server) = MemoryTransport::pair(local_addr, remote_addr);
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; MAX_MESSAGE_LENGTH];
+            while let Ok(length) = server.receive(&mut buf, Duration::from_secs(60)).await {
+                for reply in respond(&buf[..length]) {
+                    if server.send(&reply).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        scanner
+    }
+
+    ///Antwort auf 'request' mit dem Initiator SPI der Anfrage und dem Inhalt 'body'
+    fn reply(request: &[u8], next_payload: u8, exchange_type: u8, body: &[u8]) -> Vec<u8> {
+        let version = request[IKE_VERSION_OFFSET];
+        let flags = if version >> 4 == 2 { 0x20 } else { 0 };
+        let mut reply = request[..8].to_vec();
+        reply.extend_from_slice(&RESPONDER_SPI.to_be_bytes());
+        reply.extend_from_slice(&[next_payload, version, exchange_type, flags, 0, 0, 0, 0]);
+        reply.extend_from_slice(&(IKE_HEADER_LENGTH as u32 + body.len() as u32).to_be_bytes());
+        reply.extend_from_slice(body);
+        reply
+    }
+
+    ///Payload mit generischem Header
+    fn payload(next_payload: u8, body: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![next_payload, 0];
+        bytes.extend_from_slice(&(4 + body.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    ///IkeV1 Main Mode Antwort, die die erste Transformation der Anfrage akzeptiert
+    fn accept_first_transform_v1(request: &[u8]) -> Vec<u8> {
+        accept_transform_v1(request, &first_transform_v1(request))
+    }
+
+    ///Erste Transformation der IkeV1 Anfrage als letzte Transformation eines Proposals
+    fn first_transform_v1(request: &[u8]) -> Vec<u8> {
+        //header, security association and proposal header come before the first transform
+        let offset = IKE_HEADER_LENGTH + 12 + 8;
+        let length = usize::from(u16::from_be_bytes([
+            request[offset + 2],
+            request[offset + 3],
+        ]));
+        let mut transform = request[offset..offset + length].to_vec();
+        transform[0] = 0;
+        transform
+    }
+
+    ///Transformation ohne Key Length Attribut, wie sie manche Server zurückgeben
+    fn without_key_length(transform: &[u8]) -> Vec<u8> {
+        let mut stripped = transform[..8].to_vec();
+        let mut attributes = &transform[8..];
+        while !attributes.is_empty() {
+            let attribute_type = u16::from_be_bytes([attributes[0], attributes[1]]);
+            let length = if attribute_type & 0x8000 != 0 {
+                4
+            } else {
+                4 + usize::from(u16::from_be_bytes([attributes[2], attributes[3]]))
+            };
+            if attribute_type != 0x800e {
+                stripped.extend_from_slice(&attributes[..length]);
+            }
+            attributes = &attributes[length..];
+        }
+        let length = stripped.len() as u16;
+        stripped[2..4].copy_from_slice(&length.to_be_bytes());
+        stripped
+    }
+
+    ///IkeV1 Main Mode Antwort, die 'transform' akzeptiert
+    fn accept_transform_v1(request: &[u8], transform: &[u8]) -> Vec<u8> {
+        let mut body = vec![0, 0, 0, 1, 0, 0, 0, 1, 0, 0];
+        body.extend_from_slice(&(8 + transform.len() as u16).to_be_bytes());
+        body.extend_from_slice(&[1, 1, 0, 1]);
+        body.extend_from_slice(transform);
+        reply(request, 1, 2, &payload(0, &body))
+    }
+
+    ///IkeV1 Informational Antwort mit einer Notification
+    fn notify_v1(request: &[u8], message_type: NotifyMessageTypeV1) -> Vec<u8> {
+        let mut body = vec![0, 0, 0, 1, 1, 0];
+        body.extend_from_slice(&u16::from(message_type).to_be_bytes());
+        reply(request, 11, 5, &payload(0, &body))
+    }
+
+    ///Diffie-Hellman Gruppe des Key Exchange Payloads der IkeV2 Anfrage
+    fn key_exchange_group(request: &[u8]) -> u16 {
+        let request = ResponsePacketV2::parse(request).unwrap();
+        request
+            .payloads
+            .iter()
+            .find_map(|payload| match payload {
+                ResponsePayloadV2::KeyExchange(key_exchange) => {
+                    Some(key_exchange.diffie_hellman_group)
+                }
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    ///IKE_SA_INIT Antwort, die AES-CBC-128, HMAC-SHA1, HMAC-SHA1-96 und die Gruppe der Anfrage akzeptiert
+    fn accept_v2(request: &[u8]) -> Vec<u8> {
+        let group = key_exchange_group(request);
+        accept_transforms_v2(
+            request,
+            &[
+                (1, 12, &[0x80, 14, 0, 128][..]),
+                (2, 2, &[][..]),
+                (3, 2, &[][..]),
+                (4, group, &[][..]),
+            ],
+        )
+    }
+
+    ///IKE_SA_INIT Antwort, die 'accepted' (Transformationstyp, ID, Attribute) akzeptiert
+    fn accept_transforms_v2(request: &[u8], accepted: &[(u8, u16, &[u8])]) -> Vec<u8> {
+        let mut transforms = vec![];
+        for (index, &(transform_type, transform_id, attribute)) in accepted.iter().enumerate() {
+            let last = index + 1 == accepted.len();
+            transforms.extend_from_slice(&[if last { 0 } else { 3 }, 0]);
+            transforms.extend_from_slice(&(8 + attribute.len() as u16).to_be_bytes());
+            transforms.extend_from_slice(&[transform_type, 0]);
+            transforms.extend_from_slice(&transform_id.to_be_bytes());
+            transforms.extend_from_slice(attribute);
+        }
+        let mut proposal = vec![0, 0];
+        proposal.extend_from_slice(&(8 + transforms.len() as u16).to_be_bytes());
+        proposal.extend_from_slice(&[1, 1, 0, accepted.len() as u8]);
+        proposal.extend_from_slice(&transforms);
+        reply(request, 33, 34, &payload(0, &proposal))
+    }
+
+    ///Transformationen des ersten Proposals der IkeV2 Anfrage
+    fn proposed_transforms_v2(request: &[u8]) -> Vec<ParsedTransformV2> {
+        let request = ResponsePacketV2::parse(request).unwrap();
+        request
+            .payloads
+            .into_iter()
+            .find_map(|payload| match payload {
+                ResponsePayloadV2::SecurityAssociation(mut proposals) => {
+                    Some(proposals.remove(0).transforms)
+                }
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    ///Server, der AES-CBC-256, AES-GCM-16, HMAC-SHA2-256, HMAC-SHA1-96, MODP 2048 und ECP 256 unterstützt.
+    /// Er wählt je Transformationstyp die erste unterstützte Transformation der Anfrage
+    /// und verlangt mit INVALID_KE_PAYLOAD die gewählte Gruppe, wenn der Key Exchange nicht passt
+    fn select_v2(request: &[u8]) -> Vec<u8> {
+        let transforms = proposed_transforms_v2(request);
+        let select = |transform_type: u8| {
+            transforms.iter().find(|transform| {
+                transform.transform_type == transform_type
+                    && match (transform_type, transform.transform_id) {
+                        (1, 12) => transform.attribute_value(AttributeType::KeyLength) == Some(256),
+                        (1, 20) | (2, 5) | (3, 2) | (4, 14) | (4, 19) => true,
+                        _ => false,
+                    }
+            })
+        };
+        let (Some(encryption), Some(prf), Some(group)) = (select(1), select(2), select(4)) else {
+            return notify_v2(request, NotifyMessageTypeV2::NoProposalChosen, &[]);
+        };
+        let integrity = select(3);
+        if integrity.is_none() && encryption.transform_id != 20 {
+            return notify_v2(request, NotifyMessageTypeV2::NoProposalChosen, &[]);
+        }
+        if group.transform_id != key_exchange_group(request) {
+            return notify_v2(
+                request,
+                NotifyMessageTypeV2::InvalidKePayload,
+                &group.transform_id.to_be_bytes(),
+            );
+        }
+        let key_length = if encryption.transform_id == 12 {
+            &[0x80, 14, 1, 0][..]
+        } else {
+            &[][..]
+        };
+        let mut accepted = vec![
+            (1, encryption.transform_id, key_length),
+            (2, prf.transform_id, &[][..]),
+        ];
+        if let Some(integrity) = integrity {
+            accepted.push((3, integrity.transform_id, &[][..]));
+        }
+        accepted.push((4, group.transform_id, &[][..]));
+        accept_transforms_v2(request, &accepted)
+    }
+
+    ///Optionen für die IkeV2 Enumeration mit select_v2
+    fn enumeration_options() -> ScanOptions {
+        let mut options = options();
+        options.enumerate = true;
+        options.transforms.v2 = TransformSelectionV2 {
+            encryption: Some(vec![12, 20]),
+            prf: Some(vec![2, 5]),
+            integrity: Some(vec![2]),
+            diffie_hellman_group: Some(vec![14, 19, 20]),
+        };
+        options
+    }
+
+    ///IKE_SA_INIT Antwort mit einer Notification
+    fn notify_v2(request: &[u8], message_type: NotifyMessageTypeV2, data: &[u8]) -> Vec<u8> {
+        let mut body = vec![0, 0];
+        body.extend_from_slice(&u16::from(message_type).to_be_bytes());
+        body.extend_from_slice(data);
+        reply(request, 41, 34, &payload(0, &body))
+    }
+
+    fn aes_transform_v1(key_length: u16) -> AcceptedTransformV1 {
+        AcceptedTransformV1 {
+            encryption: EncryptionAlgorithmV1::AesCbc,
+            key_length: Some(key_length),
+            hash: HashAlgorithmV1::Sha1,
+            diffie_hellman_group: DiffieHellmanGroup::Modp2048,
+            authentication_method: AuthenticationMethodV1::PreSharedKey,
+        }
+    }
+
+    #[tokio::test]
+    async fn v1_scan_reports_accepted_transform() {
+        let mut transport = responder(|request| vec![accept_first_transform_v1(request)]);
+        let report = scan_transport(&mut transport, &options()).await.unwrap();
+        let ike_v1 = report.ike_v1.unwrap();
+        assert_eq!(ike_v1.transforms, vec![aes_transform_v1(128)]);
+        assert_eq!(ike_v1.unanswered_probes, 0);
+        assert_eq!(report.discarded_responses.total(), 0);
+    }
+
+    #[tokio::test]
+    async fn v1_no_proposal_chosen_is_reported() {
+        let mut transport =
+            responder(|request| vec![notify_v1(request, NotifyMessageTypeV1::NoProposalChosen)]);
+        let report = scan_transport(&mut transport, &options()).await.unwrap();
+        let ike_v1 = report.ike_v1.unwrap();
+        assert!(ike_v1.transforms.is_empty());
+        assert_eq!(
+            ike_v1.notify_errors,
+            vec![NotifyMessageTypeV1::NoProposalChosen]
+        );
+    }
+
+    #[tokio::test]
+    async fn v1_enumeration_discards_late_replies() {
+        //every reply arrives twice, the copy is read while waiting for the next probe
+        let mut transport = responder(|request| {
+            let reply = accept_first_transform_v1(request);
+            vec![reply.clone(), reply]
+        });
+        let options = ScanOptions {
+            enumerate: true,
+            ..options()
+        };
+        let report = scan_transport(&mut transport, &options).await.unwrap();
+        assert_eq!(
+            report.ike_v1.unwrap().transforms,
+            vec![
+                aes_transform_v1(128),
+                aes_transform_v1(192),
+                aes_transform_v1(256)
+            ]
+        );
+        assert_eq!(report.discarded_responses.late, 2);
+        assert_eq!(report.discarded_responses.unknown_spi, 0);
+    }
+
+    #[tokio::test]
+    async fn v1_enumeration_without_key_length_tests_every_length() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&requests);
+        let mut transport = responder(move |request| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let transform = without_key_length(&first_transform_v1(request));
+            vec![accept_transform_v1(request, &transform)]
+        });
+        let options = ScanOptions {
+            enumerate: true,
+            ..options()
+        };
+        let report = scan_transport(&mut transport, &options).await.unwrap();
+        let transforms = report.ike_v1.unwrap().transforms;
+        assert!(transforms
+            .iter()
+            .all(|transform| transform.key_length.is_none()));
+        //one probe per key length, each reply removes only the first proposed length
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn v1_key_length_selection_limits_probes() {
+        let mut transport = responder(|request| vec![accept_first_transform_v1(request)]);
+        let mut options = ScanOptions {
+            enumerate: true,
+            ..options()
+        };
+        options.transforms.v1.key_length = Some(vec![256]);
+        let report = scan_transport(&mut transport, &options).await.unwrap();
+        assert_eq!(
+            report.ike_v1.unwrap().transforms,
+            vec![aes_transform_v1(256)]
+        );
+    }
+
+    #[tokio::test]
+    async fn discovery_counts_padded_and_cut_replies() {
+        let mut transport = responder(|request| {
+            if request[IKE_VERSION_OFFSET] >> 4 == 1 {
+                let mut reply = accept_first_transform_v1(request);
+                reply.extend_from_slice(&[0; 4]);
+                vec![reply]
+            } else {
+                let mut reply = accept_v2(request);
+                reply.truncate(reply.len() - 8);
+                vec![reply]
+            }
+        });
+        let report = discover_transport(&mut transport, &options())
+            .await
+            .unwrap();
+        assert_eq!(report.ike_v1, EndpointState::Responder);
+        assert_eq!(report.ike_v2, EndpointState::Responder);
+    }
+
+    #[tokio::test]
+    async fn reply_with_unknown_spi_is_discarded() {
+        let mut transport = responder(|request| {
+            let reply = accept_first_transform_v1(request);
+            let mut foreign = reply.clone();
+            foreign[..8].copy_from_slice(&[0xee; 8]);
+            vec![foreign, reply]
+        });
+        let report = scan_transport(&mut transport, &options()).await.unwrap();
+        assert_eq!(report.ike_v1.unwrap().transforms.len(), 1);
+        assert_eq!(report.discarded_responses.unknown_spi, 1);
+    }
+
+    #[tokio::test]
+    async fn nat_keepalive_and_esp_packet_are_discarded() {
+        let server = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = server.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; MAX_MESSAGE_LENGTH];
+            let (length, source) = server.recv_from(&mut buf).await.unwrap();
+            let request = &buf[NON_ESP_MARKER.len()..length];
+            let mut reply = NON_ESP_MARKER.to_vec();
+            reply.extend_from_slice(&accept_first_transform_v1(request));
+            //NAT keepalive, ESP packet with SPI 1, then the reply
+            for datagram in [&[0xff][..], &[0, 0, 0, 1, 0, 0, 0, 1][..], &reply] {
+                server.send_to(datagram, source).await.unwrap();
+            }
+        });
+        let mut transport =
+            NatTraversalTransport::connect(server_addr, SocketAddr::from(([127, 0, 0, 1], 0)))
+                .await
+                .unwrap();
+        let options = ScanOptions {
+            transport: TransportMode::NatTraversal,
+            ..options()
+        };
+        let report = scan_transport(&mut transport, &options).await.unwrap();
+        assert_eq!(
+            report.ike_v1.unwrap().transforms,
+            vec![aes_transform_v1(128)]
+        );
+        assert_eq!(report.discarded_responses.non_ike, 2);
+        assert_eq!(report.discarded_responses.total(), 2);
+    }
+
+    #[tokio::test]
+    async fn unanswered_probe_is_counted() {
+        let mut transport = responder(|_| vec![]);
+        let report = scan_transport(&mut transport, &options()).await.unwrap();
+        let ike_v1 = report.ike_v1.unwrap();
+        assert!(ike_v1.transforms.is_empty());
+        assert_eq!(ike_v1.unanswered_probes, 1);
+    }
+
+    #[tokio::test]
+    async fn v2_scan_reports_accepted_transform() {
+        let mut transport = responder(|request| vec![accept_v2(request)]);
+        let report = scan_v2_transport(&mut transport, &options()).await.unwrap();
+        let ike_v2 = report.ike_v2.unwrap();
+        assert_eq!(ike_v2.transforms.len(), 1);
+        assert_eq!(
+            ike_v2.transforms[0].encryption,
+            EncryptionAlgorithmV2::AesCbc
+        );
+        assert_eq!(ike_v2.transforms[0].key_length, Some(128));
+        assert_eq!(
+            ike_v2.transforms[0].diffie_hellman_group,
+            DiffieHellmanGroup::Modp2048
+        );
+        assert!(!ike_v2.cookie_required);
+        assert!(ike_v2.requested_groups.is_empty());
+    }
+
+    #[tokio::test]
+    async fn v2_cookie_is_sent_back() {
+        let cookie = [0x5a; 16];
+        let mut transport = responder(move |request| {
+            let packet = ResponsePacketV2::parse(request).unwrap();
+            match packet.cookie() {
+                Some(sent) if sent == cookie => vec![accept_v2(request)],
+                Some(_) => vec![],
+                None => vec![notify_v2(request, NotifyMessageTypeV2::Cookie, &cookie)],
+            }
+        });
+        let report = scan_v2_transport(&mut transport, &options()).await.unwrap();
+        let ike_v2 = report.ike_v2.unwrap();
+        assert!(ike_v2.cookie_required);
+        assert_eq!(ike_v2.transforms.len(), 1);
+        assert_eq!(ike_v2.unanswered_probes, 0);
+    }
+
+    #[tokio::test]
+    async fn v2_fresh_cookies_are_not_followed_forever() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&requests);
+        let mut transport = responder(move |request| {
+            let count = counter.fetch_add(1, Ordering::SeqCst);
+            vec![notify_v2(
+                request,
+                NotifyMessageTypeV2::Cookie,
+                &[count as u8; 16],
+            )]
+        });
+        let report = scan_v2_transport(&mut transport, &options()).await.unwrap();
+        let ike_v2 = report.ike_v2.unwrap();
+        assert!(ike_v2.cookie_required);
+        assert!(ike_v2.transforms.is_empty());
+        assert_eq!(requests.load(Ordering::SeqCst), 1 + MAX_COOKIE_RETRIES);
+    }
+
+    #[tokio::test]
+    async fn v2_invalid_ke_payload_retries_requested_group() {
+        let mut transport = responder(|request| {
+            if key_exchange_group(request) == 19 {
+                vec![accept_v2(request)]
+            } else {
+                vec![notify_v2(
+                    request,
+                    NotifyMessageTypeV2::InvalidKePayload,
+                    &19u16.to_be_bytes(),
+                )]
+            }
+        });
+        let report = scan_v2_transport(&mut transport, &options()).await.unwrap();
+        let ike_v2 = report.ike_v2.unwrap();
+        assert_eq!(ike_v2.requested_groups, vec![DiffieHellmanGroup::from(19)]);
+        assert_eq!(ike_v2.transforms.len(), 1);
+        assert_eq!(
+            ike_v2.transforms[0].diffie_hellman_group,
+            DiffieHellmanGroup::from(19)
+        );
+    }
+
+    #[tokio::test]
+    async fn v2_enumeration_finds_every_accepted_algorithm() {
+        let without_integrity = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&without_integrity);
+        let mut transport = responder(move |request| {
+            let transforms = proposed_transforms_v2(request);
+            if !transforms
+                .iter()
+                .any(|transform| transform.transform_type == 3)
+            {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+            vec![select_v2(request)]
+        });
+        let report = scan_v2_transport(&mut transport, &enumeration_options())
+            .await
+            .unwrap();
+        let ike_v2 = report.ike_v2.unwrap();
+        assert_eq!(
+            ike_v2.algorithms,
+            AcceptedAlgorithmsV2 {
+                encryption: vec![
+                    (EncryptionAlgorithmV2::AesCbc, Some(256)),
+                    (EncryptionAlgorithmV2::AesGcm16, None),
+                ],
+                prf: vec![PseudoRandomFunctionV2::HmacSha2_256],
+                integrity: vec![IntegrityAlgorithmV2::HmacSha1_96],
+                diffie_hellman_group: vec![
+                    DiffieHellmanGroup::Modp2048,
+                    DiffieHellmanGroup::from(19)
+                ],
+            }
+        );
+        assert!(ike_v2
+            .notify_errors
+            .contains(&NotifyMessageTypeV2::NoProposalChosen));
+        assert_eq!(ike_v2.unanswered_probes, 0);
+        //CBC ciphers are still proposed once HMAC-SHA1-96 is excluded, so no proposal lacks integrity
+        assert_eq!(without_integrity.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn v2_enumeration_stops_without_response() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&requests);
+        let mut transport = responder(move |request| {
+            if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                vec![select_v2(request)]
+            } else {
+                vec![]
+            }
+        });
+        let report = scan_v2_transport(&mut transport, &enumeration_options())
+            .await
+            .unwrap();
+        let ike_v2 = report.ike_v2.unwrap();
+        assert_eq!(ike_v2.transforms.len(), 1);
+        //one silent probe for encryption, prf and group, none for the only integrity algorithm
+        assert_eq!(ike_v2.unanswered_probes, 3);
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+    }
+
+    ///Hauptversion der Anfrage
+    fn major_version(request: &[u8]) -> u8 {
+        request[IKE_VERSION_OFFSET] >> 4
+    }
+
+    #[tokio::test]
+    async fn auto_scan_of_v1_only_responder() {
+        let mut transport = responder(|request| {
+            if major_version(request) == 1 {
+                vec![accept_first_transform_v1(request)]
+            } else {
+                //an IKEv1 server answers with an IKEv1 INVALID_MAJOR_VERSION notification
+                let mut reply = notify_v1(request, NotifyMessageTypeV1::InvalidMajorVersion);
+                reply[IKE_VERSION_OFFSET] = 0x10;
+                vec![reply]
+            }
+        });
+        let report = scan_auto_transport(&mut transport, &options())
+            .await
+            .unwrap();
+        let discovery = report.discovery.unwrap();
+        assert_eq!(discovery.ike_v1, EndpointState::Responder);
+        assert_eq!(discovery.ike_v2, EndpointState::Unsupported);
+        assert_eq!(
+            report.ike_v1.unwrap().transforms,
+            vec![aes_transform_v1(128)]
+        );
+        assert!(report.ike_v2.is_none());
+    }
+
+    #[tokio::test]
+    async fn auto_scan_of_v2_only_responder() {
+        let mut transport = responder(|request| {
+            if major_version(request) == 2 {
+                vec![accept_v2(request)]
+            } else {
+                vec![]
+            }
+        });
+        let report = scan_auto_transport(&mut transport, &options())
+            .await
+            .unwrap();
+        let discovery = report.discovery.unwrap();
+        assert_eq!(discovery.ike_v1, EndpointState::NoResponse);
+        assert_eq!(discovery.ike_v2, EndpointState::Responder);
+        assert!(report.ike_v1.is_none());
+        assert_eq!(report.ike_v2.unwrap().transforms.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn auto_scan_of_v1_and_v2_responder() {
+        let mut transport = responder(|request| {
+            if major_version(request) == 1 {
+                vec![accept_first_transform_v1(request)]
+            } else {
+                vec![accept_v2(request)]
+            }
+        });
+        let report = scan_auto_transport(&mut transport, &options())
+            .await
+            .unwrap();
+        let discovery = report.discovery.unwrap();
+        assert_eq!(discovery.ike_v1, EndpointState::Responder);
+        assert_eq!(discovery.ike_v2, EndpointState::Responder);
+        assert_eq!(
+            report.ike_v1.unwrap().transforms,
+            vec![aes_transform_v1(128)]
+        );
+        assert_eq!(report.ike_v2.unwrap().transforms.len(), 1);
+        assert_eq!(report.discarded_responses.total(), 0);
+    }
+
+    #[tokio::test]
+    async fn target_address_family_follows_bind_address() {
+        let v4 = SocketAddr::from(([192, 0, 2, 7], 500));
+        let v6 = SocketAddr::from((Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 7), 500));
+        let addresses = [v4, v6];
+        //an unspecified bind address takes the first address of either family
+        let unspecified = SocketAddr::from(([0, 0, 0, 0], 0));
+        assert_eq!(
+            resolve_target(&addresses[..], unspecified).await.unwrap(),
+            v4
+        );
+        let unspecified = SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0));
+        assert_eq!(
+            resolve_target(&addresses[..], unspecified).await.unwrap(),
+            v4
+        );
+        //a concrete bind address selects the first address of its family
+        let concrete = SocketAddr::from((Ipv6Addr::LOCALHOST, 0));
+        assert_eq!(resolve_target(&addresses[..], concrete).await.unwrap(), v6);
+        let concrete = SocketAddr::from(([127, 0, 0, 1], 0));
+        assert_eq!(resolve_target(&[v6, v4][..], concrete).await.unwrap(), v4);
+        //without an address of that family the first address is used
+        assert_eq!(resolve_target(&[v6][..], concrete).await.unwrap(), v6);
+    }
 }
@@ -0,0 +1,596 @@
+//! # Bike-Scan
+//! Kommandozeilenprogramm für scan() und scan_v2().
+//! Die Ziele, die Ike Version, die Transformationen, die Zeiten und das Ausgabeformat
+//! werden über Argumente festgelegt.
+//! Exit-Codes: 0 = Transformationen gefunden, 1 = keine Transformationen gefunden,
+//! 2 = ungültige Argumente, 3 = mindestens ein Scan ist fehlgeschlagen
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::Parser;
+use clap::ValueEnum;
+use serde::Serialize;
+use trufflescan::discover;
+use trufflescan::discover_transport;
+use trufflescan::engine::ProbeEngine;
+use trufflescan::error::ScanError;
+use trufflescan::options::RateLimit;
+use trufflescan::options::Retransmission;
+use trufflescan::options::ScanOptions;
+use trufflescan::options::TransportMode;
+use trufflescan::report::DiscoveryReport;
+use trufflescan::report::EndpointState;
+use trufflescan::report::ScanReport;
+use trufflescan::resolve_target;
+use trufflescan::scan;
+use trufflescan::scan_auto;
+use trufflescan::scan_auto_transport;
+use trufflescan::scan_transport;
+use trufflescan::scan_v2;
+use trufflescan::scan_v2_transport;
+use trufflescan::scheduler::scan_targets;
+use trufflescan::scheduler::TargetResult;
+use trufflescan::targets::parse_target;
+use trufflescan::targets::read_target_file;
+use trufflescan::targets::Target;
+
+///Exit-Code, wenn Transformationen gefunden wurden
+const EXIT_FOUND: u8 = 0;
+///Exit-Code, wenn keine Transformationen gefunden wurden
+const EXIT_NOT_FOUND: u8 = 1;
+///Exit-Code bei ungültigen Argumenten
+const EXIT_INVALID_ARGUMENTS: u8 = 2;
+///Exit-Code, wenn ein Scan fehlgeschlagen ist
+const EXIT_SCAN_FAILED: u8 = 3;
+
+///Scans IPsec servers for the IKE transforms they accept
+#[derive(Debug, Parser)]
+#[command(
+    name = "trufflescan",
+    version,
+    after_help = "Exit codes: 0 = transforms found, 1 = no transforms found, 2 = invalid arguments, 3 = at least one scan failed"
+)]
+struct Args {
+    ///Targets to scan (IP address, hostname, CIDR range, each optionally with :port)
+    #[arg(required_unless_present = "target_file")]
+    targets: Vec<String>,
+    ///File with one target per line (may be given more than once)
+    #[arg(short = 'f', long)]
+    target_file: Vec<PathBuf>,
+    ///Maximum number of targets scanned at the same time
+    #[arg(long, default_value_t = 64)]
+    max_concurrent: usize,
+    ///Maximum number of scans of the same host at the same time
+    #[arg(long, default_value_t = 1)]
+    max_per_host: usize,
+    ///Share one unconnected socket per address family between all targets instead of a connected
+    /// socket per target (UDP and NAT-T); closed ports (ICMP port unreachable) are then reported as without response
+    #[arg(long)]
+    shared_socket: bool,
+    ///Datagrams per sendmmsg/recvmmsg call on the shared socket (Linux only, 1 sends each datagram on its own)
+    #[arg(long, default_value_t = 32)]
+    batch_size: usize,
+    ///Port used for targets given without a port [default: 500, 4500 with NAT-T or TCP]
+    #[arg(short, long)]
+    port: Option<u16>,
+    ///Transport of the IKE messages
+    #[arg(long, value_enum, default_value_t = TransportArg::Udp)]
+    transport: TransportArg,
+    ///IKE version to scan
+    #[arg(short = 'i', long = "ike-version", value_enum, default_value_t = VersionArg::Auto)]
+    ike_version: VersionArg,
+    ///Local address the socket is bound to
+    #[arg(long, default_value = "0.0.0.0:0")]
+    bind: SocketAddr,
+    ///Seconds to wait for a response
+    #[arg(short, long, default_value = "10", value_parser = parse_seconds)]
+    timeout: Duration,
+    ///Number of retransmissions of an unanswered probe
+    #[arg(long, default_value_t = 2)]
+    retries: u32,
+    ///Factor the timeout is multiplied with after each retransmission (at least 1)
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u32).range(1..))]
+    backoff: u32,
+    ///Maximum seconds to wait for a response after retransmissions (at least --timeout)
+    #[arg(long, default_value = "60", value_parser = parse_seconds)]
+    max_timeout: Duration,
+    ///Initial probes per second sent to a target, adapted to the target's behaviour
+    #[arg(long, default_value_t = 1.0, value_parser = parse_rate)]
+    rate: f64,
+    ///Lowest probe rate after timeouts and cookie requests (probes per second)
+    #[arg(long, default_value_t = 1.0 / 60.0, value_parser = parse_rate)]
+    min_rate: f64,
+    ///Highest probe rate for a responsive target (probes per second)
+    #[arg(long, default_value_t = 20.0, value_parser = parse_rate)]
+    max_rate: f64,
+    ///Number of probes that may be sent back to back
+    #[arg(long, default_value_t = 1.0, value_parser = parse_rate)]
+    burst: f64,
+    ///Number of transforms per IKEv1 packet
+    #[arg(long, default_value_t = 255)]
+    chunk_size_v1: usize,
+    ///Number of transforms per IKEv2 transform type and packet
+    #[arg(long, default_value_t = 63)]
+    chunk_size_v2: usize,
+    ///IKEv1 encryption algorithm IDs to test (e.g. 5,7)
+    #[arg(long, value_delimiter = ',')]
+    v1_encryption: Option<Vec<u16>>,
+    ///IKEv1 hash algorithm IDs to test
+    #[arg(long, value_delimiter = ',')]
+    v1_hash: Option<Vec<u16>>,
+    ///IKEv1 authentication method IDs to test
+    #[arg(long, value_delimiter = ',')]
+    v1_auth: Option<Vec<u16>>,
+    ///IKEv1 Diffie-Hellman group IDs to test
+    #[arg(long, value_delimiter = ',')]
+    v1_dh_group: Option<Vec<u16>>,
+    ///IKEv1 key lengths in bits to test for ciphers with a variable key length (e.g. 128,256)
+    #[arg(long, value_delimiter = ',')]
+    v1_key_length: Option<Vec<u16>>,
+    ///IKEv2 encryption algorithm IDs to test (e.g. 12,20)
+    #[arg(long, value_delimiter = ',')]
+    v2_encryption: Option<Vec<u16>>,
+    ///IKEv2 PRF IDs to test
+    #[arg(long, value_delimiter = ',')]
+    v2_prf: Option<Vec<u16>>,
+    ///IKEv2 integrity algorithm IDs to test
+    #[arg(long, value_delimiter = ',')]
+    v2_integrity: Option<Vec<u16>>,
+    ///IKEv2 Diffie-Hellman group IDs to test
+    #[arg(long, value_delimiter = ',')]
+    v2_dh_group: Option<Vec<u16>>,
+    ///Re-probe without accepted transforms until the server sends NO_PROPOSAL_CHOSEN
+    #[arg(short, long)]
+    enumerate: bool,
+    ///Only send one minimal IKEv1 and IKEv2 probe and list the targets that answer
+    #[arg(long)]
+    discover: bool,
+    ///Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+}
+
+///Auswahl der Ike Version
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum VersionArg {
+    ///IKEv1 only
+    V1,
+    ///IKEv2 only
+    V2,
+    ///Detect the IKE versions and scan every version the target speaks
+    Auto,
+}
+
+///Transport der Ike Nachrichten
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum TransportArg {
+    ///Plain UDP
+    Udp,
+    ///UDP with non-ESP marker (NAT traversal)
+    NatT,
+    ///TCP encapsulation (RFC 8229)
+    Tcp,
+}
+
+///Ausgabeformat der Berichte
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    ///Human readable text
+    Text,
+    ///JSON array with one report per target
+    Json,
+}
+
+impl Args {
+    ///Übernimmt die Argumente in die Einstellungen für den Scan
+    fn scan_options(&self) -> ScanOptions {
+        let mut options = ScanOptions {
+            bind_address: self.bind,
+            rate_limit: RateLimit {
+                rate: self.rate,
+                min_rate: self.min_rate,
+                max_rate: self.max_rate,
+                burst: self.burst,
+                ..RateLimit::default()
+            },
+            receive_timeout: self.timeout,
+            retransmission: Retransmission {
+                retries: self.retries,
+                backoff_factor: self.backoff,
+                max_timeout: self.max_timeout,
+            },
+            chunk_size_v1: self.chunk_size_v1,
+            chunk_size_v2: self.chunk_size_v2,
+            transport: match self.transport {
+                TransportArg::Udp => TransportMode::Udp,
+                TransportArg::NatT => TransportMode::NatTraversal,
+                TransportArg::Tcp => TransportMode::Tcp,
+            },
+            enumerate: self.enumerate,
+            max_concurrent_targets: self.max_concurrent,
+            max_concurrent_per_host: self.max_per_host,
+            batch_size: self.batch_size,
+            ..ScanOptions::default()
+        };
+        options.transforms.v1.encryption = self.v1_encryption.clone();
+        options.transforms.v1.hash = self.v1_hash.clone();
+        options.transforms.v1.authentication_method = self.v1_auth.clone();
+        options.transforms.v1.diffie_hellman_group = self.v1_dh_group.clone();
+        options.transforms.v1.key_length = self.v1_key_length.clone();
+        options.transforms.v2.encryption = self.v2_encryption.clone();
+        options.transforms.v2.prf = self.v2_prf.clone();
+        options.transforms.v2.integrity = self.v2_integrity.clone();
+        options.transforms.v2.diffie_hellman_group = self.v2_dh_group.clone();
+        options
+    }
+
+    ///Alle Ziele aus den Argumenten und den Zieldateien, CIDR Bereiche werden aufgelöst
+    fn targets(&self) -> Result<Vec<Target>, ScanError> {
+        let mut targets = vec![];
+        for target in &self.targets {
+            targets.extend(parse_target(target, self.default_port())?);
+        }
+        for path in &self.target_file {
+            targets.extend(read_target_file(path, self.default_port())?);
+        }
+        Ok(targets)
+    }
+
+    ///Port für Ziele ohne Port, abhängig vom Transport
+    fn default_port(&self) -> u16 {
+        self.port.unwrap_or(match self.transport {
+            TransportArg::Udp => 500,
+            TransportArg::NatT | TransportArg::Tcp => 4500,
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+    let options = args.scan_options();
+    if let Err(error) = check_options(&options) {
+        eprintln!("{}", error);
+        return ExitCode::from(EXIT_INVALID_ARGUMENTS);
+    }
+    let engine = if args.shared_socket {
+        match ProbeEngine::new(&options) {
+            Ok(engine) => Some(engine),
+            Err(error) => {
+                eprintln!("{}", error);
+                return ExitCode::from(EXIT_INVALID_ARGUMENTS);
+            }
+        }
+    } else {
+        None
+    };
+    let targets = match args.targets() {
+        Ok(targets) => targets,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::from(EXIT_INVALID_ARGUMENTS);
+        }
+    };
+    if args.discover {
+        return discover_targets(targets, &options, engine, args.output).await;
+    }
+    let version = args.ike_version;
+    let results = match scan_targets(targets, &options, move |target, options| {
+        let engine = engine.clone();
+        async move { scan_target(&target, version, &options, engine.as_ref()).await }
+    })
+    .await
+    {
+        Ok(results) => results,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::from(EXIT_INVALID_ARGUMENTS);
+        }
+    };
+    let (reports, mut failed) = split_results(results);
+
+    match args.output {
+        OutputFormat::Text => reports.iter().for_each(print_report),
+        OutputFormat::Json => failed |= !print_json(&reports),
+    }
+
+    if failed {
+        ExitCode::from(EXIT_SCAN_FAILED)
+    } else if reports.iter().any(has_transforms) {
+        ExitCode::from(EXIT_FOUND)
+    } else {
+        ExitCode::from(EXIT_NOT_FOUND)
+    }
+}
+
+///Prüft die Einstellungen einmal vor dem Scan, damit ungültige Argumente
+/// nicht erst beim Scan jedes Ziels auffallen
+fn check_options(options: &ScanOptions) -> Result<(), ScanError> {
+    options.rate_limit.validate()?;
+    if options.retransmission.max_timeout < options.receive_timeout {
+        return Err(ScanError::InvalidInput(format!(
+            "maximum timeout {:?} is lower than the timeout {:?}",
+            options.retransmission.max_timeout, options.receive_timeout
+        )));
+    }
+    Ok(())
+}
+
+///Erkennung: gibt nur die Ziele aus, die auf eine Probe geantwortet haben,
+/// als Text eine Zeile pro Ziel mit den Ike Versionen (kann als Zieldatei verwendet werden)
+async fn discover_targets(
+    targets: Vec<Target>,
+    options: &ScanOptions,
+    engine: Option<ProbeEngine>,
+    output: OutputFormat,
+) -> ExitCode {
+    let results = match scan_targets(targets, options, move |target, options| {
+        let engine = engine.clone();
+        async move { discover_target(&target, &options, engine.as_ref()).await }
+    })
+    .await
+    {
+        Ok(results) => results,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::from(EXIT_INVALID_ARGUMENTS);
+        }
+    };
+    let (mut reports, mut failed) = split_results(results);
+    reports.retain(DiscoveryReport::is_responder);
+
+    match output {
+        OutputFormat::Text => reports.iter().for_each(|report| {
+            let versions: Vec<&str> = [(report.ike_v1, "IKEv1"), (report.ike_v2, "IKEv2")]
+                .into_iter()
+                .filter(|(state, _)| *state == EndpointState::Responder)
+                .map(|(_, version)| version)
+                .collect();
+            println!("{} {}", report.target, versions.join(" "));
+        }),
+        OutputFormat::Json => failed |= !print_json(&reports),
+    }
+
+    if failed {
+        ExitCode::from(EXIT_SCAN_FAILED)
+    } else if reports.is_empty() {
+        ExitCode::from(EXIT_NOT_FOUND)
+    } else {
+        ExitCode::from(EXIT_FOUND)
+    }
+}
+
+///Trennt die Berichte von den Fehlern, die Fehler werden ausgegeben.
+/// Gibt zusätzlich zurück, ob mindestens ein Scan fehlgeschlagen ist
+fn split_results<R>(results: Vec<TargetResult<R>>) -> (Vec<R>, bool) {
+    let mut reports = vec![];
+    let mut failed = false;
+    for result in results {
+        match result.result {
+            Ok(report) => reports.push(report),
+            Err(error) => {
+                eprintln!("{}: {}", result.target, error);
+                failed = true;
+            }
+        }
+    }
+    (reports, failed)
+}
+
+///Gibt die Berichte als JSON aus, gibt false zurück, wenn das nicht möglich war
+fn print_json(reports: &impl Serialize) -> bool {
+    match serde_json::to_string_pretty(reports) {
+        Ok(json) => {
+            println!("{}", json);
+            true
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            false
+        }
+    }
+}
+
+///Prüft ein Ziel, mit 'engine' über deren gemeinsamen Socket
+async fn discover_target(
+    target: &Target,
+    options: &ScanOptions,
+    engine: Option<&ProbeEngine>,
+) -> Result<DiscoveryReport, ScanError> {
+    let address = (target.host.as_str(), target.port);
+    let Some(engine) = engine else {
+        return discover(address, options).await;
+    };
+    let remote_addr = resolve_target(address, options.bind_address).await?;
+    discover_transport(&mut engine.open(remote_addr).await?, options).await
+}
+
+///Scannt ein Ziel mit der gewählten Ike Version, mit 'engine' über deren gemeinsamen Socket.
+/// Bei Auto werden die Versionen erkannt und alle unterstützten Versionen gescannt
+async fn scan_target(
+    target: &Target,
+    version: VersionArg,
+    options: &ScanOptions,
+    engine: Option<&ProbeEngine>,
+) -> Result<ScanReport, ScanError> {
+    let address = (target.host.as_str(), target.port);
+    let Some(engine) = engine else {
+        return match version {
+            VersionArg::V1 => scan(address, options).await,
+            VersionArg::V2 => scan_v2(address, options).await,
+            VersionArg::Auto => scan_auto(address, options).await,
+        };
+    };
+    let remote_addr = resolve_target(address, options.bind_address).await?;
+    let mut transport = engine.open(remote_addr).await?;
+    match version {
+        VersionArg::V1 => scan_transport(&mut transport, options).await,
+        VersionArg::V2 => scan_v2_transport(&mut transport, options).await,
+        VersionArg::Auto => scan_auto_transport(&mut transport, options).await,
+    }
+}
+
+///Prüft, ob im Bericht mindestens eine Transformation gefunden wurde
+fn has_transforms(report: &ScanReport) -> bool {
+    report
+        .ike_v1
+        .as_ref()
+        .is_some_and(|ike_v1| !ike_v1.transforms.is_empty())
+        || report
+            .ike_v2
+            .as_ref()
+            .is_some_and(|ike_v2| !ike_v2.transforms.is_empty())
+}
+
+///Gibt einen Bericht als lesbaren Text aus
+fn print_report(report: &ScanReport) {
+    println!("Target {}", report.target);
+    if let Some(discovery) = &report.discovery {
+        println!(
+            "  Detected versions: IKEv1 {:?}, IKEv2 {:?}",
+            discovery.ike_v1, discovery.ike_v2
+        );
+    }
+    if let Some(ike_v1) = &report.ike_v1 {
+        println!("  IKEv1: exchange type {:?}", ike_v1.exchange_type);
+        for transform in &ike_v1.transforms {
+            println!(
+                "    Encryption {:?}{}, Hash {:?}, DH group {:?}, Auth {:?}",
+                transform.encryption,
+                key_length_suffix(transform.key_length),
+                transform.hash,
+                transform.diffie_hellman_group,
+                transform.authentication_method
+            );
+        }
+        if ike_v1.nat_traversal {
+            println!("    NAT-T supported");
+        }
+        for notify in &ike_v1.notify_errors {
+            println!("    Notify {:?}", notify);
+        }
+        for vendor_id in &ike_v1.vendor_ids {
+            println!("    Vendor ID {}", vendor_id);
+        }
+        if ike_v1.unanswered_probes > 0 {
+            println!("    {} probes without response", ike_v1.unanswered_probes);
+        }
+    }
+    if let Some(ike_v2) = &report.ike_v2 {
+        println!("  IKEv2: exchange type {:?}", ike_v2.exchange_type);
+        for transform in &ike_v2.transforms {
+            println!(
+                "    Encryption {:?}{}, PRF {:?}, Integrity {:?}, DH group {:?}",
+                transform.encryption,
+                key_length_suffix(transform.key_length),
+                transform.prf,
+                transform.integrity,
+                transform.diffie_hellman_group
+            );
+        }
+        let algorithms = &ike_v2.algorithms;
+        if !algorithms.encryption.is_empty() {
+            let encryption: Vec<String> = algorithms
+                .encryption
+                .iter()
+                .map(|(encryption, key_length)| {
+                    format!("{:?}{}", encryption, key_length_suffix(*key_length))
+                })
+                .collect();
+            println!("    Accepted encryption: {}", encryption.join(", "));
+            println!("    Accepted PRF: {:?}", algorithms.prf);
+            println!("    Accepted integrity: {:?}", algorithms.integrity);
+            println!(
+                "    Accepted DH groups: {:?}",
+                algorithms.diffie_hellman_group
+            );
+        }
+        if ike_v2.cookie_required {
+            println!("    Cookies enforced");
+        }
+        if let Some(nat_detection) = ike_v2.nat_detection {
+            println!(
+                "    NAT detection: scanner behind NAT {}, target behind NAT {}",
+                nat_detection.local_behind_nat, nat_detection.remote_behind_nat
+            );
+        }
+        if !ike_v2.requested_groups.is_empty() {
+            println!(
+                "    DH groups requested by INVALID_KE_PAYLOAD: {:?}",
+                ike_v2.requested_groups
+            );
+        }
+        for notify in &ike_v2.notify_errors {
+            println!("    Notify {:?}", notify);
+        }
+        for vendor_id in &ike_v2.vendor_ids {
+            println!("    Vendor ID {}", vendor_id);
+        }
+        if ike_v2.unanswered_probes > 0 {
+            println!("    {} probes without response", ike_v2.unanswered_probes);
+        }
+    }
+    let discarded = &report.discarded_responses;
+    if discarded.total() > 0 {
+        println!(
+            "  Discarded {} messages: {} late, {} unknown SPI, {} invalid header, {} truncated, {} over-long, {} non-IKE",
+            discarded.total(),
+            discarded.late,
+            discarded.unknown_spi,
+            discarded.invalid_header,
+            discarded.truncated,
+            discarded.overlong,
+            discarded.non_ike
+        );
+    }
+}
+
+///Schlüssellänge für die Textausgabe
+fn key_length_suffix(key_length: Option<u16>) -> String {
+    key_length.map_or(String::new(), |bits| format!(" ({} bit)", bits))
+}
+
+///Liest eine positive Rate
+fn parse_rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0.0 => Ok(rate),
+        _ => Err(format!("'{}' is not a valid positive number", value)),
+    }
+}
+
+///Liest eine Zeitangabe in Sekunden
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| format!("'{}' is not a valid number of seconds", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_seconds() {
+        assert_eq!(parse_seconds("2.5"), Ok(Duration::from_millis(2500)));
+        assert_eq!(parse_seconds("0"), Ok(Duration::ZERO));
+        for invalid in ["-1", "NaN", "inf", "1e20", "ten"] {
+            assert!(parse_seconds(invalid).is_err());
+        }
+    }
+
+    #[test]
+    fn max_timeout_below_timeout_is_rejected() {
+        let args = Args::parse_from(["trufflescan", "192.0.2.1", "-t", "5", "--max-timeout", "2"]);
+        assert!(matches!(
+            check_options(&args.scan_options()),
+            Err(ScanError::InvalidInput(_))
+        ));
+        let args = Args::parse_from(["trufflescan", "192.0.2.1", "-t", "5", "--max-timeout", "5"]);
+        assert!(check_options(&args.scan_options()).is_ok());
+    }
+}
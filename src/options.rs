@@ -0,0 +1,302 @@
+//! # Bike-Scan
+//! das folgende Modul enthält die Einstellungen für einen Scan.
+//! Die Felder werden von scan() und scan_v2() ausgewertet
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use crate::error::ScanError;
+use crate::ike::Transform;
+use crate::ikev2::TransformAttributeV2;
+use crate::ikev2::TransformV2;
+
+///Einstellungen für einen Scan
+/// alle Felder sind öffentlich, mit Default werden die bisherigen Werte übernommen
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    ///lokale Adresse, an die der Socket gebunden wird
+    pub bind_address: SocketAddr,
+    ///Rate, mit der Proben an ein Ziel gesendet werden
+    pub rate_limit: RateLimit,
+    ///Wartezeit auf eine Antwort des Servers beim ersten Senden einer Probe
+    pub receive_timeout: Duration,
+    ///Zeitplan für das erneute Senden einer unbeantworteten Probe
+    pub retransmission: Retransmission,
+    ///Anzahl der Transformationen in einem IkeV1 Paket (maximal 255)
+    pub chunk_size_v1: usize,
+    ///Anzahl der Transformationen je Transformationstyp in einem IkeV2 Paket
+    pub chunk_size_v2: usize,
+    ///Auswahl der zu testenden Transformationen
+    pub transforms: TransformSelection,
+    ///Transport der Ike Nachrichten
+    pub transport: TransportMode,
+    ///alle akzeptierten Transformationen ermitteln, nicht nur die vom Server bevorzugte.
+    /// Akzeptierte Transformationen werden entfernt und der Rest erneut angeboten
+    pub enumerate: bool,
+    ///maximale Anzahl gleichzeitig gescannter Ziele bei scan_targets()
+    pub max_concurrent_targets: usize,
+    ///maximale Anzahl gleichzeitiger Scans desselben Hosts bei scan_targets()
+    pub max_concurrent_per_host: usize,
+    ///Anzahl der Datagramme je sendmmsg/recvmmsg Aufruf der ProbeEngine (nur Linux),
+    /// mit 1 sendet und empfängt die Engine jedes Datagramm einzeln
+    pub batch_size: usize,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions {
+            bind_address: SocketAddr::from(([0, 0, 0, 0], 0)),
+            rate_limit: RateLimit::default(),
+            receive_timeout: Duration::from_secs(10),
+            retransmission: Retransmission::default(),
+            chunk_size_v1: 255,
+            chunk_size_v2: 63,
+            transforms: TransformSelection::default(),
+            transport: TransportMode::default(),
+            enumerate: false,
+            max_concurrent_targets: 64,
+            max_concurrent_per_host: 1,
+            batch_size: 32,
+        }
+    }
+}
+
+///Transport der Ike Nachrichten
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum TransportMode {
+    ///Ike direkt über UDP (üblicherweise Port 500)
+    #[default]
+    Udp,
+    ///NAT-Traversal: UDP mit vorangestelltem Non-ESP Marker (üblicherweise Port 4500, RFC 3948).
+    /// IkeV1 sendet die NAT-Traversal Hersteller-IDs, IkeV2 die NAT Detection Notifications
+    NatTraversal,
+    ///Ike über TCP mit dem Präfix "IKETCP" und einer Längenangabe vor jeder Nachricht
+    /// (üblicherweise Port 4500 oder 443, RFC 8229)
+    Tcp,
+}
+
+///Einstellungen des Token-Buckets für die Proben an ein Ziel (rate_limit.rs).
+/// Die Rate startet bei 'rate' und bewegt sich zwischen 'min_rate' und 'max_rate':
+/// jede Antwort multipliziert sie mit 'increase_factor',
+/// jede unbeantwortete Probe und jedes verlangte Cookie mit 'decrease_factor'
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    ///Startrate in Proben pro Sekunde
+    pub rate: f64,
+    ///minimale Rate in Proben pro Sekunde
+    pub min_rate: f64,
+    ///maximale Rate in Proben pro Sekunde
+    pub max_rate: f64,
+    ///Anzahl der Proben, die ohne Wartezeit nacheinander gesendet werden dürfen
+    pub burst: f64,
+    ///Faktor, um den die Rate nach einer Antwort steigt
+    pub increase_factor: f64,
+    ///Faktor, um den die Rate ohne Antwort oder bei einem Cookie sinkt
+    pub decrease_factor: f64,
+}
+
+impl RateLimit {
+    ///Prüft die Einstellungen: alle Raten endlich und größer null, die Startrate zwischen
+    /// minimaler und maximaler Rate, 'burst' mindestens eins, 'increase_factor' mindestens eins
+    /// und 'decrease_factor' größer null und höchstens eins
+    pub fn validate(&self) -> Result<(), ScanError> {
+        let valid_rate = |rate: f64| rate.is_finite() && rate > 0.0;
+        let valid = valid_rate(self.min_rate)
+            && valid_rate(self.max_rate)
+            && (self.min_rate..=self.max_rate).contains(&self.rate)
+            && self.burst.is_finite()
+            && self.burst >= 1.0
+            && self.increase_factor.is_finite()
+            && self.increase_factor >= 1.0
+            && self.decrease_factor > 0.0
+            && self.decrease_factor <= 1.0;
+        if !valid {
+            return Err(ScanError::InvalidInput(format!(
+                "invalid rate limit {:?}",
+                self
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        RateLimit {
+            rate: 1.0,
+            min_rate: 1.0 / 60.0,
+            max_rate: 20.0,
+            burst: 1.0,
+            increase_factor: 1.25,
+            decrease_factor: 0.5,
+        }
+    }
+}
+
+///Zeitplan für das erneute Senden (RFC 7296, Abschnitt 2.1)
+/// die Wartezeit wird nach jedem Versuch mit 'backoff_factor' multipliziert,
+/// bis 'max_timeout' erreicht ist
+#[derive(Debug, Clone)]
+pub struct Retransmission {
+    ///Anzahl der Wiederholungen nach dem ersten Senden
+    pub retries: u32,
+    ///Faktor, mit dem die Wartezeit nach jedem Versuch multipliziert wird (mindestens eins)
+    pub backoff_factor: u32,
+    ///maximale Wartezeit für einen Versuch
+    pub max_timeout: Duration,
+}
+
+impl Default for Retransmission {
+    fn default() -> Self {
+        Retransmission {
+            retries: 2,
+            backoff_factor: 2,
+            max_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+impl Retransmission {
+    ///Berechnet die Wartezeit für den Versuch 'attempt' (der erste Versuch hat die Nummer 0)
+    pub fn timeout(&self, initial_timeout: Duration, attempt: u32) -> Duration {
+        self.backoff_factor
+            .checked_pow(attempt)
+            .and_then(|factor| initial_timeout.checked_mul(factor))
+            .map_or(self.max_timeout, |timeout| timeout.min(self.max_timeout))
+    }
+}
+
+///Auswahl der Transformationen für IkeV1 und IkeV2
+#[derive(Debug, Clone, Default)]
+pub struct TransformSelection {
+    ///Auswahl für IkeV1
+    pub v1: TransformSelectionV1,
+    ///Auswahl für IkeV2
+    pub v2: TransformSelectionV2,
+}
+
+impl TransformSelection {
+    ///Minimale, weit verbreitete Auswahl für die Erkennung von Ike Servern:
+    /// IkeV1 3DES, SHA1, Pre-Shared Key, MODP 1024;
+    /// IkeV2 AES-CBC, HMAC-SHA1, HMAC-SHA1-96, MODP 2048
+    pub fn discovery() -> Self {
+        TransformSelection {
+            v1: TransformSelectionV1 {
+                encryption: Some(vec![5]),
+                hash: Some(vec![2]),
+                authentication_method: Some(vec![1]),
+                diffie_hellman_group: Some(vec![2]),
+                key_length: None,
+            },
+            v2: TransformSelectionV2 {
+                encryption: Some(vec![12]),
+                prf: Some(vec![2]),
+                integrity: Some(vec![2]),
+                diffie_hellman_group: Some(vec![14]),
+            },
+        }
+    }
+}
+
+///Auswahl der IkeV1 Attributwerte
+/// None bedeutet, dass alle Werte aus IkeV1::build_transforms() verwendet werden
+#[derive(Debug, Clone, Default)]
+pub struct TransformSelectionV1 {
+    ///erlaubte Verschlüsselungsalgorithmen
+    pub encryption: Option<Vec<u16>>,
+    ///erlaubte Hash-Typen
+    pub hash: Option<Vec<u16>>,
+    ///erlaubte Authentisierungsmethoden
+    pub authentication_method: Option<Vec<u16>>,
+    ///erlaubte Diffie-Hellman Gruppen
+    pub diffie_hellman_group: Option<Vec<u16>>,
+    ///erlaubte Schlüssellängen in Bit, Transformationen ohne Key Length Attribut werden immer verwendet
+    pub key_length: Option<Vec<u16>>,
+}
+
+impl TransformSelectionV1 {
+    ///Entfernt alle Transformationen, deren Attribute nicht ausgewählt wurden
+    pub fn apply(&self, transforms: Vec<Transform>) -> Vec<Transform> {
+        transforms
+            .into_iter()
+            .filter(|transform| {
+                let transform = *transform;
+                let encryption = transform.encryption_attribute.attribute_value_or_length;
+                let hash = transform.hash_attribute.attribute_value_or_length;
+                let authentication_method = transform
+                    .authentication_method_attribute
+                    .attribute_value_or_length;
+                let diffie_hellman = transform.diffie_hellman_attribute.attribute_value_or_length;
+                allowed(&self.encryption, encryption.get())
+                    && allowed(&self.hash, hash.get())
+                    && allowed(&self.authentication_method, authentication_method.get())
+                    && allowed(&self.diffie_hellman_group, diffie_hellman.get())
+                    && transform
+                        .key_length()
+                        .is_none_or(|key_length| allowed(&self.key_length, key_length))
+            })
+            .collect()
+    }
+}
+
+///Auswahl der IkeV2 Transformations-IDs
+/// None bedeutet, dass alle Werte aus IkeV2::build_transforms_v2() verwendet werden
+#[derive(Debug, Clone, Default)]
+pub struct TransformSelectionV2 {
+    ///erlaubte Verschlüsselungsalgorithmen
+    pub encryption: Option<Vec<u16>>,
+    ///erlaubte Pseudo-Random Funktionen
+    pub prf: Option<Vec<u16>>,
+    ///erlaubte Integritätsalgorithmen
+    pub integrity: Option<Vec<u16>>,
+    ///erlaubte Diffie-Hellman Gruppen
+    pub diffie_hellman_group: Option<Vec<u16>>,
+}
+
+impl TransformSelectionV2 {
+    ///Entfernt alle Transformationen, deren ID nicht ausgewählt wurde
+    pub fn apply(
+        &self,
+        transforms: (
+            Vec<TransformAttributeV2>,
+            Vec<TransformV2>,
+            Vec<TransformV2>,
+            Vec<TransformV2>,
+        ),
+    ) -> (
+        Vec<TransformAttributeV2>,
+        Vec<TransformV2>,
+        Vec<TransformV2>,
+        Vec<TransformV2>,
+    ) {
+        let (encryption, prf, integrity, diffie_group) = transforms;
+        (
+            encryption
+                .into_iter()
+                .filter(|transform| {
+                    let transform_id = transform.transform_id;
+                    allowed(&self.encryption, transform_id.get())
+                })
+                .collect(),
+            select_v2(&self.prf, prf),
+            select_v2(&self.integrity, integrity),
+            select_v2(&self.diffie_hellman_group, diffie_group),
+        )
+    }
+}
+
+fn select_v2(selection: &Option<Vec<u16>>, transforms: Vec<TransformV2>) -> Vec<TransformV2> {
+    transforms
+        .into_iter()
+        .filter(|transform| {
+            let transform_id = transform.transform_id;
+            allowed(selection, transform_id.get())
+        })
+        .collect()
+}
+
+fn allowed(selection: &Option<Vec<u16>>, value: u16) -> bool {
+    selection
+        .as_ref()
+        .is_none_or(|values| values.contains(&value))
+}
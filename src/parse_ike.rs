@@ -1,118 +1,567 @@
 //! # Bike-Scan
-//! das folgende Modul wird zum Parsen von Ike Version 1 verwendet
+//! das folgende Modul wird zum Parsen von Ike Version 1 verwendet.
+//! Die Payloads werden anhand des Feldes next_payload nacheinander gelesen,
+//! dadurch sind beliebige Reihenfolgen und Längen der Payloads möglich
 
+use std::mem::size_of;
+
+use log::debug;
 use zerocopy::network_endian::U16;
 use zerocopy::network_endian::U32;
 use zerocopy::network_endian::U64;
 use zerocopy::FromBytes;
 use zerocopy::FromZeroes;
 
+use crate::algorithms::AuthenticationMethodV1;
+use crate::algorithms::DiffieHellmanGroup;
+use crate::algorithms::EncryptionAlgorithmV1;
+use crate::algorithms::HashAlgorithmV1;
+use crate::error::ScanError;
+use crate::ike::AttributeType;
 use crate::ike::ExchangeType;
+use crate::ike::NotifyMessageTypeV1;
+use crate::ike::PayloadTypeV1;
+use crate::ike::NAT_TRAVERSAL_VENDOR_IDS;
+use crate::report::AcceptedTransformV1;
+use crate::report::IkeV1Report;
+use crate::report::VendorId;
 
 ///Wrapperstruct für ein IkeV1-Paket
-#[derive(Debug, Clone, Copy, FromBytes, FromZeroes)]
-#[repr(packed)]
+/// enthält den Header und alle Payloads in der empfangenen Reihenfolge
+#[derive(Debug, Clone, PartialEq)]
 pub struct ResponsePacket {
     ///header
     pub header: ResponseHeader,
-    ///Security-Association Payload
-    pub sa_payload: ResponseSecurityAssociationPayload,
-    ///Proposal-Payload
-    pub proposal_payload: ResponseProposalPayload,
-    ///Transform-Payload
-    pub transform_payload: ResponseTransformWrapped,
-    ///Hersteller-Id
-    pub vendor_id_payload: ResponseVendorID,
-    ///Notify Payload für Fehlermeldungen
-    pub notify_payload: RespondNotify,
+    ///Payloads der Antwort
+    pub payloads: Vec<ResponsePayload>,
 }
 
 impl ResponsePacket {
     ///Die folgende Funktion wird zum Parsen des Pakets verwendet.
-    /// Die Attribute aus der Transformation werden als Variablen festgelegt
-    /// es werden außerdem leere Vektoren erstellt.
-    /// Wenn die Attribute einen Wert > Null haben werden die Vektoren mit dem jeweiligen Wert des Attributs gefüllt.
-    /// Anschließend wird mit einer if-Bedingung geprüft, ob die Vektoren nicht leer sind,
-    /// wenn dies der Fall ist, werden die Ike Version und der Austauschtyp ausgegeben.
-    /// Zum Schluss werden die Inhalte der Vektoren ausgegeben.
-    pub fn parse_response(self) {
-        let diffie_hellman = self
-            .transform_payload
-            .diffie_hellman_attribute
-            .attribute_value_or_length;
-        let encryption_algorithm = self
-            .transform_payload
-            .encryption_attribute
-            .attribute_value_or_length;
-        let hash_type = self
-            .transform_payload
-            .hash_attribute
-            .attribute_value_or_length;
-        let authentication_method = self
-            .transform_payload
-            .authentication_method_attribute
-            .attribute_value_or_length;
-        let notify_message = self.notify_payload.notify_message_type;
-
-        //save valid transforms
-        let mut valid_encryption_algorithm = vec![];
-        let mut valid_hash_type = vec![];
-        let mut valid_diffie_hellman_group = vec![];
-        let mut valid_authentication_method = vec![];
-        if encryption_algorithm.get() > 0
-            && hash_type.get() > 0
-            && diffie_hellman.get() > 0
-            && authentication_method.get() > 0
-        {
-            valid_encryption_algorithm.push(encryption_algorithm.get());
-            valid_hash_type.push(hash_type.get());
-            valid_diffie_hellman_group.push(diffie_hellman.get());
-            valid_authentication_method.push(authentication_method.get());
-
-            if !valid_encryption_algorithm.is_empty()
-                && !valid_hash_type.is_empty()
-                && !valid_diffie_hellman_group.is_empty()
-                && !valid_authentication_method.is_empty()
-            {
-                //parse Ike Version
-                if self.header.version == 16 {
-                    println!("Ike Version is IkeV1")
-                } else {
-                    println!("Invalid Version")
+    /// Zuerst wird der Header gelesen, danach wird der Kette der Payloads über next_payload gefolgt.
+    /// Jeder Payload wird anhand seiner Länge aus dem Puffer geschnitten und einzeln geparst.
+    /// Reicht ein Payload über das Ende der empfangenen Bytes hinaus, wird TruncatedResponse zurückgegeben,
+    /// bei ungültigen Längen MalformedResponse.
+    pub fn parse(buf: &[u8]) -> Result<Self, ScanError> {
+        let header = ResponseHeader::read_from_prefix(buf).ok_or(ScanError::TruncatedResponse {
+            expected: size_of::<ResponseHeader>(),
+            received: buf.len(),
+        })?;
+        let mut payloads = vec![];
+        let mut next_payload = header.next_payload;
+        let mut offset = size_of::<ResponseHeader>();
+        while next_payload != u8::from(PayloadTypeV1::NoNextPayload) {
+            let (generic_header, payload) = split_payload(buf, offset)?;
+            payloads.push(ResponsePayload::parse(next_payload, payload)?);
+            next_payload = generic_header.next_payload;
+            offset += payload.len();
+        }
+        Ok(ResponsePacket { header, payloads })
+    }
+
+    ///Die folgende Funktion überträgt die Antwort in den Bericht.
+    /// Die Transformationen aus dem Security Association Payload werden übernommen,
+    /// wenn Verschlüsselungsalgorithmus, Hash-Typ, Diffie-Hellman Gruppe und Authentisierungsmethode vorhanden sind.
+    /// Außerdem werden der Austauschtyp, die Hersteller-IDs und Fehlermeldungen in den Bericht geschrieben.
+    pub fn parse_response(&self, report: &mut IkeV1Report) {
+        if self.header.version != 16 {
+            debug!("Invalid Version {}", self.header.version);
+            return;
+        }
+        report.exchange_type = ExchangeType::try_from_u8(self.header.exchange_type);
+        for accepted in self.accepted_transforms() {
+            debug!("Found valid transform: {:?}", accepted);
+            if !report.transforms.contains(&accepted) {
+                report.transforms.push(accepted);
+            }
+        }
+        for payload in &self.payloads {
+            match payload {
+                ResponsePayload::Notification(notification) if notification.is_error() => {
+                    debug!("Error {:?}", notification.message_type);
+                    report.notify_errors.push(notification.message_type);
+                }
+                ResponsePayload::VendorId(vendor_id) => {
+                    if NAT_TRAVERSAL_VENDOR_IDS
+                        .iter()
+                        .any(|nat_traversal| nat_traversal == vendor_id.as_slice())
+                    {
+                        report.nat_traversal = true;
+                    }
+                    let vendor_id = VendorId(vendor_id.clone());
+                    if !report.vendor_ids.contains(&vendor_id) {
+                        report.vendor_ids.push(vendor_id);
+                    }
                 }
-                //Print Exchange Type
-                if self.header.exchange_type == u8::from(ExchangeType::IdentityProtect) {
-                    println!("Exchange Type is Main Mode")
-                } else if self.header.exchange_type == u8::from(ExchangeType::AggressiveExchange) {
-                    println!("Exchange Type is Aggressive Mode")
+                _ => {}
+            }
+        }
+    }
+
+    ///Gibt alle Transformationen aus dem Security Association Payload zurück,
+    /// bei denen Verschlüsselungsalgorithmus, Hash-Typ, Diffie-Hellman Gruppe und Authentisierungsmethode vorhanden sind
+    pub fn accepted_transforms(&self) -> Vec<AcceptedTransformV1> {
+        self.payloads
+            .iter()
+            .filter_map(|payload| match payload {
+                ResponsePayload::SecurityAssociation(security_association) => {
+                    Some(&security_association.proposals)
+                }
+                _ => None,
+            })
+            .flatten()
+            .flat_map(|proposal| &proposal.transforms)
+            .filter_map(|transform| {
+                let accepted = transform.accepted_transform();
+                if accepted.is_none() {
+                    debug!("Incomplete transform: {:?}", transform);
+                }
+                accepted
+            })
+            .collect()
+    }
+
+    ///Prüft, ob der Server mit NO_PROPOSAL_CHOSEN geantwortet hat
+    pub fn no_proposal_chosen(&self) -> bool {
+        self.payloads.iter().any(|payload| {
+            matches!(payload, ResponsePayload::Notification(notification)
+                if notification.message_type == NotifyMessageTypeV1::NoProposalChosen)
+        })
+    }
+
+    ///Gibt an, ob der Server die Version mit INVALID_MAJOR_VERSION abgelehnt hat
+    pub fn invalid_major_version(&self) -> bool {
+        self.payloads.iter().any(|payload| {
+            matches!(payload, ResponsePayload::Notification(notification)
+                if notification.message_type == NotifyMessageTypeV1::InvalidMajorVersion)
+        })
+    }
+}
+
+///Schneidet den Payload an der Stelle 'offset' aus dem Puffer.
+/// Der Payload enthält den generischen Header
+pub(crate) fn split_payload(
+    buf: &[u8],
+    offset: usize,
+) -> Result<(ResponseGenericPayloadHeader, &[u8]), ScanError> {
+    let rest = buf.get(offset..).unwrap_or_default();
+    let generic_header = ResponseGenericPayloadHeader::read_from_prefix(rest).ok_or(
+        ScanError::TruncatedResponse {
+            expected: offset + size_of::<ResponseGenericPayloadHeader>(),
+            received: buf.len(),
+        },
+    )?;
+    let length = usize::from(generic_header.length.get());
+    if length < size_of::<ResponseGenericPayloadHeader>() {
+        return Err(ScanError::MalformedResponse(format!(
+            "payload length {} at offset {}",
+            length, offset
+        )));
+    }
+    let payload = rest.get(..length).ok_or(ScanError::TruncatedResponse {
+        expected: offset + length,
+        received: buf.len(),
+    })?;
+    Ok((generic_header, payload))
+}
+
+///Liest ein Struct mit fester Größe vom Anfang eines Payloads
+/// und gibt den Rest des Payloads zurück
+pub(crate) fn read_fixed<'a, T: FromBytes>(
+    payload: &'a [u8],
+    name: &str,
+) -> Result<(T, &'a [u8]), ScanError> {
+    let fixed = T::read_from_prefix(payload)
+        .ok_or_else(|| ScanError::MalformedResponse(format!("{} payload too short", name)))?;
+    Ok((fixed, &payload[size_of::<T>()..]))
+}
+
+///Payloads einer IkeV1 Antwort
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResponsePayload {
+    ///Security Association Payload mit Proposals und Transformationen
+    SecurityAssociation(ParsedSecurityAssociation),
+    ///Key-Exchange Daten
+    KeyExchange(Vec<u8>),
+    ///Identifizierungs-Payload
+    Identification(ParsedIdentification),
+    ///Zertifikat
+    Certificate(ParsedCertificate),
+    ///Zertifikatsanfrage, die Daten enthalten die gewünschte Zertifizierungsstelle
+    CertificateRequest(ParsedCertificate),
+    ///Hash-Daten
+    Hash(Vec<u8>),
+    ///Signatur-Daten
+    Signature(Vec<u8>),
+    ///Nonce des Responders
+    Nonce(Vec<u8>),
+    ///Notification Payload (Fehler- und Statusmeldungen)
+    Notification(ParsedNotification),
+    ///Delete Payload
+    Delete(ParsedDelete),
+    ///Hersteller-ID
+    VendorId(Vec<u8>),
+    ///Payload mit unbekanntem Typ (z.B. NAT-D), die Daten ohne generischen Header
+    Unknown {
+        ///Typ des Payloads
+        payload_type: u8,
+        ///Inhalt des Payloads
+        data: Vec<u8>,
+    },
+}
+
+impl ResponsePayload {
+    ///Parst einen einzelnen Payload, 'payload' enthält den generischen Header
+    pub fn parse(payload_type: u8, payload: &[u8]) -> Result<Self, ScanError> {
+        let body = payload[size_of::<ResponseGenericPayloadHeader>()..].to_vec();
+        let Some(known_type) = PayloadTypeV1::try_from_u8(payload_type) else {
+            return Ok(ResponsePayload::Unknown {
+                payload_type,
+                data: body,
+            });
+        };
+        Ok(match known_type {
+            PayloadTypeV1::SecurityAssociation => {
+                ResponsePayload::SecurityAssociation(ParsedSecurityAssociation::parse(payload)?)
+            }
+            PayloadTypeV1::KeyExchange => ResponsePayload::KeyExchange(body),
+            PayloadTypeV1::Identification => {
+                let (fixed, data) =
+                    read_fixed::<ResponseIdentificationPayload>(payload, "identification")?;
+                ResponsePayload::Identification(ParsedIdentification {
+                    id_type: fixed.id_type,
+                    protocol_id: fixed.protocol_id,
+                    port: fixed.port.get(),
+                    data: data.to_vec(),
+                })
+            }
+            PayloadTypeV1::Certificate | PayloadTypeV1::CertificateRequest => {
+                let (fixed, data) =
+                    read_fixed::<ResponseCertificatePayload>(payload, "certificate")?;
+                let certificate = ParsedCertificate {
+                    encoding: fixed.encoding,
+                    data: data.to_vec(),
+                };
+                if let PayloadTypeV1::Certificate = known_type {
+                    ResponsePayload::Certificate(certificate)
                 } else {
-                    println!("No valid Exchange Type")
+                    ResponsePayload::CertificateRequest(certificate)
+                }
+            }
+            PayloadTypeV1::Hash => ResponsePayload::Hash(body),
+            PayloadTypeV1::Signature => ResponsePayload::Signature(body),
+            PayloadTypeV1::Nonce => ResponsePayload::Nonce(body),
+            PayloadTypeV1::Notification => {
+                let (fixed, rest) = read_fixed::<RespondNotify>(payload, "notification")?;
+                let spi_size = usize::from(fixed.spi_size);
+                let spi = rest.get(..spi_size).ok_or_else(|| {
+                    ScanError::MalformedResponse("notification SPI too long".to_string())
+                })?;
+                ResponsePayload::Notification(ParsedNotification {
+                    doi: fixed.doi.get(),
+                    protocol_id: fixed.protocol_id,
+                    message_type: NotifyMessageTypeV1::from(fixed.notify_message_type.get()),
+                    spi: spi.to_vec(),
+                    data: rest[spi_size..].to_vec(),
+                })
+            }
+            PayloadTypeV1::Delete => {
+                let (fixed, rest) = read_fixed::<ResponseDeletePayload>(payload, "delete")?;
+                let spi_size = usize::from(fixed.spi_size);
+                let spi_count = usize::from(fixed.number_of_spis.get());
+                if spi_size * spi_count != rest.len() {
+                    return Err(ScanError::MalformedResponse(
+                        "delete payload length does not match SPIs".to_string(),
+                    ));
                 }
-                println!("Found valid transforms: Encryption Algorithm is {:?}, Hash Type is {:?}, Diffie-Hellman-Group is {:?}, Authentication Method is {:?}", 
-                         valid_encryption_algorithm, valid_hash_type, valid_diffie_hellman_group, valid_authentication_method);
+                ResponsePayload::Delete(ParsedDelete {
+                    doi: fixed.doi.get(),
+                    protocol_id: fixed.protocol_id,
+                    spis: rest
+                        .chunks(spi_size.max(1))
+                        .take(spi_count)
+                        .map(<[u8]>::to_vec)
+                        .collect(),
+                })
+            }
+            PayloadTypeV1::VendorID => ResponsePayload::VendorId(body),
+            PayloadTypeV1::NoNextPayload | PayloadTypeV1::Proposal | PayloadTypeV1::Transform => {
+                return Err(ScanError::MalformedResponse(format!(
+                    "unexpected payload type {} in payload chain",
+                    payload_type
+                )))
+            }
+        })
+    }
+}
+
+///Geparster Security Association Payload
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedSecurityAssociation {
+    ///Domain of Interpretation (1 für IPsec)
+    pub doi: u32,
+    ///Situation
+    pub situation: u32,
+    ///Proposals
+    pub proposals: Vec<ParsedProposal>,
+}
+
+impl ParsedSecurityAssociation {
+    ///Parst den Security Association Payload mit allen Proposals.
+    /// Die Proposals werden über next_payload (2 = weiteres Proposal) verkettet
+    fn parse(payload: &[u8]) -> Result<Self, ScanError> {
+        let (fixed, mut rest) =
+            read_fixed::<ResponseSecurityAssociationPayload>(payload, "security association")?;
+        let mut proposals = vec![];
+        loop {
+            let (proposal_header, _) = read_fixed::<ResponseProposalPayload>(rest, "proposal")?;
+            let length = usize::from(proposal_header.length.get());
+            let proposal = rest
+                .get(..length)
+                .filter(|_| length >= size_of::<ResponseProposalPayload>())
+                .ok_or_else(|| {
+                    ScanError::MalformedResponse(format!("proposal length {}", length))
+                })?;
+            proposals.push(ParsedProposal::parse(proposal)?);
+            rest = &rest[length..];
+            if proposal_header.next_payload != u8::from(PayloadTypeV1::Proposal) {
+                break;
             }
         }
+        Ok(ParsedSecurityAssociation {
+            doi: fixed.sa_doi.get(),
+            situation: fixed.sa_situation.get(),
+            proposals,
+        })
+    }
+}
+
+///Geparstes Proposal
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedProposal {
+    ///Nummer des Proposals
+    pub proposal: u8,
+    ///Protokoll-ID (1 für IKE)
+    pub protocol_id: u8,
+    ///Security Parameter Index
+    pub spi: Vec<u8>,
+    ///Transformationen des Proposals
+    pub transforms: Vec<ParsedTransform>,
+}
 
-        //Print Transforms
-        if notify_message == U16::from(14) {
-            println!("No valid Transform found, Error {:?}", notify_message)
+impl ParsedProposal {
+    ///Parst ein Proposal mit allen Transformationen.
+    /// Die Anzahl der Transformationen muss mit number_of_transforms übereinstimmen
+    fn parse(proposal: &[u8]) -> Result<Self, ScanError> {
+        let (fixed, rest) = read_fixed::<ResponseProposalPayload>(proposal, "proposal")?;
+        let spi_size = usize::from(fixed.spi_size);
+        let spi = rest
+            .get(..spi_size)
+            .ok_or_else(|| ScanError::MalformedResponse("proposal SPI too long".to_string()))?;
+        let mut rest = &rest[spi_size..];
+        let mut transforms = vec![];
+        while !rest.is_empty() {
+            let (transform_header, _) = read_fixed::<ResponseTransformPayload>(rest, "transform")?;
+            let length = usize::from(transform_header.length.get());
+            let transform = rest
+                .get(..length)
+                .filter(|_| length >= size_of::<ResponseTransformPayload>())
+                .ok_or_else(|| {
+                    ScanError::MalformedResponse(format!("transform length {}", length))
+                })?;
+            transforms.push(ParsedTransform::parse(transform)?);
+            rest = &rest[length..];
         }
+        if transforms.len() != usize::from(fixed.number_of_transforms) {
+            return Err(ScanError::MalformedResponse(format!(
+                "proposal announces {} transforms but contains {}",
+                fixed.number_of_transforms,
+                transforms.len()
+            )));
+        }
+        Ok(ParsedProposal {
+            proposal: fixed.proposal,
+            protocol_id: fixed.protocol_id,
+            spi: spi.to_vec(),
+            transforms,
+        })
     }
 }
 
-///Response Wrapper Struct für eine Fehlermeldung
-#[derive(Debug, Clone, Copy, FromBytes, FromZeroes)]
-#[repr(packed)]
-pub struct ResponsePacketNotify {
-    ///header
-    pub header: ResponseHeader,
-    ///Fehlermeldung
-    pub notify_payload: RespondNotify,
+///Geparste Transformation mit beliebigen Attributen
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedTransform {
+    ///Nummer der Transformation
+    pub transform_number: u8,
+    ///Transformations-ID (1 für KEY_IKE)
+    pub transform_id: u8,
+    ///Attribute der Transformation
+    pub attributes: Vec<ParsedAttribute>,
+}
+
+impl ParsedTransform {
+    ///Parst die Transformation und ihre Attribute
+    fn parse(transform: &[u8]) -> Result<Self, ScanError> {
+        let (fixed, rest) = read_fixed::<ResponseTransformPayload>(transform, "transform")?;
+        let attributes = parse_attributes(rest)?;
+        Ok(ParsedTransform {
+            transform_number: fixed.transform_number,
+            transform_id: fixed.transform_id,
+            attributes,
+        })
+    }
+
+    ///Gibt den Wert des Attributs zurück, falls es vorhanden ist und in 64 Bit passt
+    pub fn attribute_value(&self, attribute_type: AttributeType) -> Option<u64> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.attribute_type == attribute_type.type_value())
+            .and_then(|attribute| attribute.value.as_u64())
+    }
+
+    ///Wandelt die Transformation in eine akzeptierte Transformation für den Bericht um.
+    /// Fehlt eines der vier Pflichtattribute, wird None zurückgegeben
+    pub fn accepted_transform(&self) -> Option<AcceptedTransformV1> {
+        let value = |attribute_type| {
+            self.attribute_value(attribute_type)
+                .and_then(|value| u16::try_from(value).ok())
+                .filter(|value| *value > 0)
+        };
+        Some(AcceptedTransformV1 {
+            encryption: EncryptionAlgorithmV1::from(value(AttributeType::Encryption)?),
+            key_length: value(AttributeType::KeyLength),
+            hash: HashAlgorithmV1::from(value(AttributeType::HashType)?),
+            diffie_hellman_group: DiffieHellmanGroup::from(value(
+                AttributeType::DiffieHellmanGroup,
+            )?),
+            authentication_method: AuthenticationMethodV1::from(value(
+                AttributeType::AuthenticationMethod,
+            )?),
+        })
+    }
+}
+
+///Parst die Attribute einer Transformation (TLV oder TV Format).
+/// Das Format ist für IkeV1 und IkeV2 gleich
+pub(crate) fn parse_attributes(mut rest: &[u8]) -> Result<Vec<ParsedAttribute>, ScanError> {
+    let mut attributes = vec![];
+    while !rest.is_empty() {
+        let (attribute, value) = read_fixed::<ResponseAttribute>(rest, "attribute")?;
+        let attribute_type = attribute.attribute_type.get();
+        let value_or_length = attribute.attribute_value_or_length.get();
+        //the significant bit marks the short form (TV), otherwise the value follows (TLV)
+        if attribute_type & 1 << 15 != 0 {
+            attributes.push(ParsedAttribute {
+                attribute_type: attribute_type & !(1 << 15),
+                value: AttributeValue::Basic(value_or_length),
+            });
+            rest = value;
+        } else {
+            let length = usize::from(value_or_length);
+            let data = value.get(..length).ok_or_else(|| {
+                ScanError::MalformedResponse(format!("attribute length {}", length))
+            })?;
+            attributes.push(ParsedAttribute {
+                attribute_type,
+                value: AttributeValue::Variable(data.to_vec()),
+            });
+            rest = &value[length..];
+        }
+    }
+    Ok(attributes)
+}
+
+///Geparstes Attribut einer Transformation
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedAttribute {
+    ///Attribut Typ ohne das significant bit
+    pub attribute_type: u16,
+    ///Wert des Attributs
+    pub value: AttributeValue,
+}
+
+///Wert eines Attributs
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValue {
+    ///kurze Form, der Wert steht direkt im Attribut
+    Basic(u16),
+    ///lange Form, der Wert folgt mit variabler Länge
+    Variable(Vec<u8>),
+}
+
+impl AttributeValue {
+    ///Wert als Zahl, bei der langen Form nur bis zu acht Bytes
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            AttributeValue::Basic(value) => Some(u64::from(*value)),
+            AttributeValue::Variable(bytes) if bytes.len() <= 8 => Some(
+                bytes
+                    .iter()
+                    .fold(0u64, |value, byte| value << 8 | u64::from(*byte)),
+            ),
+            AttributeValue::Variable(_) => None,
+        }
+    }
+}
+
+///Geparster Identifizierungs-Payload
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedIdentification {
+    ///Typ der Identität (z.B. 1 für IPv4 Adresse)
+    pub id_type: u8,
+    ///Protokoll ID
+    pub protocol_id: u8,
+    ///Port
+    pub port: u16,
+    ///Identitätsdaten
+    pub data: Vec<u8>,
+}
+
+///Geparster Zertifikats- oder Zertifikatsanfrage-Payload
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedCertificate {
+    ///Kodierung des Zertifikats (z.B. 4 für X.509 Signatur)
+    pub encoding: u8,
+    ///Zertifikat oder Zertifizierungsstelle
+    pub data: Vec<u8>,
+}
+
+///Geparster Notification Payload
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedNotification {
+    ///Domain of Interpretation
+    pub doi: u32,
+    ///Protokoll ID
+    pub protocol_id: u8,
+    ///Nachrichtentyp
+    pub message_type: NotifyMessageTypeV1,
+    ///Security Parameter Index
+    pub spi: Vec<u8>,
+    ///zusätzliche Daten der Nachricht
+    pub data: Vec<u8>,
+}
+
+impl ParsedNotification {
+    ///Nachrichtentypen unter 16384 sind Fehlermeldungen
+    pub fn is_error(&self) -> bool {
+        u16::from(self.message_type) < 16384
+    }
+}
+
+///Geparster Delete Payload
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedDelete {
+    ///Domain of Interpretation
+    pub doi: u32,
+    ///Protokoll ID
+    pub protocol_id: u8,
+    ///gelöschte Security Parameter Indexe
+    pub spis: Vec<Vec<u8>>,
 }
 
 ///Header der Antwort
-#[derive(Debug, Clone, Copy, FromBytes, FromZeroes)]
-#[repr(packed)]
+#[derive(Debug, Clone, Copy, PartialEq, FromBytes, FromZeroes)]
+#[repr(C, packed)]
 pub struct ResponseHeader {
     ///Security Parameter Index des Initiators
     pub initiator_spi: U64,
@@ -135,9 +584,21 @@ pub struct ResponseHeader {
     pub length: U32,
 }
 
+///Generischer Header, mit dem jeder Payload beginnt
+#[derive(Debug, Clone, Copy, FromBytes, FromZeroes)]
+#[repr(C, packed)]
+pub struct ResponseGenericPayloadHeader {
+    ///nächster Payload
+    pub next_payload: u8,
+    ///reserviertes Feld
+    pub reserved: u8,
+    ///Länge des Payloads inklusive Header
+    pub length: U16,
+}
+
 ///Security Association Payload der Antwort
 #[derive(Debug, Clone, Copy, FromBytes, FromZeroes)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct ResponseSecurityAssociationPayload {
     ///nächster Payload (Proposal Payload)
     pub sa_next_payload: u8,
@@ -154,7 +615,7 @@ pub struct ResponseSecurityAssociationPayload {
 
 ///Proposal Payload
 #[derive(Debug, Clone, Copy, FromBytes, FromZeroes)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct ResponseProposalPayload {
     ///nächster Payload (Transformation Payload)
     pub next_payload: u8,
@@ -173,33 +634,9 @@ pub struct ResponseProposalPayload {
     pub number_of_transforms: u8,
 }
 
-///Wrapper Struct für eine Transformation
-#[derive(Debug, Copy, Clone, FromZeroes, FromBytes)]
-#[repr(packed)]
-pub struct ResponseTransformWrapped {
-    ///Transform Payload
-    pub transform_payload: ResponseTransformPayload,
-    ///Verschlüsselungsalgorithmus
-    pub encryption_attribute: ResponseAttribute,
-    ///Attribut fuer die Schlüssellänge
-    pub key_length: ResponseAttribute,
-    ///Hash Algorithmus
-    pub hash_attribute: ResponseAttribute,
-    ///Diffie-Hellman Gruppe
-    pub diffie_hellman_attribute: ResponseAttribute,
-    ///Authentisierungsmethode
-    pub authentication_method_attribute: ResponseAttribute,
-    ///Zeiteinheit der Lebensdauer
-    pub life_type_attribute: ResponseAttribute,
-    ///Lebensdauer
-    pub life_duration_attribute: ResponseAttribute,
-    ///Wert der Lebensdauer
-    pub life_duration_value: U32,
-}
-
 ///Transform Payload
 #[derive(Debug, Clone, Copy, FromBytes, FromZeroes)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct ResponseTransformPayload {
     ///nächster Payload
     /// drei: Transform folgt
@@ -220,32 +657,49 @@ pub struct ResponseTransformPayload {
 
 ///Attribut einer Transformation
 #[derive(Debug, Copy, Clone, FromBytes, FromZeroes)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct ResponseAttribute {
-    ///Attribut Typ (Verschlüsselungsalgorithmus, Hash-Algorithmus, Diffie-Hellman Gruppe, Authentisierungsmethode)
+    ///Attribut Typ, das significant bit kennzeichnet die kurze Form
     pub attribute_type: U16,
-    ///Attribut Wert oder Länge
-    /// in diesem nur der Wert von Bedeutung, weil nur die ersten vier Attribute benötigt werden
+    ///Attribut Wert (kurze Form) oder Länge des folgenden Werts (lange Form)
     pub attribute_value_or_length: U16,
 }
 
-///Hersteller ID
+///Identifizierungs-Payload ohne Identitätsdaten
+#[derive(Debug, Copy, Clone, FromBytes, FromZeroes)]
+#[repr(C, packed)]
+pub struct ResponseIdentificationPayload {
+    ///nächster Payload
+    pub next_payload: u8,
+    ///reservierter Bereich
+    pub reserved: u8,
+    ///Payload Länge
+    pub length: U16,
+    ///Typ der Identität
+    pub id_type: u8,
+    ///Protokoll ID
+    pub protocol_id: u8,
+    ///Port
+    pub port: U16,
+}
+
+///Zertifikats- oder Zertifikatsanfrage-Payload ohne Daten
 #[derive(Debug, Copy, Clone, FromBytes, FromZeroes)]
-#[repr(packed)]
-pub struct ResponseVendorID {
+#[repr(C, packed)]
+pub struct ResponseCertificatePayload {
     ///nächster Payload
     pub next_payload: u8,
-    ///Reservierter Bereich
+    ///reservierter Bereich
     pub reserved: u8,
     ///Payload Länge
     pub length: U16,
-    ///Hersteller ID
-    pub vendor_id: U16,
+    ///Kodierung des Zertifikats
+    pub encoding: u8,
 }
 
-///Notify Payload für Fehlermeldungen
+///Notify Payload für Fehlermeldungen (RFC 2408, Seite 48)
 #[derive(Debug, Copy, Clone, FromBytes, FromZeroes)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct RespondNotify {
     ///nächster Payload
     pub next_payload: u8,
@@ -254,7 +708,7 @@ pub struct RespondNotify {
     ///Payload Länge
     pub length: U16,
     ///Domain of Interpretation
-    pub doi: U64,
+    pub doi: U32,
     ///Protokoll ID, Wert 1 für Ike
     pub protocol_id: u8,
     ///Größe des Security Parameter Indexes
@@ -262,3 +716,235 @@ pub struct RespondNotify {
     ///Fehlertyp der Nachricht
     pub notify_message_type: U16,
 }
+
+///Delete Payload ohne Security Parameter Indexe (RFC 2408, Seite 50)
+#[derive(Debug, Copy, Clone, FromBytes, FromZeroes)]
+#[repr(C, packed)]
+pub struct ResponseDeletePayload {
+    ///nächster Payload
+    pub next_payload: u8,
+    ///reservierter Bereich
+    pub reserved: u8,
+    ///Payload Länge
+    pub length: U16,
+    ///Domain of Interpretation
+    pub doi: U32,
+    ///Protokoll ID
+    pub protocol_id: u8,
+    ///Größe eines Security Parameter Indexes
+    pub spi_size: u8,
+    ///Anzahl der Security Parameter Indexe
+    pub number_of_spis: U16,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INITIATOR_SPI: u64 = 0x0102_0304_0506_0708;
+
+    ///IkeV1 Header (Main Mode) mit dem ersten Payload 'next_payload'
+    fn header(next_payload: u8) -> Vec<u8> {
+        let mut bytes = INITIATOR_SPI.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&[0x11; 8]);
+        bytes.extend_from_slice(&[next_payload, 0x10, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        bytes
+    }
+
+    ///Payload mit generischem Header
+    fn payload(next_payload: u8, body: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![next_payload, 0];
+        bytes.extend_from_slice(&(4 + body.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    ///Attribut in der kurzen Form (TV)
+    fn tv(attribute_type: u16, value: u16) -> Vec<u8> {
+        let mut bytes = (attribute_type | 1 << 15).to_be_bytes().to_vec();
+        bytes.extend_from_slice(&value.to_be_bytes());
+        bytes
+    }
+
+    ///Attribut in der langen Form (TLV)
+    fn tlv(attribute_type: u16, value: &[u8]) -> Vec<u8> {
+        let mut bytes = attribute_type.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(value);
+        bytes
+    }
+
+    ///Transformation mit KEY_IKE und den Attributen
+    fn transform(attributes: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0, 0];
+        bytes.extend_from_slice(&(8 + attributes.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(&[1, 1, 0, 0]);
+        bytes.extend_from_slice(attributes);
+        bytes
+    }
+
+    ///Security Association Payload (ohne generischen Header) mit einem Proposal,
+    /// das 'number_of_transforms' ankündigt
+    fn security_association(number_of_transforms: u8, transforms: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0, 0, 0, 1, 0, 0, 0, 1];
+        bytes.extend_from_slice(&[0, 0]);
+        bytes.extend_from_slice(&(8 + transforms.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(&[1, 1, 0, number_of_transforms]);
+        bytes.extend_from_slice(transforms);
+        bytes
+    }
+
+    ///Transformation AES-256, SHA1, MODP 2048, Pre-Shared Key mit TLV Lebensdauer
+    fn aes_transform() -> Vec<u8> {
+        let mut attributes = tv(1, 7);
+        attributes.extend(tv(14, 256));
+        attributes.extend(tv(2, 2));
+        attributes.extend(tv(4, 14));
+        attributes.extend(tv(3, 1));
+        attributes.extend(tv(11, 1));
+        attributes.extend(tlv(12, &28800u32.to_be_bytes()));
+        transform(&attributes)
+    }
+
+    fn notification(message_type: u16) -> Vec<u8> {
+        let mut body = 1u32.to_be_bytes().to_vec();
+        body.extend_from_slice(&[1, 0]);
+        body.extend_from_slice(&message_type.to_be_bytes());
+        body
+    }
+
+    #[test]
+    fn parses_payload_chain() {
+        let mut buf = header(u8::from(PayloadTypeV1::SecurityAssociation));
+        buf.extend(payload(
+            u8::from(PayloadTypeV1::VendorID),
+            &security_association(1, &aes_transform()),
+        ));
+        buf.extend(payload(0, &NAT_TRAVERSAL_VENDOR_IDS[0]));
+        let packet = ResponsePacket::parse(&buf).unwrap();
+        assert_eq!(packet.header.initiator_spi.get(), INITIATOR_SPI);
+        assert_eq!(packet.payloads.len(), 2);
+        assert_eq!(
+            packet.accepted_transforms(),
+            vec![AcceptedTransformV1 {
+                encryption: EncryptionAlgorithmV1::AesCbc,
+                key_length: Some(256),
+                hash: HashAlgorithmV1::Sha1,
+                diffie_hellman_group: DiffieHellmanGroup::Modp2048,
+                authentication_method: AuthenticationMethodV1::PreSharedKey,
+            }]
+        );
+        let mut report = IkeV1Report::default();
+        packet.parse_response(&mut report);
+        assert_eq!(report.transforms.len(), 1);
+        assert!(report.nat_traversal);
+        assert_eq!(report.exchange_type, Some(ExchangeType::IdentityProtect));
+    }
+
+    #[test]
+    fn truncated_payload_is_reported() {
+        let mut buf = header(u8::from(PayloadTypeV1::VendorID));
+        buf.extend(payload(0, &[0xaa; 16]));
+        buf.truncate(buf.len() - 4);
+        assert!(matches!(
+            ResponsePacket::parse(&buf),
+            Err(ScanError::TruncatedResponse {
+                expected: 48,
+                received: 44
+            })
+        ));
+        assert!(matches!(
+            ResponsePacket::parse(&buf[..20]),
+            Err(ScanError::TruncatedResponse { .. })
+        ));
+    }
+
+    #[test]
+    fn payload_shorter_than_header_is_malformed() {
+        let mut buf = header(u8::from(PayloadTypeV1::VendorID));
+        buf.extend_from_slice(&[0, 0, 0, 2]);
+        assert!(matches!(
+            ResponsePacket::parse(&buf),
+            Err(ScanError::MalformedResponse(_))
+        ));
+    }
+
+    #[test]
+    fn wrong_transform_count_is_malformed() {
+        let mut buf = header(u8::from(PayloadTypeV1::SecurityAssociation));
+        buf.extend(payload(0, &security_association(2, &aes_transform())));
+        assert!(matches!(
+            ResponsePacket::parse(&buf),
+            Err(ScanError::MalformedResponse(message)) if message.contains("announces 2 transforms")
+        ));
+    }
+
+    #[test]
+    fn parses_tv_and_tlv_attributes() {
+        let mut bytes = tlv(12, &[0, 0, 0x70, 0x80]);
+        bytes.extend(tv(1, 5));
+        bytes.extend(tlv(16, &[0xff; 9]));
+        let attributes = parse_attributes(&bytes).unwrap();
+        assert_eq!(
+            attributes,
+            vec![
+                ParsedAttribute {
+                    attribute_type: 12,
+                    value: AttributeValue::Variable(vec![0, 0, 0x70, 0x80]),
+                },
+                ParsedAttribute {
+                    attribute_type: 1,
+                    value: AttributeValue::Basic(5),
+                },
+                ParsedAttribute {
+                    attribute_type: 16,
+                    value: AttributeValue::Variable(vec![0xff; 9]),
+                },
+            ]
+        );
+        assert_eq!(attributes[0].value.as_u64(), Some(28800));
+        assert_eq!(attributes[2].value.as_u64(), None);
+        //the value of the TLV attribute is longer than the rest of the transform
+        assert!(matches!(
+            parse_attributes(&tlv(12, &[0; 4])[..6]),
+            Err(ScanError::MalformedResponse(_))
+        ));
+    }
+
+    #[test]
+    fn transform_without_mandatory_attribute_is_not_accepted() {
+        let mut attributes = tv(1, 7);
+        attributes.extend(tv(2, 2));
+        attributes.extend(tv(4, 14));
+        let mut buf = header(u8::from(PayloadTypeV1::SecurityAssociation));
+        buf.extend(payload(
+            0,
+            &security_association(1, &transform(&attributes)),
+        ));
+        let packet = ResponsePacket::parse(&buf).unwrap();
+        assert!(packet.accepted_transforms().is_empty());
+    }
+
+    #[test]
+    fn parses_notifications_and_unknown_payloads() {
+        let mut buf = header(20);
+        buf.extend(payload(u8::from(PayloadTypeV1::Notification), &[0xbb; 20]));
+        buf.extend(payload(0, &notification(14)));
+        let packet = ResponsePacket::parse(&buf).unwrap();
+        assert_eq!(
+            packet.payloads[0],
+            ResponsePayload::Unknown {
+                payload_type: 20,
+                data: vec![0xbb; 20],
+            }
+        );
+        assert!(packet.no_proposal_chosen());
+        assert!(!packet.invalid_major_version());
+        let mut report = IkeV1Report::default();
+        packet.parse_response(&mut report);
+        assert_eq!(
+            report.notify_errors,
+            vec![NotifyMessageTypeV1::NoProposalChosen]
+        );
+    }
+}
@@ -1,48 +1,505 @@
 //! # Bike-Scan
-//! das folgende Modul wird zum Parsen von Ike Version 2 verwendet
+//! das folgende Modul wird zum Parsen von Ike Version 2 verwendet.
+//! Die Payloads werden wie bei IkeV1 über next_payload nacheinander gelesen,
+//! unbekannte Payloads mit gesetztem critical bit führen zu einem Fehler
 
-use zerocopy::network_endian::U128;
+use std::mem::size_of;
+use std::net::SocketAddr;
+
+use log::debug;
 use zerocopy::network_endian::U16;
 use zerocopy::network_endian::U32;
 use zerocopy::network_endian::U64;
-use zerocopy::AsBytes;
 use zerocopy::FromBytes;
 use zerocopy::FromZeroes;
 
-use crate::ikev2::ProtocolId;
-///Wrapper Struct für das Parsen des Ike Version 2 Protokolls
-#[derive(Debug, Clone, Copy, FromBytes, FromZeroes)]
-#[repr(packed)]
+use crate::algorithms::DiffieHellmanGroup;
+use crate::algorithms::EncryptionAlgorithmV2;
+use crate::algorithms::IntegrityAlgorithmV2;
+use crate::algorithms::PseudoRandomFunctionV2;
+use crate::error::ScanError;
+use crate::ikev2::nat_detection_hash;
+use crate::ikev2::AttributeType;
+use crate::ikev2::ExchangeTypeV2;
+use crate::ikev2::NotifyMessageTypeV2;
+use crate::ikev2::PayloadTypeV2;
+use crate::ikev2::TransformTypeValues;
+use crate::parse_ike::parse_attributes;
+use crate::parse_ike::read_fixed;
+use crate::parse_ike::split_payload;
+use crate::parse_ike::ParsedAttribute;
+use crate::parse_ike::ResponseGenericPayloadHeader;
+use crate::report::AcceptedTransformV2;
+use crate::report::IkeV2Report;
+use crate::report::NatDetection;
+use crate::report::VendorId;
+
+///critical bit im generischen Payload Header (RFC 7296 Seite 75)
+const CRITICAL_BIT: u8 = 1 << 7;
+
+///Wrapper Struct für eine IkeV2 Nachricht
+/// enthält den Header und alle Payloads in der empfangenen Reihenfolge
+#[derive(Debug, Clone, PartialEq)]
 pub struct ResponsePacketV2 {
     ///Ike Header
     pub header: ResponseHeaderV2,
-    ///Security Association Payload
-    pub sa_payload_v2: ResponseSecurityAssociationV2,
-    ///Proposal Payload
-    pub proposal_v2: ResponseProposalV2,
-    ///Verschlüsselungsalgorithmus
-    pub encryption_transform: ResponseTransformV2,
-    ///Pseudo-Random Funktion
-    pub prf_transform: ResponseTransformV2,
-    ///Integritätslagorithmus
-    pub integrity_algorithm_transform: ResponseTransformV2,
-    ///Diffie-Helman Gruppe
-    pub diffie_transform: ResponseTransformV2,
+    ///Payloads der Nachricht
+    pub payloads: Vec<ResponsePayloadV2>,
 }
 
 impl ResponsePacketV2 {
     ///Parsen der Antwort des Servers.
-    /// Die Funktion read_from_prefix liest den ersten Teil der Antwort aus.
-    /// Die Länge des Wrapperstruct darf nicht größer als die Länge der
-    /// gesendeten Bytes sein.
-    pub fn parse_ike_v2(buf: &[u8]) -> Option<Self> {
-        Self::read_from_prefix(buf)
+    /// Nach dem Header wird der Kette der Payloads über next_payload gefolgt.
+    /// Payloads mit unbekanntem Typ werden übersprungen, außer das critical bit ist gesetzt,
+    /// dann kann die Nachricht nicht verarbeitet werden und es wird MalformedResponse zurückgegeben.
+    pub fn parse(buf: &[u8]) -> Result<Self, ScanError> {
+        let header =
+            ResponseHeaderV2::read_from_prefix(buf).ok_or(ScanError::TruncatedResponse {
+                expected: size_of::<ResponseHeaderV2>(),
+                received: buf.len(),
+            })?;
+        let mut payloads = vec![];
+        let mut next_payload = header.next_payload;
+        let mut offset = size_of::<ResponseHeaderV2>();
+        while next_payload != u8::from(PayloadTypeV2::NoNextPayload) {
+            let (generic_header, payload) = split_payload(buf, offset)?;
+            let critical = generic_header.reserved & CRITICAL_BIT != 0;
+            payloads.push(ResponsePayloadV2::parse(next_payload, critical, payload)?);
+            next_payload = generic_header.next_payload;
+            offset += payload.len();
+        }
+        Ok(ResponsePacketV2 { header, payloads })
+    }
+
+    ///Die gefundenen Transformationen und der Austauschtyp werden in den Bericht übernommen.
+    /// Transformationen werden nur übernommen, wenn Verschlüsselungsalgorithmus, Pseudo-Random Funktion
+    /// und Diffie-Hellman Gruppe vorhanden sind, da kombinierte Verfahren wie AES-GCM keinen
+    /// Integritätsalgorithmus benötigen.
+    /// Außerdem werden Fehlermeldungen und Hersteller-IDs in den Bericht geschrieben.
+    pub fn parse_response(&self, report: &mut IkeV2Report) {
+        if self.header.version != 32 {
+            debug!("Invalid Version {}", self.header.version);
+            return;
+        }
+        report.exchange_type = ExchangeTypeV2::try_from_u8(self.header.exchange_type);
+        for payload in &self.payloads {
+            match payload {
+                ResponsePayloadV2::SecurityAssociation(proposals) => {
+                    for proposal in proposals {
+                        match proposal.accepted_transform() {
+                            Some(transform) => {
+                                debug!("Found Transforms: {:?}", transform);
+                                report.algorithms.add(&transform);
+                                if !report.transforms.contains(&transform) {
+                                    report.transforms.push(transform);
+                                }
+                            }
+                            None => debug!("Incomplete proposal: {:?}", proposal),
+                        }
+                    }
+                }
+                ResponsePayloadV2::Notify(notify) if notify.is_error() => {
+                    debug!("Error {:?}", notify.message_type);
+                    report.notify_errors.push(notify.message_type);
+                }
+                ResponsePayloadV2::VendorId(vendor_id) => {
+                    let vendor_id = VendorId(vendor_id.clone());
+                    if !report.vendor_ids.contains(&vendor_id) {
+                        report.vendor_ids.push(vendor_id);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    ///Gibt die vom Server ausgewählte Transformation zurück.
+    /// Bei NO_PROPOSAL_CHOSEN oder einer unvollständigen Auswahl wird None zurückgegeben
+    pub fn accepted_transform(&self) -> Option<AcceptedTransformV2> {
+        if self.no_proposal_chosen() {
+            return None;
+        }
+        self.payloads.iter().find_map(|payload| match payload {
+            ResponsePayloadV2::SecurityAssociation(proposals) => proposals
+                .iter()
+                .find_map(ParsedProposalV2::accepted_transform),
+            _ => None,
+        })
+    }
+
+    ///Prüft, ob der Server mit NO_PROPOSAL_CHOSEN geantwortet hat
+    pub fn no_proposal_chosen(&self) -> bool {
+        self.notifications()
+            .any(|notify| notify.message_type == NotifyMessageTypeV2::NoProposalChosen)
+    }
+
+    ///Gibt an, ob der Server die Version mit INVALID_MAJOR_VERSION abgelehnt hat
+    pub fn invalid_major_version(&self) -> bool {
+        self.notifications()
+            .any(|notify| notify.message_type == NotifyMessageTypeV2::InvalidMajorVersion)
+    }
+
+    ///Gibt die Diffie-Hellman Gruppe zurück, die der Server mit INVALID_KE_PAYLOAD anfragt.
+    /// Die Notification Daten enthalten die Gruppe als zwei Bytes (RFC 7296 Seite 103)
+    pub fn requested_key_exchange_group(&self) -> Option<DiffieHellmanGroup> {
+        self.notifications()
+            .filter(|notify| notify.message_type == NotifyMessageTypeV2::InvalidKePayload)
+            .find_map(|notify| <[u8; 2]>::try_from(notify.data.as_slice()).ok())
+            .map(|group| DiffieHellmanGroup::from(u16::from_be_bytes(group)))
+    }
+
+    ///Gibt das Cookie zurück, falls der Server mit einer COOKIE Notification antwortet
+    pub fn cookie(&self) -> Option<&[u8]> {
+        self.notifications()
+            .find(|notify| notify.message_type == NotifyMessageTypeV2::Cookie)
+            .map(|notify| notify.data.as_slice())
+    }
+
+    ///Vergleicht die NAT Detection Notifications des Servers mit den erwarteten Hashes.
+    /// 'local' ist die Adresse des Scanners, 'remote' die Adresse des Servers.
+    /// Ohne NAT Detection Notifications wird None zurückgegeben
+    pub fn nat_detection(
+        &self,
+        local: SocketAddr,
+        remote: SocketAddr,
+    ) -> Result<Option<NatDetection>, ScanError> {
+        let hashes = |message_type| -> Vec<&[u8]> {
+            self.notifications()
+                .filter(|notify| notify.message_type == message_type)
+                .map(|notify| notify.data.as_slice())
+                .collect()
+        };
+        let source_hashes = hashes(NotifyMessageTypeV2::NatDetectionSourceIp);
+        let destination_hashes = hashes(NotifyMessageTypeV2::NatDetectionDestinationIp);
+        if source_hashes.is_empty() && destination_hashes.is_empty() {
+            return Ok(None);
+        }
+        let initiator_spi = self.header.initiator_spi.get();
+        let responder_spi = self.header.responder_spi.get();
+        let remote_hash = nat_detection_hash(initiator_spi, responder_spi, remote)?;
+        let local_hash = nat_detection_hash(initiator_spi, responder_spi, local)?;
+        Ok(Some(NatDetection {
+            local_behind_nat: !destination_hashes.contains(&local_hash.as_slice()),
+            remote_behind_nat: !source_hashes.contains(&remote_hash.as_slice()),
+        }))
+    }
+
+    ///Gibt alle Notify Payloads der Nachricht zurück
+    pub fn notifications(&self) -> impl Iterator<Item = &ParsedNotifyV2> {
+        self.payloads.iter().filter_map(|payload| match payload {
+            ResponsePayloadV2::Notify(notify) => Some(notify),
+            _ => None,
+        })
+    }
+}
+
+///Payloads einer IkeV2 Nachricht
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResponsePayloadV2 {
+    ///Security Association Payload mit den Proposals
+    SecurityAssociation(Vec<ParsedProposalV2>),
+    ///Key Exchange Payload
+    KeyExchange(ParsedKeyExchangeV2),
+    ///Identifizierungs-Payload des Initiators
+    IdentificationInitiator(ParsedIdentificationV2),
+    ///Identifizierungs-Payload des Responders
+    IdentificationResponder(ParsedIdentificationV2),
+    ///Zertifikat
+    Certificate(ParsedCertificateV2),
+    ///Zertifikatsanfrage, die Daten enthalten die Hashes der Zertifizierungsstellen
+    CertificateRequest(ParsedCertificateV2),
+    ///Authentication Payload
+    Authentication(ParsedAuthenticationV2),
+    ///Nonce des Responders
+    Nonce(Vec<u8>),
+    ///Notify Payload (Fehler- und Statusmeldungen)
+    Notify(ParsedNotifyV2),
+    ///Hersteller-ID
+    VendorId(Vec<u8>),
+    ///Payload mit unbekanntem Typ ohne critical bit, die Daten ohne generischen Header
+    Unknown {
+        ///Typ des Payloads
+        payload_type: u8,
+        ///Inhalt des Payloads
+        data: Vec<u8>,
+    },
+}
+
+impl ResponsePayloadV2 {
+    ///Parst einen einzelnen Payload, 'payload' enthält den generischen Header.
+    /// Ist der Typ unbekannt und das critical bit gesetzt, wird ein Fehler zurückgegeben
+    pub fn parse(payload_type: u8, critical: bool, payload: &[u8]) -> Result<Self, ScanError> {
+        let body = payload[size_of::<ResponseGenericPayloadHeader>()..].to_vec();
+        let Some(known_type) = PayloadTypeV2::try_from_u8(payload_type) else {
+            if critical {
+                return Err(ScanError::MalformedResponse(format!(
+                    "unsupported critical payload type {}",
+                    payload_type
+                )));
+            }
+            debug!("Skipping unknown payload type {}", payload_type);
+            return Ok(ResponsePayloadV2::Unknown {
+                payload_type,
+                data: body,
+            });
+        };
+        Ok(match known_type {
+            PayloadTypeV2::SecurityAssociation => ResponsePayloadV2::SecurityAssociation(
+                ParsedProposalV2::parse_all(&payload[size_of::<ResponseGenericPayloadHeader>()..])?,
+            ),
+            PayloadTypeV2::KeyExchange => {
+                let (fixed, data) =
+                    read_fixed::<ResponseKeyExchangePayloadV2>(payload, "key exchange")?;
+                ResponsePayloadV2::KeyExchange(ParsedKeyExchangeV2 {
+                    diffie_hellman_group: fixed.diffie_hellman_group.get(),
+                    data: data.to_vec(),
+                })
+            }
+            PayloadTypeV2::IdentificationInitiator | PayloadTypeV2::IdentificationResponder => {
+                let (fixed, data) =
+                    read_fixed::<ResponseIdentificationV2>(payload, "identification")?;
+                let identification = ParsedIdentificationV2 {
+                    id_type: fixed.id_type,
+                    data: data.to_vec(),
+                };
+                if let PayloadTypeV2::IdentificationInitiator = known_type {
+                    ResponsePayloadV2::IdentificationInitiator(identification)
+                } else {
+                    ResponsePayloadV2::IdentificationResponder(identification)
+                }
+            }
+            PayloadTypeV2::Certificate | PayloadTypeV2::CertificateRequest => {
+                let (fixed, data) = read_fixed::<ResponseCertRequestV2>(payload, "certificate")?;
+                let certificate = ParsedCertificateV2 {
+                    encoding: fixed.cert_encoding,
+                    data: data.to_vec(),
+                };
+                if let PayloadTypeV2::Certificate = known_type {
+                    ResponsePayloadV2::Certificate(certificate)
+                } else {
+                    ResponsePayloadV2::CertificateRequest(certificate)
+                }
+            }
+            PayloadTypeV2::Authentication => {
+                let (fixed, data) =
+                    read_fixed::<ResponseAuthenticationV2>(payload, "authentication")?;
+                ResponsePayloadV2::Authentication(ParsedAuthenticationV2 {
+                    auth_method: fixed.auth_method,
+                    data: data.to_vec(),
+                })
+            }
+            PayloadTypeV2::Nonce => ResponsePayloadV2::Nonce(body),
+            PayloadTypeV2::Notify => {
+                let (fixed, rest) = read_fixed::<ResponseNotifyPayloadV2>(payload, "notify")?;
+                let spi_size = usize::from(fixed.spi_size);
+                let spi = rest.get(..spi_size).ok_or_else(|| {
+                    ScanError::MalformedResponse("notify SPI too long".to_string())
+                })?;
+                ResponsePayloadV2::Notify(ParsedNotifyV2 {
+                    protocol_id: fixed.protocol_id,
+                    message_type: NotifyMessageTypeV2::from(fixed.notify_message_type.get()),
+                    spi: spi.to_vec(),
+                    data: rest[spi_size..].to_vec(),
+                })
+            }
+            PayloadTypeV2::VendorID => ResponsePayloadV2::VendorId(body),
+            PayloadTypeV2::NoNextPayload => {
+                return Err(ScanError::MalformedResponse(
+                    "unexpected payload type 0 in payload chain".to_string(),
+                ))
+            }
+        })
+    }
+}
+
+///Geparstes Proposal
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedProposalV2 {
+    ///Nummer des Proposals
+    pub proposal_number: u8,
+    ///ID des Protokolls (1 für IKE)
+    pub protocol_id: u8,
+    ///Security Parameter Index
+    pub spi: Vec<u8>,
+    ///Transformationen des Proposals
+    pub transforms: Vec<ParsedTransformV2>,
+}
+
+impl ParsedProposalV2 {
+    ///Parst alle Proposals des Security Association Payloads.
+    /// Die Proposals werden über next_proposal (2 = weiteres Proposal) verkettet
+    fn parse_all(mut rest: &[u8]) -> Result<Vec<Self>, ScanError> {
+        let mut proposals = vec![];
+        loop {
+            let (fixed, _) = read_fixed::<ResponseProposalV2>(rest, "proposal")?;
+            let length = usize::from(fixed.length.get());
+            let proposal = rest
+                .get(..length)
+                .filter(|_| length >= size_of::<ResponseProposalV2>())
+                .ok_or_else(|| {
+                    ScanError::MalformedResponse(format!("proposal length {}", length))
+                })?;
+            proposals.push(Self::parse(proposal)?);
+            rest = &rest[length..];
+            if fixed.next_proposal != 2 {
+                break;
+            }
+        }
+        Ok(proposals)
+    }
+
+    ///Parst ein Proposal mit allen Transformationen
+    fn parse(proposal: &[u8]) -> Result<Self, ScanError> {
+        let (fixed, rest) = read_fixed::<ResponseProposalV2>(proposal, "proposal")?;
+        let spi_size = usize::from(fixed.spi_size);
+        let spi = rest
+            .get(..spi_size)
+            .ok_or_else(|| ScanError::MalformedResponse("proposal SPI too long".to_string()))?;
+        let mut rest = &rest[spi_size..];
+        let mut transforms = vec![];
+        while !rest.is_empty() {
+            let (transform_header, _) = read_fixed::<ResponseTransformV2>(rest, "transform")?;
+            let length = usize::from(transform_header.length.get());
+            let transform = rest
+                .get(..length)
+                .filter(|_| length >= size_of::<ResponseTransformV2>())
+                .ok_or_else(|| {
+                    ScanError::MalformedResponse(format!("transform length {}", length))
+                })?;
+            let (fixed_transform, attributes) =
+                read_fixed::<ResponseTransformV2>(transform, "transform")?;
+            transforms.push(ParsedTransformV2 {
+                transform_type: fixed_transform.transform_type,
+                transform_id: fixed_transform.transform_id.get(),
+                attributes: parse_attributes(attributes)?,
+            });
+            rest = &rest[length..];
+        }
+        if transforms.len() != usize::from(fixed.number_of_transforms) {
+            return Err(ScanError::MalformedResponse(format!(
+                "proposal announces {} transforms but contains {}",
+                fixed.number_of_transforms,
+                transforms.len()
+            )));
+        }
+        Ok(ParsedProposalV2 {
+            proposal_number: fixed.proposal_number,
+            protocol_id: fixed.protocol_id,
+            spi: spi.to_vec(),
+            transforms,
+        })
+    }
+
+    ///Gibt die erste Transformation des angegebenen Typs zurück
+    pub fn transform(&self, transform_type: TransformTypeValues) -> Option<&ParsedTransformV2> {
+        self.transforms
+            .iter()
+            .find(|transform| transform.transform_type == u8::from(transform_type))
+    }
+
+    ///Wandelt das Proposal in eine akzeptierte Transformation für den Bericht um.
+    /// Ohne Verschlüsselungsalgorithmus, Pseudo-Random Funktion oder Diffie-Hellman Gruppe
+    /// wird None zurückgegeben
+    pub fn accepted_transform(&self) -> Option<AcceptedTransformV2> {
+        let encryption = self.transform(TransformTypeValues::EncryptionAlgorithm)?;
+        let prf = self.transform(TransformTypeValues::PseudoRandomFunction)?;
+        let diffie_group = self.transform(TransformTypeValues::DiffieHellmanGroup)?;
+        let integrity = self
+            .transform(TransformTypeValues::IntegrityAlgorithm)
+            .filter(|integrity| integrity.transform_id > 0);
+        Some(AcceptedTransformV2 {
+            encryption: EncryptionAlgorithmV2::from(encryption.transform_id),
+            key_length: encryption
+                .attribute_value(AttributeType::KeyLength)
+                .and_then(|key_length| u16::try_from(key_length).ok()),
+            prf: PseudoRandomFunctionV2::from(prf.transform_id),
+            integrity: integrity
+                .map(|integrity| IntegrityAlgorithmV2::from(integrity.transform_id)),
+            diffie_hellman_group: DiffieHellmanGroup::from(diffie_group.transform_id),
+        })
+    }
+}
+
+///Geparste Transformation mit beliebigen Attributen
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedTransformV2 {
+    ///Typ der Transformation (z.B. 1 für Verschlüsselungsalgorithmus)
+    pub transform_type: u8,
+    ///Transformations ID
+    pub transform_id: u16,
+    ///Attribute der Transformation
+    pub attributes: Vec<ParsedAttribute>,
+}
+
+impl ParsedTransformV2 {
+    ///Gibt den Wert des Attributs zurück, falls es vorhanden ist und in 64 Bit passt
+    pub fn attribute_value(&self, attribute_type: AttributeType) -> Option<u64> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.attribute_type == attribute_type.type_value())
+            .and_then(|attribute| attribute.value.as_u64())
+    }
+}
+
+///Geparster Key Exchange Payload
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedKeyExchangeV2 {
+    ///Diffie-Hellman Gruppe des Servers
+    pub diffie_hellman_group: u16,
+    ///Key-Exchange Daten
+    pub data: Vec<u8>,
+}
+
+///Geparster Identifizierungs-Payload
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedIdentificationV2 {
+    ///Typ der Identität
+    pub id_type: u8,
+    ///Identitätsdaten
+    pub data: Vec<u8>,
+}
+
+///Geparster Zertifikats- oder Zertifikatsanfrage-Payload
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedCertificateV2 {
+    ///Kodierung des Zertifikats
+    pub encoding: u8,
+    ///Zertifikat oder Hashes der Zertifizierungsstellen
+    pub data: Vec<u8>,
+}
+
+///Geparster Authentication Payload
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedAuthenticationV2 {
+    ///Authentisierungsmethode
+    pub auth_method: u8,
+    ///Authentisierungsdaten
+    pub data: Vec<u8>,
+}
+
+///Geparster Notify Payload
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedNotifyV2 {
+    ///Protokoll ID
+    pub protocol_id: u8,
+    ///Nachrichtentyp
+    pub message_type: NotifyMessageTypeV2,
+    ///Security Parameter Index
+    pub spi: Vec<u8>,
+    ///Notification Daten (z.B. Cookie oder angefragte Diffie-Hellman Gruppe)
+    pub data: Vec<u8>,
+}
+
+impl ParsedNotifyV2 {
+    ///Nachrichtentypen unter 16384 sind Fehlermeldungen
+    pub fn is_error(&self) -> bool {
+        u16::from(self.message_type) < 16384
     }
 }
 
 ///Ike-Header
-#[derive(Debug, Clone, Copy, FromBytes, FromZeroes)]
-#[repr(packed)]
+#[derive(Debug, Clone, Copy, PartialEq, FromBytes, FromZeroes)]
+#[repr(C, packed)]
 pub struct ResponseHeaderV2 {
     ///Security Parameter Index des Initiators
     pub initiator_spi: U64,
@@ -67,7 +524,7 @@ pub struct ResponseHeaderV2 {
 
 ///Security Association Payload der Antwort
 #[derive(Debug, Clone, Copy, FromBytes, FromZeroes)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct ResponseSecurityAssociationV2 {
     ///nächster Payload
     pub sa2_next_payload: u8,
@@ -79,7 +536,7 @@ pub struct ResponseSecurityAssociationV2 {
 
 ///Proposal der Antwort
 #[derive(Debug, Clone, Copy, FromBytes, FromZeroes)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct ResponseProposalV2 {
     ///nächstes Proposal
     pub next_proposal: u8,
@@ -97,11 +554,10 @@ pub struct ResponseProposalV2 {
     pub number_of_transforms: u8,
 }
 
-///Transformation der Antwort, ohne Attribut.
-/// Dieses Struct wird für den Integritätsalgortihmus,
-/// die Pseudo-Random Funktion und die Diffie-Hellman Gruppe verwendet
+///Transformation der Antwort ohne Attribute.
+/// Die Attribute folgen bis zum Ende der Transformation
 #[derive(Debug, Clone, Copy, FromBytes, FromZeroes)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct ResponseTransformV2 {
     ///nächste Transformation
     pub next_transform: u8,
@@ -117,40 +573,9 @@ pub struct ResponseTransformV2 {
     pub transform_id: U16,
 }
 
-///Transformation der Antwort mit Attribut.
-/// Dieses Struct wird für das Parsen des Verschlüsselungsalgorithmus verwendet.
-#[derive(Debug, Clone, Copy, FromBytes, FromZeroes)]
-#[repr(packed)]
-pub struct ResponseTransformAttributeV2 {
-    ///nächste Transformation
-    pub next_transform: u8,
-    ///reserviertere Bereich
-    pub reserved: u8,
-    ///Länge der Transformation
-    pub length: U16,
-    ///Typ der Transformation
-    pub transform_type: u8,
-    ///zweiter reservierter Bereich
-    pub reserved2: u8,
-    ///Transformations ID
-    pub transform_id: U16,
-    ///Attribut für die Schlüssellänge
-    pub attribute: ResponseAttributeV2,
-}
-
-///Attribut zum Verarbeiten der Schlüssellänge
-#[derive(Debug, Clone, Copy, FromBytes, FromZeroes)]
-#[repr(packed)]
-pub struct ResponseAttributeV2 {
-    ///Attribut Typ = Schlüssellänge
-    pub attribute_type: U16,
-    ///Attributwert = 128, 192 oder 256 Bit
-    pub attribute_value: U16,
-}
-
 ///Key Exchange Payload der Antwort
 #[derive(Debug, Clone, Copy, FromBytes, FromZeroes)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct ResponseKeyExchangePayloadV2 {
     ///nächster Payload
     pub next_payload: u8,
@@ -166,7 +591,7 @@ pub struct ResponseKeyExchangePayloadV2 {
 ///Nonce Payload (RFC 7296 Seite 99)
 /// enthält die Nonce des Responders
 #[derive(Debug, Clone, Copy, FromBytes, FromZeroes)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct ResponseNoncePayloadV2 {
     ///nächster Payload
     pub next_payload_: u8,
@@ -175,10 +600,10 @@ pub struct ResponseNoncePayloadV2 {
     ///Payload Länge
     pub length: U16,
 }
-///CertificateRequest Payload (RFC 7296 Seite 95
-///ist für das Anfragen präferierter Zertifikate via IKE zuständig
+///Certificate und CertificateRequest Payload (RFC 7296 Seite 94 und 95)
+/// ist für das Anfragen präferierter Zertifikate via IKE zuständig
 #[derive(Debug, Clone, Copy, FromBytes, FromZeroes)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct ResponseCertRequestV2 {
     ///nächster Payload
     pub next_payload: u8,
@@ -193,7 +618,7 @@ pub struct ResponseCertRequestV2 {
 ///Notify Payload (RFC 7296 Seite 100)
 /// überträgt informative Daten wie Fehler- oder Statusmeldungen
 #[derive(Debug, Clone, Copy, FromBytes, FromZeroes)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct ResponseNotifyPayloadV2 {
     ///nächster Payload
     pub next_payload: u8,
@@ -205,4 +630,270 @@ pub struct ResponseNotifyPayloadV2 {
     pub protocol_id: u8,
     ///SPI Größe (0, wenn sich die Nachricht auf die IKE-SA bezieht)
     pub spi_size: u8,
+    ///Nachrichtentyp
+    pub notify_message_type: U16,
+}
+
+///Identifizierungs-Payload ohne Identitätsdaten (RFC 7296 Seite 90)
+#[derive(Debug, Clone, Copy, FromBytes, FromZeroes)]
+#[repr(C, packed)]
+pub struct ResponseIdentificationV2 {
+    ///nächster Payload
+    pub next_payload: u8,
+    ///reserviertes Feld + critical bit
+    pub reserved: u8,
+    ///Payload Länge
+    pub length: U16,
+    ///Typ der Identität
+    pub id_type: u8,
+    ///zweites reserviertes Feld
+    pub reserved2: [u8; 3],
+}
+
+///Authentication Payload ohne Authentisierungsdaten (RFC 7296 Seite 97)
+#[derive(Debug, Clone, Copy, FromBytes, FromZeroes)]
+#[repr(C, packed)]
+pub struct ResponseAuthenticationV2 {
+    ///nächster Payload
+    pub next_payload: u8,
+    ///reserviertes Feld + critical bit
+    pub reserved: u8,
+    ///Payload Länge
+    pub length: U16,
+    ///Authentisierungsmethode
+    pub auth_method: u8,
+    ///zweites reserviertes Feld
+    pub reserved2: [u8; 3],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INITIATOR_SPI: u64 = 0x0102_0304_0506_0708;
+    const RESPONDER_SPI: u64 = 0x1112_1314_1516_1718;
+
+    ///IkeV2 Header (IKE_SA_INIT Antwort) mit dem ersten Payload 'next_payload'
+    fn header(next_payload: u8) -> Vec<u8> {
+        let mut bytes = INITIATOR_SPI.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&RESPONDER_SPI.to_be_bytes());
+        bytes.extend_from_slice(&[next_payload, 0x20, 34, 0x20, 0, 0, 0, 0, 0, 0, 0, 0]);
+        bytes
+    }
+
+    ///Payload mit generischem Header, 'flags' enthält das critical bit
+    fn payload(next_payload: u8, flags: u8, body: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![next_payload, flags];
+        bytes.extend_from_slice(&(4 + body.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    ///Transformation mit Typ, ID und Attributen
+    fn transform(last: bool, transform_type: u8, transform_id: u16, attributes: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![if last { 0 } else { 3 }, 0];
+        bytes.extend_from_slice(&(8 + attributes.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(&[transform_type, 0]);
+        bytes.extend_from_slice(&transform_id.to_be_bytes());
+        bytes.extend_from_slice(attributes);
+        bytes
+    }
+
+    ///Proposal, das 'number_of_transforms' ankündigt
+    fn proposal(number_of_transforms: u8, transforms: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0, 0];
+        bytes.extend_from_slice(&(8 + transforms.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(&[1, 1, 0, number_of_transforms]);
+        bytes.extend_from_slice(transforms);
+        bytes
+    }
+
+    ///AES-CBC-256 (Schlüssellänge als TV Attribut), HMAC-SHA1, HMAC-SHA1-96, MODP 2048
+    fn accepted_transforms() -> Vec<u8> {
+        let mut bytes = transform(false, 1, 12, &[0x80, 14, 1, 0]);
+        bytes.extend(transform(false, 2, 2, &[]));
+        bytes.extend(transform(false, 3, 2, &[]));
+        bytes.extend(transform(true, 4, 14, &[]));
+        bytes
+    }
+
+    ///Notify Payload (ohne generischen Header) ohne SPI
+    fn notify(message_type: NotifyMessageTypeV2, data: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0, 0];
+        bytes.extend_from_slice(&u16::from(message_type).to_be_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    ///Nachricht, die nur aus Notify Payloads besteht
+    fn notifications(notifies: &[Vec<u8>]) -> Vec<u8> {
+        let notify_type = u8::from(PayloadTypeV2::Notify);
+        let mut buf = header(notify_type);
+        for (index, body) in notifies.iter().enumerate() {
+            let next_payload = if index + 1 < notifies.len() {
+                notify_type
+            } else {
+                0
+            };
+            buf.extend(payload(next_payload, 0, body));
+        }
+        buf
+    }
+
+    #[test]
+    fn parses_payload_chain() {
+        let mut buf = header(u8::from(PayloadTypeV2::SecurityAssociation));
+        buf.extend(payload(
+            u8::from(PayloadTypeV2::KeyExchange),
+            0,
+            &proposal(4, &accepted_transforms()),
+        ));
+        buf.extend(payload(
+            u8::from(PayloadTypeV2::Nonce),
+            0,
+            &[0, 14, 0, 0, 0xcc, 0xcc],
+        ));
+        buf.extend(payload(0, 0, &[0xdd; 32]));
+        let packet = ResponsePacketV2::parse(&buf).unwrap();
+        assert_eq!(packet.payloads.len(), 3);
+        assert_eq!(
+            packet.payloads[1],
+            ResponsePayloadV2::KeyExchange(ParsedKeyExchangeV2 {
+                diffie_hellman_group: 14,
+                data: vec![0xcc, 0xcc],
+            })
+        );
+        assert_eq!(
+            packet.accepted_transform(),
+            Some(AcceptedTransformV2 {
+                encryption: EncryptionAlgorithmV2::AesCbc,
+                key_length: Some(256),
+                prf: PseudoRandomFunctionV2::HmacSha1,
+                integrity: Some(IntegrityAlgorithmV2::HmacSha1_96),
+                diffie_hellman_group: DiffieHellmanGroup::Modp2048,
+            })
+        );
+        let mut report = IkeV2Report::default();
+        packet.parse_response(&mut report);
+        assert_eq!(report.transforms.len(), 1);
+        assert_eq!(report.exchange_type, Some(ExchangeTypeV2::IkeSaInit));
+    }
+
+    #[test]
+    fn truncated_payload_is_reported() {
+        let mut buf = header(u8::from(PayloadTypeV2::Nonce));
+        buf.extend(payload(0, 0, &[0xdd; 32]));
+        assert!(matches!(
+            ResponsePacketV2::parse(&buf[..40]),
+            Err(ScanError::TruncatedResponse {
+                expected: 64,
+                received: 40
+            })
+        ));
+    }
+
+    #[test]
+    fn wrong_transform_count_is_malformed() {
+        let mut buf = header(u8::from(PayloadTypeV2::SecurityAssociation));
+        buf.extend(payload(0, 0, &proposal(3, &accepted_transforms())));
+        assert!(matches!(
+            ResponsePacketV2::parse(&buf),
+            Err(ScanError::MalformedResponse(message)) if message.contains("announces 3 transforms")
+        ));
+    }
+
+    #[test]
+    fn parses_tv_and_tlv_attributes_of_transform() {
+        //unknown TLV attribute before the key length
+        let mut attributes = vec![0x00, 0x30, 0, 3, 0xee, 0xee, 0xee];
+        attributes.extend_from_slice(&[0x80, 14, 0, 128]);
+        let mut transforms = transform(false, 1, 20, &attributes);
+        transforms.extend(transform(false, 2, 5, &[]));
+        transforms.extend(transform(true, 4, 19, &[]));
+        let mut buf = header(u8::from(PayloadTypeV2::SecurityAssociation));
+        buf.extend(payload(0, 0, &proposal(3, &transforms)));
+        let packet = ResponsePacketV2::parse(&buf).unwrap();
+        let accepted = packet.accepted_transform().unwrap();
+        assert_eq!(accepted.key_length, Some(128));
+        assert_eq!(accepted.integrity, None);
+        let ResponsePayloadV2::SecurityAssociation(proposals) = &packet.payloads[0] else {
+            panic!("no security association: {:?}", packet.payloads);
+        };
+        assert_eq!(proposals[0].transforms[0].attributes.len(), 2);
+    }
+
+    #[test]
+    fn unknown_payloads_are_skipped_unless_critical() {
+        let mut buf = header(99);
+        buf.extend(payload(0, 0, &[1, 2, 3]));
+        let packet = ResponsePacketV2::parse(&buf).unwrap();
+        assert_eq!(
+            packet.payloads,
+            vec![ResponsePayloadV2::Unknown {
+                payload_type: 99,
+                data: vec![1, 2, 3],
+            }]
+        );
+        let mut buf = header(99);
+        buf.extend(payload(0, CRITICAL_BIT, &[1, 2, 3]));
+        assert!(matches!(
+            ResponsePacketV2::parse(&buf),
+            Err(ScanError::MalformedResponse(message)) if message.contains("critical")
+        ));
+    }
+
+    #[test]
+    fn extracts_cookie() {
+        let buf = notifications(&[notify(NotifyMessageTypeV2::Cookie, &[0xab; 20])]);
+        let packet = ResponsePacketV2::parse(&buf).unwrap();
+        assert_eq!(packet.cookie(), Some(&[0xab; 20][..]));
+        assert_eq!(packet.requested_key_exchange_group(), None);
+        let mut report = IkeV2Report::default();
+        packet.parse_response(&mut report);
+        assert!(report.notify_errors.is_empty());
+    }
+
+    #[test]
+    fn extracts_requested_key_exchange_group() {
+        let buf = notifications(&[notify(NotifyMessageTypeV2::InvalidKePayload, &[0, 19])]);
+        let packet = ResponsePacketV2::parse(&buf).unwrap();
+        assert_eq!(
+            packet.requested_key_exchange_group(),
+            Some(DiffieHellmanGroup::from(19))
+        );
+        assert_eq!(packet.cookie(), None);
+        assert!(!packet.no_proposal_chosen());
+        //the group must be exactly two bytes
+        let buf = notifications(&[notify(NotifyMessageTypeV2::InvalidKePayload, &[19])]);
+        let packet = ResponsePacketV2::parse(&buf).unwrap();
+        assert_eq!(packet.requested_key_exchange_group(), None);
+    }
+
+    #[test]
+    fn compares_nat_detection_hashes() {
+        let local: SocketAddr = "192.0.2.1:500".parse().unwrap();
+        let remote: SocketAddr = "198.51.100.7:500".parse().unwrap();
+        let remote_hash = nat_detection_hash(INITIATOR_SPI, RESPONDER_SPI, remote).unwrap();
+        let translated: SocketAddr = "203.0.113.9:4500".parse().unwrap();
+        let translated_hash = nat_detection_hash(INITIATOR_SPI, RESPONDER_SPI, translated).unwrap();
+        let buf = notifications(&[
+            notify(NotifyMessageTypeV2::NatDetectionSourceIp, &remote_hash),
+            notify(
+                NotifyMessageTypeV2::NatDetectionDestinationIp,
+                &translated_hash,
+            ),
+        ]);
+        let packet = ResponsePacketV2::parse(&buf).unwrap();
+        assert_eq!(
+            packet.nat_detection(local, remote).unwrap(),
+            Some(NatDetection {
+                local_behind_nat: true,
+                remote_behind_nat: false,
+            })
+        );
+        let buf = notifications(&[notify(NotifyMessageTypeV2::NoProposalChosen, &[])]);
+        let packet = ResponsePacketV2::parse(&buf).unwrap();
+        assert_eq!(packet.nat_detection(local, remote).unwrap(), None);
+        assert!(packet.no_proposal_chosen());
+    }
 }
@@ -0,0 +1,176 @@
+//! # Bike-Scan
+//! das folgende Modul begrenzt die Rate, mit der Proben an ein Ziel gesendet werden.
+//! Es wird ein Token-Bucket verwendet, dessen Rate sich an das Verhalten des Ziels anpasst:
+//! Antworten erhöhen die Rate, fehlende Antworten und verlangte Cookies verringern sie
+
+use std::time::Duration;
+
+use log::debug;
+use tokio::time::Instant;
+
+use crate::error::ScanError;
+use crate::options::RateLimit;
+
+///Token-Bucket für die Proben an ein Ziel
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    ///Einstellungen der Rate
+    limit: RateLimit,
+    ///aktuelle Rate in Proben pro Sekunde
+    rate: f64,
+    ///verfügbare Tokens, eine Probe verbraucht ein Token
+    tokens: f64,
+    ///Zeitpunkt, bis zu dem die Tokens aufgefüllt wurden
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    ///Erstellt einen Token-Bucket mit der Startrate aus den Einstellungen (siehe RateLimit::validate).
+    /// Der Bucket ist zu Beginn voll, die erste Probe wird sofort gesendet
+    pub fn new(limit: &RateLimit) -> Result<Self, ScanError> {
+        limit.validate()?;
+        Ok(RateLimiter {
+            limit: limit.clone(),
+            rate: limit.rate,
+            tokens: limit.burst,
+            last_refill: Instant::now(),
+        })
+    }
+
+    ///aktuelle Rate in Proben pro Sekunde
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    ///Wartet, bis ein Token verfügbar ist, und verbraucht es
+    pub async fn acquire(&mut self) {
+        self.refill();
+        if self.tokens < 1.0 {
+            let wait = Duration::from_secs_f64((1.0 - self.tokens) / self.rate);
+            tokio::time::sleep(wait).await;
+            self.refill();
+        }
+        self.tokens -= 1.0;
+    }
+
+    ///Das Ziel hat geantwortet, die Rate wird erhöht
+    pub fn on_response(&mut self) {
+        self.set_rate(self.rate * self.limit.increase_factor);
+    }
+
+    ///Das Ziel hat nicht geantwortet, die Rate wird verringert
+    pub fn on_timeout(&mut self) {
+        self.set_rate(self.rate * self.limit.decrease_factor);
+    }
+
+    ///Das Ziel verlangt ein Cookie und ist damit unter Last, die Rate wird verringert
+    pub fn on_cookie(&mut self) {
+        self.set_rate(self.rate * self.limit.decrease_factor);
+    }
+
+    fn set_rate(&mut self, rate: f64) {
+        //tokens collected with the old rate stay available
+        self.refill();
+        let rate = rate.clamp(self.limit.min_rate, self.limit.max_rate);
+        if rate != self.rate {
+            debug!("Probe rate changed to {:.3} probes per second", rate);
+        }
+        self.rate = rate;
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.limit.burst);
+        self.last_refill = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///Einstellungen mit Startrate 'rate' zwischen 0,5 und 4 Proben pro Sekunde
+    fn limit(rate: f64, burst: f64) -> RateLimit {
+        RateLimit {
+            rate,
+            min_rate: 0.5,
+            max_rate: 4.0,
+            burst,
+            increase_factor: 2.0,
+            decrease_factor: 0.5,
+        }
+    }
+
+    #[test]
+    fn invalid_limits_are_rejected() {
+        assert!(RateLimiter::new(&limit(1.0, 1.0)).is_ok());
+        for invalid in [
+            limit(5.0, 1.0),
+            limit(0.25, 1.0),
+            limit(1.0, 0.5),
+            limit(f64::NAN, 1.0),
+            RateLimit {
+                min_rate: 0.0,
+                ..limit(1.0, 1.0)
+            },
+            RateLimit {
+                increase_factor: 0.5,
+                ..limit(1.0, 1.0)
+            },
+            RateLimit {
+                decrease_factor: 0.0,
+                ..limit(1.0, 1.0)
+            },
+        ] {
+            assert!(matches!(
+                RateLimiter::new(&invalid),
+                Err(ScanError::InvalidInput(_))
+            ));
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn acquire_waits_for_the_next_token() {
+        let mut limiter = RateLimiter::new(&limit(2.0, 2.0)).unwrap();
+        let start = Instant::now();
+        //the full bucket allows a burst of two probes
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_millis(500));
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn responses_raise_the_rate_up_to_max_rate() {
+        let mut limiter = RateLimiter::new(&limit(1.0, 1.0)).unwrap();
+        limiter.on_response();
+        assert_eq!(limiter.rate(), 2.0);
+        limiter.on_response();
+        limiter.on_response();
+        assert_eq!(limiter.rate(), 4.0);
+        let start = Instant::now();
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_millis(250));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn timeouts_and_cookies_lower_the_rate_down_to_min_rate() {
+        let mut limiter = RateLimiter::new(&limit(2.0, 1.0)).unwrap();
+        limiter.on_timeout();
+        assert_eq!(limiter.rate(), 1.0);
+        limiter.on_cookie();
+        assert_eq!(limiter.rate(), 0.5);
+        limiter.on_timeout();
+        limiter.on_cookie();
+        assert_eq!(limiter.rate(), 0.5);
+        let start = Instant::now();
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(2));
+    }
+}
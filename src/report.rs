@@ -0,0 +1,283 @@
+//! # Bike-Scan
+//! das folgende Modul enthält den Bericht, den scan() und scan_v2() zurückgeben.
+//! Der Bericht enthält für jede Ike Version einen eigenen Abschnitt
+
+use std::fmt;
+use std::net::SocketAddr;
+
+use serde::Serialize;
+use serde::Serializer;
+
+use crate::algorithms::AuthenticationMethodV1;
+use crate::algorithms::DiffieHellmanGroup;
+use crate::algorithms::EncryptionAlgorithmV1;
+use crate::algorithms::EncryptionAlgorithmV2;
+use crate::algorithms::HashAlgorithmV1;
+use crate::algorithms::IntegrityAlgorithmV2;
+use crate::algorithms::PseudoRandomFunctionV2;
+use crate::ike::ExchangeType;
+use crate::ike::NotifyMessageTypeV1;
+use crate::ikev2::ExchangeTypeV2;
+use crate::ikev2::NotifyMessageTypeV2;
+
+///Ergebnis eines Scans für ein Ziel
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScanReport {
+    ///gescanntes Ziel
+    pub target: SocketAddr,
+    ///Ergebnisse für IkeV1, None wenn IkeV1 nicht gescannt wurde
+    pub ike_v1: Option<IkeV1Report>,
+    ///Ergebnisse für IkeV2, None wenn IkeV2 nicht gescannt wurde
+    pub ike_v2: Option<IkeV2Report>,
+    ///erkannte Ike Versionen, nur bei scan_auto()
+    pub discovery: Option<DiscoveryReport>,
+    ///Nachrichten, die keiner Probe zugeordnet werden konnten und verworfen wurden
+    pub discarded_responses: DiscardedResponses,
+}
+
+impl ScanReport {
+    ///Erstellt einen leeren Bericht für das Ziel
+    pub fn new(target: SocketAddr) -> Self {
+        ScanReport {
+            target,
+            ike_v1: None,
+            ike_v2: None,
+            discovery: None,
+            discarded_responses: DiscardedResponses::default(),
+        }
+    }
+}
+
+///Verworfene Nachrichten, die zu keiner offenen Probe passten (correlation.rs)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DiscardedResponses {
+    ///verspätete Antworten auf frühere Proben, z.B. nach einer Wiederholung
+    pub late: usize,
+    ///Nachrichten mit einem Initiator SPI, der zu keiner gesendeten Probe gehört
+    pub unknown_spi: usize,
+    ///Antworten mit unpassendem Header (Version, Austauschtyp, Flags, Message ID)
+    pub invalid_header: usize,
+    ///Antworten, die kürzer als ein Ike Header oder als die Längenangabe im Header sind
+    pub truncated: usize,
+    ///Antworten mit Bytes nach dem Ende der Nachricht laut Längenangabe im Header
+    pub overlong: usize,
+    ///Datagramme ohne Non-ESP Marker bei NAT-Traversal und TCP, z.B. NAT Keepalives oder ESP Pakete
+    pub non_ike: usize,
+}
+
+impl DiscardedResponses {
+    ///Anzahl aller verworfenen Nachrichten
+    pub fn total(&self) -> usize {
+        self.late
+            + self.unknown_spi
+            + self.invalid_header
+            + self.truncated
+            + self.overlong
+            + self.non_ike
+    }
+}
+
+///Ergebnis der Erkennung (discover()) für ein Ziel
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiscoveryReport {
+    ///geprüftes Ziel
+    pub target: SocketAddr,
+    ///Zustand für die IkeV1 Probe (Main Mode)
+    pub ike_v1: EndpointState,
+    ///Zustand für die IkeV2 Probe (IKE_SA_INIT)
+    pub ike_v2: EndpointState,
+}
+
+impl DiscoveryReport {
+    ///Erstellt einen Bericht ohne Antworten für das Ziel
+    pub fn new(target: SocketAddr) -> Self {
+        DiscoveryReport {
+            target,
+            ike_v1: EndpointState::default(),
+            ike_v2: EndpointState::default(),
+        }
+    }
+
+    ///Bericht für ein Ziel, dessen Port geschlossen ist
+    pub fn closed(target: SocketAddr) -> Self {
+        DiscoveryReport {
+            target,
+            ike_v1: EndpointState::Closed,
+            ike_v2: EndpointState::Closed,
+        }
+    }
+
+    ///Gibt an, ob das Ziel auf mindestens eine Probe geantwortet hat
+    pub fn is_responder(&self) -> bool {
+        self.ike_v1.is_responder() || self.ike_v2.is_responder()
+    }
+}
+
+///Zustand eines Ziels für eine Ike Version
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize)]
+pub enum EndpointState {
+    ///keine Antwort, auch nicht nach den Wiederholungen
+    #[default]
+    NoResponse,
+    ///das Ziel hat geantwortet (auch eine Notification zählt)
+    Responder,
+    ///das Ziel hat geantwortet, unterstützt die Version aber nicht
+    /// (INVALID_MAJOR_VERSION oder Antwort mit einer anderen Hauptversion)
+    Unsupported,
+    ///der Port ist geschlossen (ICMP Port Unreachable oder TCP Verbindung abgelehnt)
+    Closed,
+}
+
+impl EndpointState {
+    ///Gibt an, ob auf die Probe eine Ike Nachricht zurückkam
+    pub fn is_responder(self) -> bool {
+        matches!(self, EndpointState::Responder | EndpointState::Unsupported)
+    }
+}
+
+///Ergebnisse des IkeV1 Scans
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct IkeV1Report {
+    ///Austauschtyp der Antworten (z.B. Main Mode)
+    pub exchange_type: Option<ExchangeType>,
+    ///vom Server akzeptierte Transformationen
+    pub transforms: Vec<AcceptedTransformV1>,
+    ///empfangene Fehlermeldungen
+    pub notify_errors: Vec<NotifyMessageTypeV1>,
+    ///empfangene Hersteller-IDs
+    pub vendor_ids: Vec<VendorId>,
+    ///der Server hat eine NAT-Traversal Hersteller-ID (RFC 3947 oder Draft) gesendet
+    pub nat_traversal: bool,
+    ///Anzahl der Proben, auf die auch nach allen Wiederholungen keine Antwort kam
+    pub unanswered_probes: usize,
+}
+
+///Ergebnisse des IkeV2 Scans
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct IkeV2Report {
+    ///Austauschtyp der Antworten (IKE_SA_INIT)
+    pub exchange_type: Option<ExchangeTypeV2>,
+    ///vom Server akzeptierte Transformationen
+    pub transforms: Vec<AcceptedTransformV2>,
+    ///akzeptierte Algorithmen je Transformationstyp
+    pub algorithms: AcceptedAlgorithmsV2,
+    ///Diffie-Hellman Gruppen, die der Server mit INVALID_KE_PAYLOAD angefragt hat
+    pub requested_groups: Vec<DiffieHellmanGroup>,
+    ///der Server hat mit einer COOKIE Notification ein Cookie verlangt (Schutz gegen DoS)
+    pub cookie_required: bool,
+    ///Ergebnis der NAT Detection, None wenn der Server keine NAT Detection Notifications gesendet hat
+    pub nat_detection: Option<NatDetection>,
+    ///empfangene Fehlermeldungen
+    pub notify_errors: Vec<NotifyMessageTypeV2>,
+    ///empfangene Hersteller-IDs
+    pub vendor_ids: Vec<VendorId>,
+    ///Anzahl der Proben, auf die auch nach allen Wiederholungen keine Antwort kam
+    pub unanswered_probes: usize,
+}
+
+impl IkeV2Report {
+    ///Übernimmt eine mit INVALID_KE_PAYLOAD angefragte Gruppe.
+    /// Der Server unterstützt diese Gruppe, daher wird sie auch bei den akzeptierten Gruppen eingetragen
+    pub fn add_requested_group(&mut self, group: DiffieHellmanGroup) {
+        push_unique(&mut self.requested_groups, group);
+        push_unique(&mut self.algorithms.diffie_hellman_group, group);
+    }
+}
+
+///Ergebnis der NAT Detection (RFC 7296, Abschnitt 2.23)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub struct NatDetection {
+    ///der Server sieht eine andere Adresse als die lokale, der Scanner steht hinter einem NAT
+    pub local_behind_nat: bool,
+    ///die Adresse des Servers passt nicht zu seinem Hash, der Server steht hinter einem NAT
+    pub remote_behind_nat: bool,
+}
+
+///Eine vom Server akzeptierte IkeV1 Transformation
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct AcceptedTransformV1 {
+    ///Verschlüsselungsalgorithmus
+    pub encryption: EncryptionAlgorithmV1,
+    ///Schlüssellänge in Bit, falls der Server sie angegeben hat
+    pub key_length: Option<u16>,
+    ///Hash-Typ
+    pub hash: HashAlgorithmV1,
+    ///Diffie-Hellman Gruppe
+    pub diffie_hellman_group: DiffieHellmanGroup,
+    ///Authentisierungsmethode
+    pub authentication_method: AuthenticationMethodV1,
+}
+
+///Eine vom Server akzeptierte IkeV2 Transformation
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct AcceptedTransformV2 {
+    ///Verschlüsselungsalgorithmus
+    pub encryption: EncryptionAlgorithmV2,
+    ///Schlüssellänge in Bit, falls der Server sie angegeben hat
+    pub key_length: Option<u16>,
+    ///Pseudo-Random Funktion
+    pub prf: PseudoRandomFunctionV2,
+    ///Integritätsalgorithmus, None bei kombinierten Verfahren (z.B. AES-GCM)
+    pub integrity: Option<IntegrityAlgorithmV2>,
+    ///Diffie-Hellman Gruppe
+    pub diffie_hellman_group: DiffieHellmanGroup,
+}
+
+///Alle akzeptierten IkeV2 Algorithmen, getrennt nach Transformationstyp
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AcceptedAlgorithmsV2 {
+    ///Verschlüsselungsalgorithmen mit Schlüssellänge
+    pub encryption: Vec<(EncryptionAlgorithmV2, Option<u16>)>,
+    ///Pseudo-Random Funktionen
+    pub prf: Vec<PseudoRandomFunctionV2>,
+    ///Integritätsalgorithmen
+    pub integrity: Vec<IntegrityAlgorithmV2>,
+    ///Diffie-Hellman Gruppen
+    pub diffie_hellman_group: Vec<DiffieHellmanGroup>,
+}
+
+impl AcceptedAlgorithmsV2 {
+    ///Übernimmt die Algorithmen einer akzeptierten Transformation, doppelte Einträge werden übersprungen
+    pub fn add(&mut self, transform: &AcceptedTransformV2) {
+        push_unique(
+            &mut self.encryption,
+            (transform.encryption, transform.key_length),
+        );
+        push_unique(&mut self.prf, transform.prf);
+        if let Some(integrity) = transform.integrity {
+            push_unique(&mut self.integrity, integrity);
+        }
+        push_unique(
+            &mut self.diffie_hellman_group,
+            transform.diffie_hellman_group,
+        );
+    }
+}
+
+///Fügt den Wert hinzu, falls er noch nicht enthalten ist
+fn push_unique<T: PartialEq>(values: &mut Vec<T>, value: T) {
+    if !values.contains(&value) {
+        values.push(value);
+    }
+}
+
+///Hersteller-ID aus einem Vendor ID Payload
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VendorId(pub Vec<u8>);
+
+///Ausgabe der Hersteller-ID als Hex-String
+impl fmt::Display for VendorId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+///Die Hersteller-ID wird als Hex-String serialisiert
+impl Serialize for VendorId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
@@ -0,0 +1,126 @@
+//! # Bike-Scan
+//! das folgende Modul scannt mehrere Ziele gleichzeitig.
+//! Die Anzahl gleichzeitiger Scans wird insgesamt und je Host begrenzt,
+//! für jedes Ziel wird ein eigener Bericht zurückgegeben
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+
+use log::warn;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::error::ScanError;
+use crate::options::ScanOptions;
+use crate::report::ScanReport;
+use crate::targets::Target;
+
+///Ergebnis des Scans eines Ziels, standardmäßig ein ScanReport
+#[derive(Debug)]
+pub struct TargetResult<R = ScanReport> {
+    ///gescanntes Ziel
+    pub target: Target,
+    ///Bericht oder Fehler des Scans
+    pub result: Result<R, ScanError>,
+}
+
+///Scannt alle Ziele mit der Funktion 'scan' (z.B. scan() oder discover())
+/// und gibt die Ergebnisse in der Reihenfolge der Ziele zurück, für jedes Ziel genau eines.
+/// Bricht ein Scan ab (Panic), ist sein Ergebnis ScanError::TaskFailed.
+/// Es laufen höchstens 'max_concurrent_targets' Scans gleichzeitig
+/// und höchstens 'max_concurrent_per_host' Scans für denselben Host (z.B. auf verschiedenen Ports)
+pub async fn scan_targets<F, Fut, R>(
+    targets: Vec<Target>,
+    options: &ScanOptions,
+    scan: F,
+) -> Result<Vec<TargetResult<R>>, ScanError>
+where
+    F: Fn(Target, Arc<ScanOptions>) -> Fut,
+    Fut: Future<Output = Result<R, ScanError>> + Send + 'static,
+    R: Send + 'static,
+{
+    if options.max_concurrent_targets == 0 || options.max_concurrent_per_host == 0 {
+        return Err(ScanError::InvalidInput(
+            "concurrency limits must not be zero".to_string(),
+        ));
+    }
+    let shared_options = Arc::new(options.clone());
+    let global_limit = Arc::new(Semaphore::new(options.max_concurrent_targets));
+    let mut host_limits: HashMap<String, Arc<Semaphore>> = HashMap::new();
+    let mut tasks = JoinSet::new();
+    for (index, target) in targets.into_iter().enumerate() {
+        let host_limit = host_limits
+            .entry(target.host.clone())
+            .or_insert_with(|| Arc::new(Semaphore::new(options.max_concurrent_per_host)))
+            .clone();
+        let global_limit = global_limit.clone();
+        let scan_future = scan(target.clone(), shared_options.clone());
+        tasks.spawn(async move {
+            //wait for the host first, so waiting scans do not block other hosts
+            let _host_permit = host_limit.acquire_owned().await;
+            let _global_permit = global_limit.acquire_owned().await;
+            //a separate task turns a panic into the result of this target
+            let result = match tokio::spawn(scan_future).await {
+                Ok(result) => result,
+                Err(error) => Err(ScanError::TaskFailed(error.to_string())),
+            };
+            (index, TargetResult { target, result })
+        });
+    }
+
+    let mut results = vec![];
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok(result) => results.push(result),
+            Err(error) => warn!("Scheduler task failed: {}", error),
+        }
+    }
+    results.sort_by_key(|(index, _)| *index);
+    Ok(results.into_iter().map(|(_, result)| result).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///Ziel auf Port 500
+    fn target(host: &str) -> Target {
+        Target {
+            host: host.to_string(),
+            port: 500,
+        }
+    }
+
+    #[tokio::test]
+    async fn results_keep_target_order() {
+        let targets = vec![target("192.0.2.2"), target("192.0.2.1")];
+        let results = scan_targets(targets, &ScanOptions::default(), |target, _| async move {
+            Ok(target.host)
+        })
+        .await
+        .unwrap();
+        let hosts: Vec<String> = results
+            .into_iter()
+            .map(|result| result.result.unwrap())
+            .collect();
+        assert_eq!(hosts, vec!["192.0.2.2", "192.0.2.1"]);
+    }
+
+    #[tokio::test]
+    async fn panicked_scan_is_reported_as_failed_target() {
+        let targets = vec![target("192.0.2.1"), target("192.0.2.2")];
+        let results = scan_targets(targets, &ScanOptions::default(), |target, _| async move {
+            if target.host == "192.0.2.1" {
+                panic!("scan failed");
+            }
+            Ok(())
+        })
+        .await
+        .unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].target, target("192.0.2.1"));
+        assert!(matches!(results[0].result, Err(ScanError::TaskFailed(_))));
+        assert!(results[1].result.is_ok());
+    }
+}
@@ -0,0 +1,234 @@
+//! # Bike-Scan
+//! das folgende Modul wandelt die Angaben der Ziele in einzelne Ziele um.
+//! Ein Ziel kann eine IP Adresse, ein Hostname, ein CIDR Bereich (192.0.2.0/24, 2001:db8::/120)
+//! jeweils optional mit Port sein, mehrere Ziele können aus einer Datei gelesen werden
+
+use std::fmt;
+use std::fs;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+use std::path::Path;
+
+use crate::error::ScanError;
+
+///maximale Anzahl an Adressen, in die ein CIDR Bereich aufgelöst wird
+pub const MAX_RANGE_ADDRESSES: u128 = 1 << 16;
+
+///Ein einzelnes Ziel eines Scans
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Target {
+    ///IP Adresse oder Hostname
+    pub host: String,
+    ///UDP oder TCP Port
+    pub port: u16,
+}
+
+///IPv6 Adressen werden in eckigen Klammern ausgegeben ([2001:db8::1]:500)
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
+///Wandelt eine Zielangabe in Ziele um.
+/// Ziele ohne Port bekommen 'default_port', IPv6 Adressen und Bereiche mit Port
+/// müssen in eckigen Klammern stehen ([::1]:500, [2001:db8::/120]:4500).
+/// CIDR Bereiche werden in alle enthaltenen Adressen aufgelöst, bei IPv4 Bereichen bis /30
+/// ohne Netz- und Broadcastadresse
+pub fn parse_target(spec: &str, default_port: u16) -> Result<Vec<Target>, ScanError> {
+    let (host, port) = split_port(spec.trim(), default_port)?;
+    if host.is_empty() {
+        return Err(ScanError::InvalidInput(format!("empty target '{}'", spec)));
+    }
+    let Some((address, prefix_length)) = host.split_once('/') else {
+        return Ok(vec![Target {
+            host: host.to_string(),
+            port,
+        }]);
+    };
+    let invalid_range = || ScanError::InvalidInput(format!("invalid CIDR range '{}'", host));
+    let address: IpAddr = address.parse().map_err(|_| invalid_range())?;
+    let prefix_length: u32 = prefix_length.parse().map_err(|_| invalid_range())?;
+    let addresses = match address {
+        IpAddr::V4(address) => {
+            let values = expand_range(host, u32::from(address).into(), prefix_length, 32)?;
+            //a /31 has no network and broadcast address (RFC 3021)
+            let hosts = if prefix_length < 31 {
+                &values[1..values.len() - 1]
+            } else {
+                &values[..]
+            };
+            hosts
+                .iter()
+                .map(|value| IpAddr::from(Ipv4Addr::from(*value as u32)))
+                .collect::<Vec<_>>()
+        }
+        IpAddr::V6(address) => expand_range(host, u128::from(address), prefix_length, 128)?
+            .into_iter()
+            .map(|value| IpAddr::from(Ipv6Addr::from(value)))
+            .collect(),
+    };
+    Ok(addresses
+        .into_iter()
+        .map(|address| Target {
+            host: address.to_string(),
+            port,
+        })
+        .collect())
+}
+
+///Liest die Ziele aus einer Datei, eine Zielangabe am Anfang jeder Zeile.
+/// Weitere Angaben nach dem Ziel (z.B. die Ike Versionen aus der Erkennung) werden ignoriert,
+/// leere Zeilen und Zeilen, die mit '#' beginnen, werden übersprungen
+pub fn read_target_file(path: &Path, default_port: u16) -> Result<Vec<Target>, ScanError> {
+    let content = fs::read_to_string(path).map_err(|error| {
+        ScanError::InvalidInput(format!(
+            "target file {} could not be read: {}",
+            path.display(),
+            error
+        ))
+    })?;
+    let mut targets = vec![];
+    for line in content.lines() {
+        match line.split_whitespace().next() {
+            Some(target) if !target.starts_with('#') => {
+                targets.extend(parse_target(target, default_port)?)
+            }
+            _ => continue,
+        }
+    }
+    Ok(targets)
+}
+
+///Trennt den Port vom Ziel, falls einer angegeben wurde
+fn split_port(spec: &str, default_port: u16) -> Result<(&str, u16), ScanError> {
+    let parse_port = |port: &str| {
+        port.parse()
+            .map_err(|_| ScanError::InvalidInput(format!("invalid port in target '{}'", spec)))
+    };
+    if let Some(bracketed) = spec.strip_prefix('[') {
+        let Some((host, rest)) = bracketed.split_once(']') else {
+            return Err(ScanError::InvalidInput(format!(
+                "missing ']' in target '{}'",
+                spec
+            )));
+        };
+        return match rest.strip_prefix(':') {
+            Some(port) => Ok((host, parse_port(port)?)),
+            None if rest.is_empty() => Ok((host, default_port)),
+            None => Err(ScanError::InvalidInput(format!(
+                "invalid target '{}'",
+                spec
+            ))),
+        };
+    }
+    //more than one ':' is an IPv6 address without port
+    match spec.split_once(':') {
+        Some((host, port)) if !port.contains(':') => Ok((host, parse_port(port)?)),
+        _ => Ok((spec, default_port)),
+    }
+}
+
+///Berechnet alle Adressen des Bereichs 'address/prefix_length' für Adressen mit 'bits' Bits
+fn expand_range(
+    range: &str,
+    address: u128,
+    prefix_length: u32,
+    bits: u32,
+) -> Result<Vec<u128>, ScanError> {
+    let host_bits = bits.checked_sub(prefix_length).ok_or_else(|| {
+        ScanError::InvalidInput(format!("invalid prefix length in CIDR range '{}'", range))
+    })?;
+    let count = 1u128
+        .checked_shl(host_bits)
+        .filter(|count| *count <= MAX_RANGE_ADDRESSES)
+        .ok_or_else(|| {
+            ScanError::InvalidInput(format!(
+                "CIDR range '{}' contains more than {} addresses",
+                range, MAX_RANGE_ADDRESSES
+            ))
+        })?;
+    let network = address & !(count - 1);
+    Ok((network..network + count).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///Adressen der Ziele
+    fn hosts(targets: &[Target]) -> Vec<&str> {
+        targets.iter().map(|target| target.host.as_str()).collect()
+    }
+
+    #[test]
+    fn parses_host_with_and_without_port() {
+        assert_eq!(
+            parse_target("vpn.example.com", 500).unwrap(),
+            vec![Target {
+                host: "vpn.example.com".to_string(),
+                port: 500
+            }]
+        );
+        assert_eq!(parse_target("192.0.2.1:4500", 500).unwrap()[0].port, 4500);
+        assert_eq!(
+            parse_target("[2001:db8::1]:4500", 500).unwrap(),
+            vec![Target {
+                host: "2001:db8::1".to_string(),
+                port: 4500
+            }]
+        );
+        assert_eq!(parse_target("2001:db8::1", 500).unwrap()[0].port, 500);
+        assert!(parse_target("192.0.2.1:ike", 500).is_err());
+        assert!(parse_target("[2001:db8::1", 500).is_err());
+    }
+
+    #[test]
+    fn ipv4_range_skips_network_and_broadcast_address() {
+        let targets = parse_target("192.0.2.5/30", 500).unwrap();
+        assert_eq!(hosts(&targets), vec!["192.0.2.5", "192.0.2.6"]);
+        assert_eq!(parse_target("192.0.2.0/24", 500).unwrap().len(), 254);
+    }
+
+    #[test]
+    fn ipv4_point_to_point_range_keeps_both_addresses() {
+        let targets = parse_target("192.0.2.4/31", 500).unwrap();
+        assert_eq!(hosts(&targets), vec!["192.0.2.4", "192.0.2.5"]);
+        let targets = parse_target("192.0.2.4/32", 500).unwrap();
+        assert_eq!(hosts(&targets), vec!["192.0.2.4"]);
+    }
+
+    #[test]
+    fn ipv6_range_keeps_all_addresses() {
+        let targets = parse_target("[2001:db8::/126]:4500", 500).unwrap();
+        assert_eq!(
+            hosts(&targets),
+            vec!["2001:db8::", "2001:db8::1", "2001:db8::2", "2001:db8::3"]
+        );
+        assert!(targets.iter().all(|target| target.port == 4500));
+    }
+
+    #[test]
+    fn ranges_are_limited_to_max_range_addresses() {
+        assert_eq!(parse_target("10.0.0.0/16", 500).unwrap().len(), 65534);
+        assert!(parse_target("10.0.0.0/15", 500).is_err());
+        assert_eq!(
+            parse_target("2001:db8::/112", 500).unwrap().len() as u128,
+            MAX_RANGE_ADDRESSES
+        );
+        assert!(parse_target("2001:db8::/111", 500).is_err());
+        assert!(parse_target("::/0", 500).is_err());
+    }
+
+    #[test]
+    fn invalid_ranges_are_rejected() {
+        assert!(parse_target("192.0.2.0/33", 500).is_err());
+        assert!(parse_target("192.0.2.0/x", 500).is_err());
+        assert!(parse_target("vpn.example.com/24", 500).is_err());
+    }
+}
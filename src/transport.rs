@@ -0,0 +1,562 @@
+//! # Bike-Scan
+//! das folgende Modul enthält den Transport der Ike Nachrichten zum Server.
+//! Die Probe-Logik in lib.rs verwendet nur das Trait Transport,
+//! UDP und NAT-Traversal senden jede Ike Nachricht als eigenes Datagramm,
+//! bei TCP (RFC 8229) werden die Nachrichten mit einer Längenangabe in einen Stream geschrieben.
+//! MemoryTransport verbindet zwei Enden im Speicher, z.B. für Tests ohne Server
+
+use std::future::Future;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpSocket;
+use tokio::net::TcpStream;
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+
+use crate::error::ScanError;
+use crate::options::ScanOptions;
+use crate::options::TransportMode;
+
+///Non-ESP Marker vor Ike Nachrichten bei NAT-Traversal und TCP (RFC 3948, RFC 8229)
+pub const NON_ESP_MARKER: [u8; 4] = [0; 4];
+
+///Präfix, das der Initiator einmal zu Beginn jeder TCP Verbindung sendet (RFC 8229, Abschnitt 3)
+pub const TCP_STREAM_PREFIX: [u8; 6] = *b"IKETCP";
+
+///Länge des Längenfelds vor jeder Nachricht im TCP Stream
+const TCP_LENGTH_FIELD_LENGTH: usize = 2;
+
+///Transport von Ike Nachrichten zwischen Scanner und Server.
+/// Ein Transport gehört zu genau einem Server, das Einrahmen der Nachrichten übernimmt der Transport
+pub trait Transport {
+    ///Sendet eine Ike Nachricht an den Server
+    fn send(&mut self, message: &[u8]) -> impl Future<Output = Result<(), ScanError>> + Send;
+
+    ///Wartet höchstens 'timeout' auf die nächste Ike Nachricht des Servers
+    /// und gibt deren Länge in 'buf' zurück.
+    /// Ohne Antwort wird ScanError::Timeout zurückgegeben
+    fn receive(
+        &mut self,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> impl Future<Output = Result<usize, ScanError>> + Send;
+
+    ///Gibt an, ob der Transport Nachrichten zuverlässig überträgt.
+    /// Über einen zuverlässigen Transport werden Nachrichten nicht erneut gesendet
+    fn is_reliable(&self) -> bool {
+        false
+    }
+
+    ///lokale Adresse, wird für die NAT Detection verwendet
+    fn local_addr(&self) -> Result<SocketAddr, ScanError>;
+
+    ///Adresse des Servers
+    fn peer_addr(&self) -> Result<SocketAddr, ScanError>;
+}
+
+///Transport, der beim Scan aus den Einstellungen gewählt wird
+#[derive(Debug)]
+pub enum Connection {
+    ///Ike direkt über UDP
+    Udp(UdpTransport),
+    ///UDP mit Non-ESP Marker vor jeder Nachricht
+    NatTraversal(NatTraversalTransport),
+    ///Ike über TCP
+    Tcp(TcpTransport),
+}
+
+impl Connection {
+    ///Öffnet die Verbindung zum Server mit dem Transport aus den Einstellungen
+    pub async fn open(remote_addr: SocketAddr, options: &ScanOptions) -> Result<Self, ScanError> {
+        let bind_address = options.bind_address;
+        Ok(match options.transport {
+            TransportMode::Udp => {
+                Connection::Udp(UdpTransport::connect(remote_addr, bind_address).await?)
+            }
+            TransportMode::NatTraversal => Connection::NatTraversal(
+                NatTraversalTransport::connect(remote_addr, bind_address).await?,
+            ),
+            TransportMode::Tcp => {
+                Connection::Tcp(TcpTransport::connect(remote_addr, bind_address).await?)
+            }
+        })
+    }
+}
+
+impl Transport for Connection {
+    async fn send(&mut self, message: &[u8]) -> Result<(), ScanError> {
+        match self {
+            Connection::Udp(transport) => transport.send(message).await,
+            Connection::NatTraversal(transport) => transport.send(message).await,
+            Connection::Tcp(transport) => transport.send(message).await,
+        }
+    }
+
+    async fn receive(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, ScanError> {
+        match self {
+            Connection::Udp(transport) => transport.receive(buf, timeout).await,
+            Connection::NatTraversal(transport) => transport.receive(buf, timeout).await,
+            Connection::Tcp(transport) => transport.receive(buf, timeout).await,
+        }
+    }
+
+    fn is_reliable(&self) -> bool {
+        match self {
+            Connection::Udp(transport) => transport.is_reliable(),
+            Connection::NatTraversal(transport) => transport.is_reliable(),
+            Connection::Tcp(transport) => transport.is_reliable(),
+        }
+    }
+
+    fn local_addr(&self) -> Result<SocketAddr, ScanError> {
+        match self {
+            Connection::Udp(transport) => transport.local_addr(),
+            Connection::NatTraversal(transport) => transport.local_addr(),
+            Connection::Tcp(transport) => transport.local_addr(),
+        }
+    }
+
+    fn peer_addr(&self) -> Result<SocketAddr, ScanError> {
+        match self {
+            Connection::Udp(transport) => transport.peer_addr(),
+            Connection::NatTraversal(transport) => transport.peer_addr(),
+            Connection::Tcp(transport) => transport.peer_addr(),
+        }
+    }
+}
+
+///Ike direkt über einen verbundenen UDP Socket
+#[derive(Debug)]
+pub struct UdpTransport {
+    socket: UdpSocket,
+}
+
+impl UdpTransport {
+    ///Bindet einen UDP Socket an die lokale Adresse und verbindet ihn mit dem Server
+    pub async fn connect(
+        remote_addr: SocketAddr,
+        bind_address: SocketAddr,
+    ) -> Result<Self, ScanError> {
+        let socket = UdpSocket::bind(bind_address_for(bind_address, remote_addr)?).await?;
+        socket.connect(remote_addr).await?;
+        Ok(UdpTransport { socket })
+    }
+}
+
+impl Transport for UdpTransport {
+    async fn send(&mut self, message: &[u8]) -> Result<(), ScanError> {
+        self.socket.send(message).await?;
+        Ok(())
+    }
+
+    async fn receive(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, ScanError> {
+        receive_datagram(&self.socket, buf, timeout).await
+    }
+
+    fn local_addr(&self) -> Result<SocketAddr, ScanError> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    fn peer_addr(&self) -> Result<SocketAddr, ScanError> {
+        Ok(self.socket.peer_addr()?)
+    }
+}
+
+///NAT-Traversal: UDP mit Non-ESP Marker vor jeder Ike Nachricht (RFC 3948)
+#[derive(Debug)]
+pub struct NatTraversalTransport {
+    socket: UdpSocket,
+}
+
+impl NatTraversalTransport {
+    ///Bindet einen UDP Socket an die lokale Adresse und verbindet ihn mit dem Server
+    pub async fn connect(
+        remote_addr: SocketAddr,
+        bind_address: SocketAddr,
+    ) -> Result<Self, ScanError> {
+        let socket = UdpSocket::bind(bind_address_for(bind_address, remote_addr)?).await?;
+        socket.connect(remote_addr).await?;
+        Ok(NatTraversalTransport { socket })
+    }
+}
+
+impl Transport for NatTraversalTransport {
+    async fn send(&mut self, message: &[u8]) -> Result<(), ScanError> {
+        let mut datagram = NON_ESP_MARKER.to_vec();
+        datagram.extend_from_slice(message);
+        self.socket.send(&datagram).await?;
+        Ok(())
+    }
+
+    async fn receive(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, ScanError> {
+        let length = receive_datagram(&self.socket, buf, timeout).await?;
+        strip_non_esp_marker(buf, length)
+    }
+
+    fn local_addr(&self) -> Result<SocketAddr, ScanError> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    fn peer_addr(&self) -> Result<SocketAddr, ScanError> {
+        Ok(self.socket.peer_addr()?)
+    }
+}
+
+///Ike über TCP (RFC 8229).
+/// Schließt der Server die Verbindung, wird sie beim nächsten Senden neu aufgebaut
+#[derive(Debug)]
+pub struct TcpTransport {
+    bind_address: SocketAddr,
+    remote_addr: SocketAddr,
+    local_addr: SocketAddr,
+    stream: Option<TcpStream>,
+    ///empfangene Bytes, die noch keine vollständige Nachricht ergeben
+    received: Vec<u8>,
+}
+
+impl TcpTransport {
+    ///Baut die Verbindung auf und sendet das Präfix "IKETCP"
+    pub async fn connect(
+        remote_addr: SocketAddr,
+        bind_address: SocketAddr,
+    ) -> Result<Self, ScanError> {
+        let bind_address = bind_address_for(bind_address, remote_addr)?;
+        let mut transport = TcpTransport {
+            bind_address,
+            remote_addr,
+            local_addr: bind_address,
+            stream: None,
+            received: vec![],
+        };
+        transport.open_stream().await?;
+        Ok(transport)
+    }
+
+    async fn open_stream(&mut self) -> Result<&mut TcpStream, ScanError> {
+        let socket = match self.bind_address {
+            SocketAddr::V4(_) => TcpSocket::new_v4()?,
+            SocketAddr::V6(_) => TcpSocket::new_v6()?,
+        };
+        socket.set_reuseaddr(true)?;
+        socket.bind(self.bind_address)?;
+        let mut stream = socket.connect(self.remote_addr).await?;
+        stream.write_all(&TCP_STREAM_PREFIX).await?;
+        self.local_addr = stream.local_addr()?;
+        self.received.clear();
+        Ok(self.stream.insert(stream))
+    }
+
+    ///Liest, bis eine vollständige Ike Nachricht empfangen wurde, und kopiert sie ohne Rahmen nach 'buf'.
+    /// Kann abgebrochen werden, ohne dass empfangene Bytes verloren gehen
+    async fn read_message(&mut self, buf: &mut [u8]) -> Result<usize, ScanError> {
+        loop {
+            if let Some(length) = self.take_message(buf)? {
+                return Ok(length);
+            }
+            let Some(stream) = self.stream.as_mut() else {
+                return Err(ScanError::ConnectionClosed);
+            };
+            match stream.read_buf(&mut self.received).await {
+                Ok(0) => {
+                    self.stream = None;
+                    return Err(ScanError::ConnectionClosed);
+                }
+                Ok(_) => {}
+                Err(error)
+                    if matches!(
+                        error.kind(),
+                        std::io::ErrorKind::ConnectionReset | std::io::ErrorKind::ConnectionAborted
+                    ) =>
+                {
+                    self.stream = None;
+                    return Err(ScanError::ConnectionClosed);
+                }
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+
+    ///Entnimmt die erste vollständige Nachricht aus den empfangenen Bytes.
+    /// Ein Rahmen ohne Non-ESP Marker (z.B. ein ESP Paket) wird entnommen und als ScanError::NonIkeMessage verworfen.
+    /// Ist schon die Längenangabe ungültig, lässt sich der Stream nicht mehr in Rahmen teilen:
+    /// die Verbindung und die empfangenen Bytes werden verworfen und beim nächsten Senden neu aufgebaut
+    fn take_message(&mut self, buf: &mut [u8]) -> Result<Option<usize>, ScanError> {
+        let Some(length_field) = self.received.get(..TCP_LENGTH_FIELD_LENGTH) else {
+            return Ok(None);
+        };
+        let frame_length = usize::from(u16::from_be_bytes([length_field[0], length_field[1]]));
+        if frame_length < TCP_LENGTH_FIELD_LENGTH {
+            self.stream = None;
+            self.received.clear();
+            return Err(ScanError::NonIkeMessage(format!(
+                "invalid TCP frame length {}, connection dropped",
+                frame_length
+            )));
+        }
+        if self.received.len() < frame_length {
+            return Ok(None);
+        }
+        let frame: Vec<u8> = self.received.drain(..frame_length).collect();
+        let message = &frame[TCP_LENGTH_FIELD_LENGTH..];
+        let length = message.len().min(buf.len());
+        buf[..length].copy_from_slice(&message[..length]);
+        strip_non_esp_marker(buf, length).map(Some)
+    }
+}
+
+impl Transport for TcpTransport {
+    ///Sendet die Nachricht mit Längenfeld und Non-ESP Marker.
+    /// Die Länge enthält das Längenfeld selbst (RFC 8229, Abschnitt 3)
+    async fn send(&mut self, message: &[u8]) -> Result<(), ScanError> {
+        let length = TCP_LENGTH_FIELD_LENGTH + NON_ESP_MARKER.len() + message.len();
+        let length = u16::try_from(length).map_err(|_| {
+            ScanError::InvalidInput(format!("message of {} bytes is too long for TCP", length))
+        })?;
+        let mut frame = length.to_be_bytes().to_vec();
+        frame.extend_from_slice(&NON_ESP_MARKER);
+        frame.extend_from_slice(message);
+        let stream = match self.stream.as_mut() {
+            Some(stream) => stream,
+            None => self.open_stream().await?,
+        };
+        stream.write_all(&frame).await?;
+        Ok(())
+    }
+
+    async fn receive(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, ScanError> {
+        match tokio::time::timeout(timeout, self.read_message(buf)).await {
+            Ok(received) => received,
+            Err(_) => Err(ScanError::Timeout),
+        }
+    }
+
+    ///Über TCP werden Nachrichten nicht erneut gesendet (RFC 8229, Abschnitt 6.2)
+    fn is_reliable(&self) -> bool {
+        true
+    }
+
+    fn local_addr(&self) -> Result<SocketAddr, ScanError> {
+        Ok(self.local_addr)
+    }
+
+    fn peer_addr(&self) -> Result<SocketAddr, ScanError> {
+        Ok(self.remote_addr)
+    }
+}
+
+///Ein Ende einer Verbindung im Speicher.
+/// Jede gesendete Nachricht wird unverändert am anderen Ende empfangen
+#[derive(Debug)]
+pub struct MemoryTransport {
+    local_addr: SocketAddr,
+    peer_addr: SocketAddr,
+    sender: mpsc::UnboundedSender<Vec<u8>>,
+    receiver: mpsc::UnboundedReceiver<Vec<u8>>,
+}
+
+impl MemoryTransport {
+    ///Erzeugt zwei verbundene Enden, das erste hat die Adresse 'local_addr',
+    /// das zweite die Adresse 'peer_addr'
+    pub fn pair(local_addr: SocketAddr, peer_addr: SocketAddr) -> (Self, Self) {
+        let (local_sender, peer_receiver) = mpsc::unbounded_channel();
+        let (peer_sender, local_receiver) = mpsc::unbounded_channel();
+        (
+            MemoryTransport {
+                local_addr,
+                peer_addr,
+                sender: local_sender,
+                receiver: local_receiver,
+            },
+            MemoryTransport {
+                local_addr: peer_addr,
+                peer_addr: local_addr,
+                sender: peer_sender,
+                receiver: peer_receiver,
+            },
+        )
+    }
+}
+
+impl Transport for MemoryTransport {
+    async fn send(&mut self, message: &[u8]) -> Result<(), ScanError> {
+        self.sender
+            .send(message.to_vec())
+            .map_err(|_| ScanError::ConnectionClosed)
+    }
+
+    ///Ist das andere Ende geschlossen, wird ScanError::ConnectionClosed zurückgegeben
+    async fn receive(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, ScanError> {
+        match tokio::time::timeout(timeout, self.receiver.recv()).await {
+            Ok(Some(message)) => {
+                let length = message.len().min(buf.len());
+                buf[..length].copy_from_slice(&message[..length]);
+                Ok(length)
+            }
+            Ok(None) => Err(ScanError::ConnectionClosed),
+            Err(_) => Err(ScanError::Timeout),
+        }
+    }
+
+    fn local_addr(&self) -> Result<SocketAddr, ScanError> {
+        Ok(self.local_addr)
+    }
+
+    fn peer_addr(&self) -> Result<SocketAddr, ScanError> {
+        Ok(self.peer_addr)
+    }
+}
+
+///Passt die lokale Adresse an die Adressfamilie des Servers an.
+/// Eine unspezifische Adresse (0.0.0.0 oder ::) wird durch die unspezifische Adresse
+/// der Familie des Servers ersetzt, eine konkrete Adresse der anderen Familie ist ungültig
+pub fn bind_address_for(
+    bind_address: SocketAddr,
+    remote_addr: SocketAddr,
+) -> Result<SocketAddr, ScanError> {
+    if bind_address.is_ipv4() == remote_addr.is_ipv4() {
+        return Ok(bind_address);
+    }
+    if !bind_address.ip().is_unspecified() {
+        return Err(ScanError::InvalidInput(format!(
+            "bind address {} and target {} use different address families",
+            bind_address, remote_addr
+        )));
+    }
+    let unspecified = match remote_addr.ip() {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    Ok(SocketAddr::new(unspecified, bind_address.port()))
+}
+
+///Wartet höchstens 'timeout' auf ein Datagramm des Servers
+async fn receive_datagram(
+    socket: &UdpSocket,
+    buf: &mut [u8],
+    timeout: Duration,
+) -> Result<usize, ScanError> {
+    match tokio::time::timeout(timeout, socket.recv_from(buf)).await {
+        Ok(received) => Ok(received?.0),
+        Err(_) => Err(ScanError::Timeout),
+    }
+}
+
+///Entfernt den Non-ESP Marker vom Anfang der Nachricht und gibt die neue Länge zurück.
+/// Fehlt der Marker, ist die Nachricht keine Ike Nachricht (RFC 3948, Abschnitt 2.2)
+/// und wird vom Empfänger verworfen
+fn strip_non_esp_marker(buf: &mut [u8], length: usize) -> Result<usize, ScanError> {
+    if length < NON_ESP_MARKER.len() || buf[..NON_ESP_MARKER.len()] != NON_ESP_MARKER {
+        return Err(ScanError::NonIkeMessage(format!(
+            "{} bytes without non-ESP marker",
+            length
+        )));
+    }
+    buf.copy_within(NON_ESP_MARKER.len()..length, 0);
+    Ok(length - NON_ESP_MARKER.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///TCP Transport ohne Verbindung, der die Bytes 'received' bereits empfangen hat
+    fn tcp_transport(received: &[u8]) -> TcpTransport {
+        let address = SocketAddr::from(([127, 0, 0, 1], 4500));
+        TcpTransport {
+            bind_address: address,
+            remote_addr: address,
+            local_addr: address,
+            stream: None,
+            received: received.to_vec(),
+        }
+    }
+
+    #[test]
+    fn strips_non_esp_marker() {
+        let mut buf = [0, 0, 0, 0, 1, 2, 3];
+        assert_eq!(strip_non_esp_marker(&mut buf, 7).unwrap(), 3);
+        assert_eq!(buf[..3], [1, 2, 3]);
+    }
+
+    #[test]
+    fn nat_keepalive_is_not_ike() {
+        let mut buf = [0xff];
+        assert!(matches!(
+            strip_non_esp_marker(&mut buf, 1),
+            Err(ScanError::NonIkeMessage(_))
+        ));
+    }
+
+    #[test]
+    fn tcp_frame_without_marker_is_skipped() {
+        //ESP packet with SPI 1, then an IKE message "ike"
+        let mut transport = tcp_transport(&[
+            0, 10, 0, 0, 0, 1, 0, 0, 0, 1, 0, 9, 0, 0, 0, 0, b'i', b'k', b'e',
+        ]);
+        let mut buf = [0u8; 16];
+        assert!(matches!(
+            transport.take_message(&mut buf),
+            Err(ScanError::NonIkeMessage(_))
+        ));
+        assert_eq!(transport.take_message(&mut buf).unwrap(), Some(3));
+        assert_eq!(&buf[..3], b"ike");
+        assert_eq!(transport.take_message(&mut buf).unwrap(), None);
+    }
+
+    #[test]
+    fn tcp_frame_with_invalid_length_drops_received_bytes() {
+        let mut transport = tcp_transport(&[0, 1, 0, 0, 0, 0, 1]);
+        let mut buf = [0u8; 16];
+        assert!(matches!(
+            transport.take_message(&mut buf),
+            Err(ScanError::NonIkeMessage(_))
+        ));
+        assert!(transport.received.is_empty());
+        assert_eq!(transport.take_message(&mut buf).unwrap(), None);
+    }
+
+    #[test]
+    fn incomplete_tcp_frame_waits_for_more_bytes() {
+        let mut transport = tcp_transport(&[0, 9, 0, 0, 0, 0, b'i']);
+        let mut buf = [0u8; 16];
+        assert_eq!(transport.take_message(&mut buf).unwrap(), None);
+        assert_eq!(transport.received.len(), 7);
+    }
+
+    #[test]
+    fn unspecified_bind_address_follows_target_family() {
+        let target = SocketAddr::from((Ipv6Addr::LOCALHOST, 500));
+        let bind_address = SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0));
+        assert_eq!(
+            bind_address_for(bind_address, target).unwrap(),
+            SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0))
+        );
+        let target = SocketAddr::from((Ipv4Addr::LOCALHOST, 500));
+        let bind_address = SocketAddr::from((Ipv6Addr::UNSPECIFIED, 4500));
+        assert_eq!(
+            bind_address_for(bind_address, target).unwrap(),
+            SocketAddr::from((Ipv4Addr::UNSPECIFIED, 4500))
+        );
+    }
+
+    #[test]
+    fn concrete_bind_address_of_other_family_is_rejected() {
+        let target = SocketAddr::from((Ipv6Addr::LOCALHOST, 500));
+        let bind_address = SocketAddr::from((Ipv4Addr::LOCALHOST, 0));
+        assert!(matches!(
+            bind_address_for(bind_address, target),
+            Err(ScanError::InvalidInput(_))
+        ));
+        let bind_address = SocketAddr::from((Ipv6Addr::LOCALHOST, 0));
+        assert_eq!(
+            bind_address_for(bind_address, target).unwrap(),
+            bind_address
+        );
+    }
+}